@@ -1,4 +1,3 @@
-#[allow(dead_code)]
 // Included crates
 //
 // Chrono used for timestamping 
@@ -11,21 +10,30 @@ extern crate serde_json;
 // Use statements
 //
 // Used for timestamping 
-use self::chrono::Utc;
+use self::chrono::{ Utc, DateTime };
 // Standard libraries used for Strings and Vectors
 #[allow(unused_imports)]
 use std::string::String;
 // For hashing necessities
 use hash_util::*;
-// For JSON functionality
-#[allow(unused_imports)]
-use self::serde_json::Error;
+// Reading and writing blocks fails with the crate's error
+use error::Error;
+// Files are written atomically
+use file;
 // Used for writing to output files
 #[allow(unused_imports)]
 use std::fs::{ OpenOptions, File };
 // Uses standard input / output
 #[allow(unused_imports)]
 use std::io::prelude::*;
+// Used for sharing the mining state between threads
+use std::sync::atomic::{ AtomicBool, AtomicU64, Ordering };
+use std::thread;
+// Transactions make up the body of the block
+use transaction::Transaction;
+// Used for computing the Merkle Root of the block's body
+use merkle::Merkle;
+use tree::TreeVersion;
 
 /*
  *
@@ -36,6 +44,84 @@ use std::io::prelude::*;
  *
  */    
 
+/*
+ *
+ * Header Version:
+ *     - The format a block's header is encoded in before it is hashed. Blocks record the
+ *       version of their header so headers hashed with an older format can still be verified.
+ *
+ *     - V0: the index, timestamp, Merkle Root and previous hash are concatenated as strings
+ *           with nothing separating them, the header of the first version of the crate. The
+ *           difficulty and nonce are added once either is set and the MMR root once there is
+ *           one, so blocks written before those fields existed hash as they always did. Different
+ *           headers can hash the same preimage ( difficulty 1 with nonce 23 and difficulty 12
+ *           with nonce 3 ).
+ *     - V1: the header is hashed as its canonical encoding ( see encode_header ), every
+ *           field is fixed width or length prefixed so no two headers encode the same.
+ *
+ */
+#[derive( Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize )]
+pub enum HeaderVersion
+{
+
+    // Concatenated strings
+    V0,
+    // Canonical encoding
+    V1
+
+}
+
+// Header Version impl
+impl HeaderVersion
+{
+
+    // The version new blocks are hashed with
+    pub fn current() -> HeaderVersion
+    {
+
+        HeaderVersion::V1
+        
+    }
+
+    // The version of blocks serialized before header versions were recorded
+    pub fn legacy() -> HeaderVersion
+    {
+
+        HeaderVersion::V0
+        
+    }
+
+    // The byte the version is encoded as
+    pub fn to_byte( &self ) -> u8
+    {
+
+        match *self
+        {
+
+            HeaderVersion::V0 => 0,
+            HeaderVersion::V1 => 1
+                
+        }
+        
+    }
+
+    // The version encoded as the given byte, if there is one
+    pub fn from_byte( byte: u8 ) -> Option<HeaderVersion>
+    {
+
+        match byte
+        {
+
+            0 => Some( HeaderVersion::V0 ),
+            1 => Some( HeaderVersion::V1 ),
+            _ => None
+                
+        }
+        
+    }
+    
+}
+
 // Block struct
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct Block
@@ -44,13 +130,43 @@ pub struct Block
     // The index of the block
     pub index: u64,
     // The block's previous hash 
-    pub previous_hash: String,
-    // The time the block was created 
-    pub timestamp: String,
+    pub previous_hash: Digest,
+    // The time the block was created, written in the display format of a UTC date time
+    #[serde(with = "::timestamp")]
+    pub timestamp: DateTime<Utc>,
     // Merkle Root
-    pub merkle_root: String,
+    pub merkle_root: Digest,
     // The block's hash
-    pub hash: String,
+    pub hash: Digest,
+    // The difficulty the block was mined with, blocks serialized before difficulty was recorded
+    // are read in with a difficulty of 0
+    #[serde(default)]
+    pub difficulty: u32,
+    // The nonce searched for when the block is mined, blocks serialized before mining existed
+    // are read in with a nonce of 0
+    #[serde(default)]
+    pub nonce: u64,
+    // The format of the Merkle Tree the root was built with, blocks serialized before tree
+    // versions existed are read in with the legacy version
+    #[serde(default = "TreeVersion::legacy")]
+    pub tree_version: TreeVersion,
+    // The body of the block ( the transactions committed to by the Merkle Root ), blocks
+    // serialized before the body existed are read in with an empty body
+    #[serde(default)]
+    pub transactions: Vec<Transaction>,
+    // The root of the Merkle Mountain Range over the hashes of the blocks before this one on its
+    // branch, blocks serialized before the MMR existed are read in with no root ( written as an
+    // empty string )
+    #[serde(default, with = "::digest::optional")]
+    pub mmr_root: Option<Digest>,
+    // The hash algorithm the header and the Merkle Tree are hashed with, blocks serialized
+    // before algorithms were recorded are read in with the default algorithm
+    #[serde(default)]
+    pub hash_algorithm: HashAlgorithm,
+    // The format the header is encoded in before it is hashed, blocks serialized before
+    // header versions existed are read in with the legacy version
+    #[serde(default = "HeaderVersion::legacy")]
+    pub header_version: HeaderVersion,
 
 }
 
@@ -58,38 +174,64 @@ pub struct Block
 impl Block
 {   
 
-    // Constructor for a new block
+    // Constructor for a new block, the Merkle Root is computed from the given transactions
+    #[allow(dead_code)]
+    pub fn new( index: u64, transactions: Vec<Transaction> ) -> Block
+    {
+
+        Block::with_hash_algorithm( index, transactions, HashAlgorithm::default() )
+        
+    }
+
+    // Constructor for a new block hashed with the given algorithm
     #[allow(dead_code)]
-    pub fn new( index: u64, merkle_root: String ) -> Block
+    pub fn with_hash_algorithm( index: u64, transactions: Vec<Transaction>, algorithm: HashAlgorithm ) -> Block
     {
 
         // Generate a default block
         let mut block = Block
         {
 
-            index: index,
+            index,
             previous_hash: empty_hash(),
-            timestamp: Utc::now().to_string(),
-            merkle_root: merkle_root,
+            timestamp: Utc::now(),
+            merkle_root: *Merkle::with_algorithm( transactions.clone(), TreeVersion::current(), algorithm ).root_hash(),
             hash: empty_hash(),
+            difficulty: 0,
+            nonce: 0,
+            tree_version: TreeVersion::current(),
+            transactions,
+            mmr_root: None,
+            hash_algorithm: algorithm,
+            header_version: HeaderVersion::current(),
 
         };
         // Generate a hash from all of the fields of this block
         block.hash = generate_header_hash( &block );
         // Return the block
-        return block;
+        block
         
     }
 
     // Constructor for the origin block (first block in the chain with hash 0)
     #[allow(dead_code)]
     pub fn origin() -> Block
+    {
+
+        Block::origin_with_hash_algorithm( HashAlgorithm::default() )
+
+    }
+
+    // Constructor for the origin block of a chain hashed with the given algorithm, the origin's
+    // hash is the default empty hash whatever the algorithm so it can always be found
+    #[allow(dead_code)]
+    pub fn origin_with_hash_algorithm( algorithm: HashAlgorithm ) -> Block
     {
         
-        // Create a new block and make the hash equal the empty hash
-        let mut block : Block = Block::new( 0, empty_hash() );
+        // Create a new block with an empty body and make the hash equal the empty hash
+        let mut block : Block = Block::with_hash_algorithm( 0, Vec::new(), algorithm );
         block.hash = empty_hash();
-        return block;
+        block
 
     }
 
@@ -107,20 +249,16 @@ impl Block
         
     }
 
-    // Writes the serialization of a block to a specified output file
+    // Writes the serialization of a block to a specified output file, a file already there is
+    // replaced whole or left as it was
     #[allow(dead_code)]
     pub fn write_to( &self, file_name: &str ) -> Result< (), Error >
     {
 
         // Serializes the json
         let json_block = serde_json::to_string( &self )?;
-        // Creates the new file with the given name
-        let mut file = OpenOptions::new().write( true ).create( true ).open( file_name ).unwrap();
-        // Appends the json to the file
-        #[allow(unused_variables)]
-        let temp = file.write_all( json_block.as_ref() );
-        // Returns the result or Error
-        Ok( () )
+        // Replaces the file with the json atomically
+        file::write_atomically( file_name, json_block.as_ref() )
         
     }
 
@@ -130,14 +268,13 @@ impl Block
     {
 
         // Opens the file with the specified name
-        let mut file = OpenOptions::new().read( true ).open( file_name ).unwrap();
+        let mut file = OpenOptions::new().read( true ).open( file_name )?;
         // Creates an emtpy string
         let mut json = String::new();
         // Reads the file as a string
-        #[allow(unused_variables)]
-        let temp = file.read_to_string( &mut json );
+        file.read_to_string( &mut json )?;
         // Returns the String or Error
-        Ok( ( json ) )
+        Ok( json )
         
     }
 
@@ -147,9 +284,9 @@ impl Block
     {
 
         // Constructs the block
-        let block = serde_json::from_str( &Block::read_json( file_name )? );
+        let block = serde_json::from_str( &Block::read_json( file_name )? )?;
         // Returns the block or Error
-        Ok( block.unwrap() )
+        Ok( block )
         
     }
 
@@ -162,18 +299,38 @@ impl Block
         
     }
 
+    // Sets the index and rehashes the header
+    #[allow(dead_code)]
+    pub fn set_index( &mut self, index: u64 )
+    {
+
+        self.index = index;
+        self.hash = generate_header_hash( self );
+        
+    }
+
     // Returns the timestamp of the block 
     #[allow(dead_code)]
-    pub fn timestamp( &self ) -> &String
+    pub fn timestamp( &self ) -> &DateTime<Utc>
     {
         
         &self.timestamp
         
     }
 
+    // Sets the timestamp and rehashes the header
+    #[allow(dead_code)]
+    pub fn set_timestamp( &mut self, timestamp: DateTime<Utc> )
+    {
+
+        self.timestamp = timestamp;
+        self.hash = generate_header_hash( self );
+        
+    }
+
     // Returns the hash of the previous block 
     #[allow(dead_code)]
-    pub fn previous_hash( &self ) -> &String
+    pub fn previous_hash( &self ) -> &Digest
     {
 
         &self.previous_hash
@@ -182,7 +339,7 @@ impl Block
 
     // Returns the Merkle Root of the block 
     #[allow(dead_code)]
-    pub fn merkle_root( &self ) -> &String
+    pub fn merkle_root( &self ) -> &Digest
     {
 
         &self.merkle_root
@@ -191,23 +348,338 @@ impl Block
 
     // Returns the hash of the block 
     #[allow(dead_code)]
-    pub fn hash( &self ) -> &String
+    pub fn hash( &self ) -> &Digest
     {
 
         &self.hash
         
     }
 
+    // Returns the difficulty recorded in the block's header
+    #[allow(dead_code)]
+    pub fn difficulty( &self ) -> &u32
+    {
+
+        &self.difficulty
+        
+    }
+
+    // Returns the nonce of the block 
+    #[allow(dead_code)]
+    pub fn nonce( &self ) -> &u64
+    {
+
+        &self.nonce
+        
+    }
+
+    // Sets the nonce and recalculates the header hash
+    #[allow(dead_code)]
+    pub fn set_nonce( &mut self, nonce: u64 )
+    {
+
+        self.nonce = nonce;
+        self.hash = generate_header_hash( self );
+        
+    }
+
+    // Determines whether the block's hash meets the given difficulty
+    #[allow(dead_code)]
+    pub fn meets_difficulty( &self, difficulty: u32 ) -> bool
+    {
+
+        self.hash.meets_difficulty( difficulty )
+        
+    }
+
+    // Mines the block on every available core until a nonce is found that makes the header hash
+    // meet the difficulty, the difficulty is recorded in the header
+    #[allow(dead_code)]
+    pub fn mine( &mut self, difficulty: u32 )
+    {
+
+        // Nothing ever cancels this search 
+        let cancel = AtomicBool::new( false );
+        self.mine_until( difficulty, &cancel );
+        
+    }
+
+    /* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *
+     * Mines the block until a nonce meeting the difficulty is found or the search is          *
+     * cancelled, returns true if the block was mined and false if it was cancelled. The       *
+     * difficulty is recorded in the header of a mined block, a cancelled block is left        *
+     * unchanged.                                                                              *
+     *                                                                                         *
+     * The search is split over one thread per core, thread i tries the nonces i, i + n,       *
+     * i + 2n ... where n is the number of threads. The first thread to find a nonce records   *
+     * it and raises the found flag, which stops the rest of the threads. Setting the cancel   *
+     * flag from another thread stops every thread the same way.                               *
+     * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+    #[allow(dead_code)]
+    pub fn mine_until( &mut self, difficulty: u32, cancel: &AtomicBool ) -> bool
+    {
+
+        // The number of threads to search with
+        let threads = thread::available_parallelism().map( | count | count.get() ).unwrap_or( 1 ) as u64;
+        // Raised once a nonce has been found 
+        let found = AtomicBool::new( false );
+        // The smallest nonce found
+        let winner = AtomicU64::new( u64::MAX );
+        // The header hashed by the threads records the difficulty being mined for
+        let previous_difficulty = self.difficulty;
+        self.difficulty = difficulty;
+        {
+            
+            // The block is only read while the threads search
+            let block: &Block = self;
+            thread::scope( | scope |
+            {
+
+                for start in 0 .. threads
+                {
+
+                    let ( found, winner ) = ( &found, &winner );
+                    scope.spawn( move ||
+                    {
+
+                        let mut nonce = start;
+                        while !found.load( Ordering::Relaxed ) && !cancel.load( Ordering::Relaxed )
+                        {
+
+                            if generate_header_hash_with_nonce( block, nonce ).meets_difficulty( difficulty )
+                            {
+
+                                // Records the nonce and stops the other threads
+                                winner.fetch_min( nonce, Ordering::SeqCst );
+                                found.store( true, Ordering::SeqCst );
+                                break;
+                                
+                            }
+                            nonce = match nonce.checked_add( threads )
+                            {
+
+                                Some( next ) => next,
+                                None => break
+                                    
+                            };
+                            
+                        }
+                        
+                    } );
+                    
+                }
+                
+            } );
+            
+        }
+        // Sets the nonce if one was found 
+        if found.load( Ordering::SeqCst )
+        {
+
+            self.set_nonce( winner.load( Ordering::SeqCst ) );
+            true
+            
+        }
+        else
+        {
+
+            // Restores the header ( the hash was never recalculated )
+            self.difficulty = previous_difficulty;
+            false
+            
+        }
+        
+    }
+
+    // Returns the transactions in the body of the block
+    #[allow(dead_code)]
+    pub fn transactions( &self ) -> &Vec<Transaction>
+    {
+
+        &self.transactions
+        
+    }
+
+    // Returns the format of the block's Merkle Tree
+    #[allow(dead_code)]
+    pub fn tree_version( &self ) -> TreeVersion
+    {
+
+        self.tree_version
+        
+    }
+
+    // Returns the hash algorithm the block is hashed with
+    #[allow(dead_code)]
+    pub fn hash_algorithm( &self ) -> HashAlgorithm
+    {
+
+        self.hash_algorithm
+        
+    }
+
+    // Sets the hash algorithm, recomputing the Merkle Root and the header hash with it
+    #[allow(dead_code)]
+    pub fn set_hash_algorithm( &mut self, algorithm: HashAlgorithm )
+    {
+
+        if self.hash_algorithm != algorithm
+        {
+
+            self.hash_algorithm = algorithm;
+            self.merkle_root = *self.merkle().root_hash();
+            self.hash = generate_header_hash( self );
+            
+        }
+        
+    }
+
+    // Returns the format the block's header is encoded in
+    #[allow(dead_code)]
+    pub fn header_version( &self ) -> HeaderVersion
+    {
+
+        self.header_version
+        
+    }
+
+    // Sets the header version and rehashes the header
+    #[allow(dead_code)]
+    pub fn set_header_version( &mut self, version: HeaderVersion )
+    {
+
+        self.header_version = version;
+        self.hash = generate_header_hash( self );
+        
+    }
+
+    // Returns the canonical encoding of the block's header
+    #[allow(dead_code)]
+    pub fn encode_header( &self ) -> Vec<u8>
+    {
+
+        self.encode_header_with_nonce( self.nonce )
+        
+    }
+
+    /*
+     *
+     * Canonical header encoding:
+     *     - The fields of the header in a fixed order, each either fixed width or prefixed with
+     *       its length, so the encoding of one header can never be the encoding of another.
+     *       The header is encoded with the given nonce so candidate nonces can be tried while
+     *       mining without modifying the block.
+     *
+     *     - header version, hash algorithm, tree version: 1 byte each
+     *     - index: 8 bytes, big endian
+     *     - timestamp: a 4 byte big endian length followed by the UTF-8 bytes of its display
+     *       format
+     *     - Merkle Root, previous hash: 32 bytes each
+     *     - difficulty: 4 bytes, big endian
+     *     - nonce: 8 bytes, big endian
+     *     - MMR root: 0x00 if there is none, otherwise 0x01 followed by its 32 bytes
+     *
+     */
+    #[allow(dead_code)]
+    pub fn encode_header_with_nonce( &self, nonce: u64 ) -> Vec<u8>
+    {
+
+        let mut bytes = vec![ self.header_version.to_byte(), self.hash_algorithm.to_byte(), self.tree_version.to_byte() ];
+        bytes.extend_from_slice( &self.index.to_be_bytes() );
+        let timestamp = self.timestamp.to_string();
+        bytes.extend_from_slice( &( timestamp.len() as u32 ).to_be_bytes() );
+        bytes.extend_from_slice( timestamp.as_bytes() );
+        bytes.extend_from_slice( self.merkle_root.as_bytes() );
+        bytes.extend_from_slice( self.previous_hash.as_bytes() );
+        bytes.extend_from_slice( &self.difficulty.to_be_bytes() );
+        bytes.extend_from_slice( &nonce.to_be_bytes() );
+        match self.mmr_root
+        {
+
+            Some( ref root ) =>
+            {
+
+                bytes.push( 1 );
+                bytes.extend_from_slice( root.as_bytes() );
+                
+            },
+            None => bytes.push( 0 )
+                
+        }
+        bytes
+        
+    }
+
+    // Builds the Merkle Tree of the block's body with the block's tree version and algorithm
+    #[allow(dead_code)]
+    pub fn merkle( &self ) -> Merkle
+    {
+
+        Merkle::with_algorithm( self.transactions.clone(), self.tree_version, self.hash_algorithm )
+        
+    }
+
+    // Recomputes the Merkle Root from the body and checks it against the stored root, used
+    // to validate a block after it has been read in
+    #[allow(dead_code)]
+    pub fn verify_merkle_root( &self ) -> bool
+    {
+
+        *self.merkle().root_hash() == self.merkle_root
+        
+    }
+
+    // Determines whether the block was written before blocks carried their body ( it has no
+    // body and the legacy tree version ), its Merkle Root commits to transactions that were
+    // never stored so it can't be recomputed
+    #[allow(dead_code)]
+    pub fn predates_body( &self ) -> bool
+    {
+
+        self.transactions.is_empty() && self.tree_version == TreeVersion::legacy()
+        
+    }
+
+    // Determines whether the block's Merkle Tree is ambiguous ( two sibling nodes have the same
+    // hash in a tree version that pairs unpaired nodes with themselves ), the same root could
+    // then be claimed by a block with a different body
+    #[allow(dead_code)]
+    pub fn has_ambiguous_merkle_root( &self ) -> bool
+    {
+
+        self.merkle().is_ambiguous()
+        
+    }
+
+
+    // Get the MMR root, None for a block that doesn't commit to the blocks before it
+    #[allow(dead_code)]
+    pub fn mmr_root( &self ) -> Option<&Digest>
+    {
+
+        self.mmr_root.as_ref()
+        
+    }
+
+    // Sets the MMR root and rehashes the header
+    #[allow(dead_code)]
+    pub fn set_mmr_root( &mut self, root: Option<Digest> )
+    {
+
+        self.mmr_root = root;
+        self.hash = generate_header_hash( self );
+        
+    }
 
     // Sets the previous hash 
     #[allow(dead_code)]
-    pub fn set_previous_hash( &mut self, hash: &String )
+    pub fn set_previous_hash( &mut self, hash: &Digest )
     {
 
         // Clones the current hash and sets it to the previous hash 
-        self.previous_hash = hash.clone();
+        self.previous_hash = *hash;
         // Recalculate the header of the block with the new previous hash
-        self.hash = generate_header_hash( &self );
+        self.hash = generate_header_hash( self );
         
     }    
     
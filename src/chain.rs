@@ -6,23 +6,40 @@ extern crate serde_json;
 
 // Use statements
 //
-// The underlying structure will be a hash map
+// The indexes of the chain are kept in hash maps
 use std::collections::HashMap;
 // Output of the chain data will be done using JSON
 #[allow(unused_imports)]
 use std::fs::{ OpenOptions, File };
 // Prelude
 use std::io::prelude::*;
-// Standard error
-use std::io::Error;
+// Reading and writing chains fails with the crate's error
+use error::Error;
+// Files are written atomically
+use file;
 // Hashes
 use std::string::String;
 // Blocks for the blockchain
-use block::Block;
+use block::{ Block, HeaderVersion };
 // Hash utilities
 use hash_util::*;
 // Everything else
 use std::*;
+// Used for displaying validation errors
+use std::fmt;
+// Heights are converted to positions in the height index
+use std::convert::TryFrom;
+// Headers commit to the blocks before them with a Merkle Mountain Range
+use mmr::{ Mmr, MmrProof };
+// Blocks are timestamped with UTC date times
+use chrono::{ DateTime, Duration, Utc };
+// Used for the current time
+use timestamp;
+// Chains keep their blocks in storage, in memory unless they're moved into other storage
+use storage::{ Storage, MemoryStorage };
+// Chains are serialized by hand so their blocks can be read from storage as they're written out
+use self::serde::ser::{ Serialize, Serializer, SerializeMap, SerializeStruct };
+use self::serde::de::{ Deserialize, Deserializer };
 
 /*
  *
@@ -30,145 +47,1939 @@ use std::*;
  *     - This file contains the functionality for constructing and maintaining chains   
  *       ( allows the creation of a blockchain )
  *
+ *     - A chain's blocks are kept in storage ( see storage.rs ) along with the transactions in
+ *       them and their Merkle Trees. The chain itself only holds an index of the blocks
+ *       connected to the origin and the best branch's MMR, blocks are read from storage when
+ *       they're needed.
+ *
+ */
+
+// The number of blocks before a block whose timestamps its median time past is taken over
+pub const MEDIAN_TIME_SPAN: usize = 11;
+// How far ahead of the current time a block's timestamp may be, in seconds
+pub const MAX_FUTURE_DRIFT: i64 = 2 * 60 * 60;
+
+/*
+ *
+ * Validation Error:
+ *     - Describes the first block that failed validation and why it failed
+ *
+ */
+#[derive( Debug, Clone, PartialEq )]
+pub struct ValidationError
+{
+
+    // The position of the offending block counting from the origin ( height 0 )
+    pub height: u64,
+    // The hash of the offending block ( or the hash that failed to resolve to a block )
+    pub hash: Digest,
+    // What was wrong with the block
+    pub kind: ValidationErrorKind
+
+}
+
+// The reasons a chain can fail validation
+#[derive( Debug, Clone, PartialEq )]
+pub enum ValidationErrorKind
+{
+
+    // There is no block stored under the origin key
+    MissingOrigin,
+    // The origin block isn't the expected origin ( index 0 with the empty hash )
+    InvalidOrigin,
+    // The tail hash doesn't resolve to a block connected to the origin
+    MissingBlock,
+    // The block is already stored in the chain
+    DuplicateBlock,
+    // The block's previous hash doesn't resolve to a block connected to the origin
+    UnknownParent,
+    // Another branch has more cumulative work than the branch ending at the tail
+    NotBestTip,
+    // The block's previous hash doesn't match the hash of the block before it
+    BrokenLink{ expected: Digest, found: Digest },
+    // The block is hashed with a different algorithm than the chain
+    UnexpectedHashAlgorithm{ expected: HashAlgorithm, found: HashAlgorithm },
+    // The block's header is encoded in an older format than the header of the block before it,
+    // so a branch can't go back to hashing the ambiguous legacy header
+    HeaderVersionDecreasing{ previous: HeaderVersion, found: HeaderVersion },
+    // The stored header hash doesn't match the recomputed header hash
+    InvalidHash{ expected: Digest },
+    // The stored Merkle Root doesn't match the root recomputed from the block's body
+    InvalidMerkleRoot,
+    // The block's Merkle Tree duplicates sibling nodes, so its root is shared with a different
+    // body ( CVE-2012-2459 )
+    AmbiguousMerkleRoot,
+    // The block's index isn't greater than the index of the block before it
+    IndexNotIncreasing{ previous: u64, found: u64 },
+    // The block's timestamp is earlier than the median time past of the blocks before it
+    TimestampBeforeMedian{ median: DateTime<Utc> },
+    // The block's timestamp is further ahead of the current time than the allowed drift
+    TimestampTooFarAhead{ limit: DateTime<Utc> },
+    // The difficulty recorded in the block isn't the difficulty the chain requires at its height
+    UnexpectedDifficulty{ expected: u32, found: u32 },
+    // The block's hash doesn't meet the difficulty of the chain
+    InsufficientWork{ difficulty: u32 },
+    // The MMR root in the block isn't the root of the blocks before it on its branch ( or isn't
+    // missing for a block from before the chain's MMR started )
+    UnexpectedMmrRoot{ expected: Option<Digest>, found: Option<Digest> },
+    // Blocks are stored in the chain that can't be reached from the origin
+    UnreachableBlocks{ count: usize }
+
+}
+
+// Display impl for validation errors
+impl fmt::Display for ValidationError
+{
+
+    fn fmt( &self, f: &mut fmt::Formatter ) -> fmt::Result
+    {
+
+        write!( f, "block {} at height {} is invalid: {:?}", self.hash, self.height, self.kind )
+        
+    }
+    
+}
+
+// Validation errors can be used as standard errors
+impl error::Error for ValidationError {}
+
+/*
+ *
+ * Reorg:
+ *     - Describes how the tip of the best branch moved when a block was accepted. Blocks
+ *       are disconnected from the old tip back to the fork point and connected from the
+ *       fork point up to the new tip, extending the tip connects just the new block and
+ *       a block added to a side branch changes nothing.
+ *
+ */
+#[derive( Debug, Clone, PartialEq, Default )]
+pub struct Reorg
+{
+
+    // The hashes of the blocks removed from the best branch, newest first
+    pub disconnected: Vec<Digest>,
+    // The hashes of the blocks added to the best branch, oldest first
+    pub connected: Vec<Digest>
+
+}
+
+/*
+ *
+ * Blocks:
+ *     - Iterates over blocks on the best branch in height order, from the origin towards the
+ *       tail. Reversing it walks from the tail back towards the origin. Made by Chain::iter
+ *       and Chain::range.
+ *
  */
+#[derive( Debug )]
+pub struct Blocks< 'a, S: 'a + Storage = MemoryStorage >
+{
+
+    // The storage the blocks are read from
+    storage: &'a S,
+    // The hashes of the blocks still to be visited, lowest height first
+    hashes: slice::Iter< 'a, Digest >
+
+}
+
+// Clone impl for blocks, the storage is shared
+impl<'a, S: Storage> Clone for Blocks<'a, S>
+{
+
+    fn clone( &self ) -> Self
+    {
+
+        Blocks{ storage: self.storage, hashes: self.hashes.clone() }
+
+    }
+
+}
+
+// Iterator impl for blocks, oldest first
+impl<'a, S: Storage> Iterator for Blocks<'a, S>
+{
+
+    type Item = Result< Block, Error >;
+
+    fn next( &mut self ) -> Option< Result< Block, Error > >
+    {
+
+        let storage = self.storage;
+        self.hashes.next().map( | hash | stored_block( storage, hash ) )
+
+    }
+
+    fn size_hint( &self ) -> ( usize, Option<usize> )
+    {
+
+        self.hashes.size_hint()
+
+    }
+
+}
+
+// Reverse iteration over blocks, newest first
+impl<'a, S: Storage> DoubleEndedIterator for Blocks<'a, S>
+{
+
+    fn next_back( &mut self ) -> Option< Result< Block, Error > >
+    {
+
+        let storage = self.storage;
+        self.hashes.next_back().map( | hash | stored_block( storage, hash ) )
+
+    }
+
+}
+
+// The number of blocks left is known
+impl<'a, S: Storage> ExactSizeIterator for Blocks<'a, S> {}
+
+// Reads a block the chain's index holds from storage, a block that isn't there is an integrity
+// error
+fn stored_block< S: Storage >( storage: &S, hash: &Digest ) -> Result< Block, Error >
+{
+
+    storage.get_block( hash )?.ok_or_else( || Error::Integrity( format!( "block {} is indexed but isn't stored", hash ) ) )
+
+}
+
+// Stores a block along with the transactions in it ( by id, with the block's hash ) and its
+// Merkle Tree
+fn store_block< S: Storage >( storage: &mut S, block: &Block ) -> Result< (), Error >
+{
+
+    storage.put_block( block )?;
+    for transaction in block.transactions()
+    {
+
+        storage.put_transaction( &transaction.id_with( block.hash_algorithm() ), block.hash(), transaction )?;
+
+    }
+    storage.put_merkle( &block.merkle() )
+
+}
+
+// Writes a block to storage like store_block along with its index entry and the chain's
+// settings when they're given, and flushes the storage
+fn write_block< S: Storage >( storage: &mut S, block: &Block, meta: &Meta, nodes: &[Digest], settings: Option<&Settings> ) -> Result< (), Error >
+{
+
+    store_block( storage, block )?;
+    put_meta( storage, block.hash(), meta, nodes )?;
+    if let Some( settings ) = settings
+    {
+
+        put_settings( storage, settings )?;
+
+    }
+    storage.flush()
+
+}
+
+// Stores the index entry of a block along with the MMR nodes appending the block to its branch's
+// MMR adds, the best branch's MMR is rebuilt from them when a chain is opened
+fn put_meta< S: Storage >( storage: &mut S, hash: &Digest, meta: &Meta, nodes: &[Digest] ) -> Result< (), Error >
+{
+
+    storage.put_meta( hash, &serde_json::to_string( &( meta, nodes ) )? )
+
+}
+
+// Stores the index entry of every block connected to the origin. Every branch is walked out from
+// the origin with the MMR of the path to the block being stored, so each block's MMR nodes are
+// those of its own branch
+fn store_index< S: Storage >( storage: &mut S, algorithm: HashAlgorithm, meta: &HashMap< Digest, Meta > ) -> Result< (), Error >
+{
+
+    let children = children( meta );
+    let mut mmr = Mmr::with_algorithm( algorithm );
+    let mut stack = if meta.contains_key( &empty_hash() ) { vec![ empty_hash() ] } else { Vec::new() };
+    while let Some( hash ) = stack.pop()
+    {
+
+        let entry = &meta[ &hash ];
+        mmr.truncate( entry.height as usize );
+        let nodes = mmr.nodes_after( entry.height as usize, &hash );
+        mmr.append_nodes( &nodes );
+        put_meta( storage, &hash, entry, &nodes )?;
+        if let Some( extending ) = children.get( &hash )
+        {
+
+            stack.extend( extending.iter().cloned() );
+
+        }
+
+    }
+    Ok( () )
+
+}
+
+// Maps each indexed block's hash to the indexed blocks that extend it, sorted by hash so walks
+// are repeatable ( the origin isn't counted as its own child )
+fn children( meta: &HashMap< Digest, Meta > ) -> HashMap< Digest, Vec<Digest> >
+{
+
+    let mut children: HashMap< Digest, Vec<Digest> > = HashMap::new();
+    for ( hash, entry ) in meta
+    {
+
+        if *hash != empty_hash()
+        {
+
+            children.entry( entry.previous ).or_default().push( *hash );
+
+        }
+
+    }
+    for hashes in children.values_mut()
+    {
+
+        hashes.sort();
+
+    }
+    children
+
+}
+
+// The index entry of a block connected to the origin, what the blocks after it are checked
+// against without reading it from storage. It's stored alongside the block so a chain is opened
+// without reading its blocks
+#[derive( Debug, Clone, Copy, PartialEq, Serialize, Deserialize )]
+struct Meta
+{
+
+    // The hash of the block before it ( the origin's is its own )
+    previous: Digest,
+    // The number of blocks between the block and the origin
+    height: u64,
+    // The total work of the branch ending at the block
+    work: u128,
+    // The block's timestamp and recorded difficulty
+    #[serde(with = "::timestamp")]
+    timestamp: DateTime<Utc>,
+    difficulty: u32
+
+}
+
+// Meta impl
+impl Meta
+{
+
+    // The index entry of a block, its height and work are filled in by after once the entry of
+    // the block before it is known
+    fn of( block: &Block ) -> Meta
+    {
+
+        Meta{ previous: *block.previous_hash(), height: 0, work: 0, timestamp: *block.timestamp(), difficulty: *block.difficulty() }
+
+    }
+
+    // The index entry of the origin block
+    fn origin( origin: &Block ) -> Meta
+    {
+
+        Meta{ previous: empty_hash(), ..Meta::of( origin ) }
+
+    }
+
+    // The entry with the height and work it has after the block with the given entry
+    fn after( self, parent: &Meta ) -> Meta
+    {
+
+        Meta{ height: parent.height + 1, work: parent.work.saturating_add( block_work( self.difficulty ) ), ..self }
+
+    }
+
+}
+
+// The work a block mined with the given difficulty represents ( 2^difficulty hashes )
+fn block_work( difficulty: u32 ) -> u128
+{
+
+    1u128.checked_shl( difficulty ).unwrap_or( u128::MAX )
+    
+}
+
+// The median time past of a block extending the given ancestors, the median of the timestamps
+// of the last MEDIAN_TIME_SPAN of them ( or of every block on shorter branches ). A block can't
+// be timestamped before it, so a miner can't wind the clock back while still letting
+// timestamps drift out of order a little.
+fn median_time_past( ancestors: &[&Meta] ) -> DateTime<Utc>
+{
+
+    let start = ancestors.len().saturating_sub( MEDIAN_TIME_SPAN );
+    let mut times: Vec< DateTime<Utc> > = ancestors[ start .. ].iter().map( | meta | meta.timestamp ).collect();
+    times.sort();
+    times[ times.len() / 2 ]
+    
+}
+
+// The chain struct, its blocks are kept in the storage S
+#[allow(dead_code)]
+#[derive( Debug )]
+pub struct Chain< S: Storage = MemoryStorage >
+{
+
+    // Unique ID of the node hosting this chain
+    uid: Digest,
+    // Every block on every branch keyed by its own hash, along with the transactions in them
+    // and their Merkle Trees
+    storage: S,
+    // The tip of the branch with the most work
+    tail_hash: Digest,
+    // The number of leading zero bits the first blocks' hashes need, later blocks use the
+    // retargeted difficulty
+    difficulty: u32,
+    // The target time between blocks in milliseconds
+    target_spacing: u64,
+    // The number of blocks between retargets, 0 turns retargeting off
+    retarget_window: u64,
+    // The height from which blocks commit to the MMR of the blocks before them
+    mmr_height: u64,
+    // The hash algorithm every block of the chain is hashed with
+    hash_algorithm: HashAlgorithm,
+    // The index entry of every block connected to the origin, this is rebuilt from storage
+    // when a chain is opened or read in
+    meta: HashMap< Digest, Meta >,
+    // The MMR of the blocks on the best branch from the origin to the tail, this is rebuilt
+    // when a chain is opened or read in
+    mmr: Mmr,
+    // The hashes of the blocks on the best branch indexed by height, from the origin to the
+    // tail, this is rebuilt when a chain is opened or read in
+    best: Vec<Digest>,
+
+}
+
+// The name of the record a chain's settings are saved under in storage
+const SETTINGS_RECORD: &str = "chain";
+
+// The settings of a chain, saved to storage alongside its blocks and written out with them as
+// json
+#[derive( Serialize, Deserialize )]
+struct Settings
+{
+
+    // The fields of the chain with the same names, see Chain
+    uid: Digest,
+    tail_hash: Digest,
+    // Chains serialized before proof of work existed are read in with no difficulty
+    #[serde(default)]
+    difficulty: u32,
+    #[serde(default)]
+    target_spacing: u64,
+    #[serde(default)]
+    retarget_window: u64,
+    // Chains serialized before the MMR existed start at the first height after their blocks
+    #[serde(default = "legacy_mmr_height")]
+    mmr_height: u64,
+    // Chains serialized before algorithms were recorded are read in with the default
+    // algorithm ( SHA3-256 )
+    #[serde(default)]
+    hash_algorithm: HashAlgorithm
+
+}
+
+// Settings impl
+impl Settings
+{
+
+    // The settings of a new chain hashed with the given algorithm, without proof of work
+    fn new( hash_algorithm: HashAlgorithm ) -> Settings
+    {
+
+        Settings
+        {
+
+            uid: empty_hash(),
+            tail_hash: empty_hash(),
+            difficulty: 0,
+            target_spacing: 0,
+            retarget_window: 0,
+            mmr_height: 1,
+            hash_algorithm
+
+        }
+
+    }
+
+}
+
+// Stores the settings of a chain
+fn put_settings< S: Storage >( storage: &mut S, settings: &Settings ) -> Result< (), Error >
+{
+
+    storage.put_record( SETTINGS_RECORD, &serde_json::to_string( settings )? )
+
+}
+
+// The MMR height of chains serialized before the MMR existed, replaced once they are read in
+fn legacy_mmr_height() -> u64
+{
+
+    u64::MAX
+
+}
+
+// A chain as it is read from json, its blocks are moved into memory storage
+#[derive( Deserialize )]
+struct ChainRecord
+{
+
+    // Every block on every branch is mapped to by its own hash ( the keys are rebuilt when a
+    // chain is read in, so files keying the origin by "0" still read in )
+    #[serde(deserialize_with = "read_blocks")]
+    chain: HashMap< Digest, Block >,
+    // The rest of the chain's fields
+    #[serde(flatten)]
+    settings: Settings
+
+}
+
+// Reads the blocks of a chain in keyed by their own hashes, whatever they were keyed by
+// ( chains written before branches were kept keyed blocks by their previous hash )
+fn read_blocks< 'de, D: Deserializer<'de> >( deserializer: D ) -> Result< HashMap< Digest, Block >, D::Error >
+{
+
+    let blocks: HashMap< String, Block > = Deserialize::deserialize( deserializer )?;
+    Ok( blocks.into_values().map( | block | ( *block.hash(), block ) ).collect() )
+
+}
+
+// Writes out every block in storage mapped to by its hash
+struct BlockMap< 'a, S: 'a >( &'a S );
+
+// Serialize impl for the blocks in storage, they're read one at a time as they're written
+impl<'a, S: Storage> Serialize for BlockMap<'a, S>
+{
+
+    fn serialize< T: Serializer >( &self, serializer: T ) -> Result< T::Ok, T::Error >
+    {
+
+        let hashes = self.0.block_hashes().map_err( serde::ser::Error::custom )?;
+        let mut map = serializer.serialize_map( Some( hashes.len() ) )?;
+        for hash in &hashes
+        {
+
+            map.serialize_entry( hash, &stored_block( self.0, hash ).map_err( serde::ser::Error::custom )? )?;
+
+        }
+        map.end()
+
+    }
+
+}
+
+// Serialize impl for chains, the fields are written in the order chains have always been
+// written in with the blocks under "chain"
+impl<S: Storage> Serialize for Chain<S>
+{
+
+    fn serialize< T: Serializer >( &self, serializer: T ) -> Result< T::Ok, T::Error >
+    {
+
+        let mut chain = serializer.serialize_struct( "Chain", 8 )?;
+        chain.serialize_field( "uid", &self.uid )?;
+        chain.serialize_field( "chain", &BlockMap( &self.storage ) )?;
+        chain.serialize_field( "tail_hash", &self.tail_hash )?;
+        chain.serialize_field( "difficulty", &self.difficulty )?;
+        chain.serialize_field( "target_spacing", &self.target_spacing )?;
+        chain.serialize_field( "retarget_window", &self.retarget_window )?;
+        chain.serialize_field( "mmr_height", &self.mmr_height )?;
+        chain.serialize_field( "hash_algorithm", &self.hash_algorithm )?;
+        chain.end()
+
+    }
+
+}
+
+// Deserialize impl for chains, a chain is read into memory storage and indexed
+impl<'de> Deserialize<'de> for Chain
+{
+
+    fn deserialize< D: Deserializer<'de> >( deserializer: D ) -> Result< Chain, D::Error >
+    {
+
+        let record = ChainRecord::deserialize( deserializer )?;
+        Chain::from_record( record ).map_err( serde::de::Error::custom )
+
+    }
+
+}
+
+// Default impl for Chain
+impl Default for Chain
+{
+
+    // A new chain holding just its origin
+    fn default() -> Chain
+    {
+
+        Chain::new()
+
+    }
+
+}
+
+// Impl for chains kept in memory
+impl Chain
+{
+
+    // Constructor for a new chain
+    #[allow(dead_code)]
+    pub fn new() -> Chain
+    {
+
+        Chain::with_hash_algorithm( HashAlgorithm::default() )
+        
+    }
+
+    // Constructor for a new chain whose blocks are hashed with the given algorithm
+    #[allow(dead_code)]
+    pub fn with_hash_algorithm( algorithm: HashAlgorithm ) -> Chain
+    {
+
+        Chain::in_memory( Settings::new( algorithm ) )
+        
+    }
+
+    // Constructor for a new chain that requires proof of work with the given difficulty
+    #[allow(dead_code)]
+    pub fn with_difficulty( difficulty: u32 ) -> Chain
+    {
+
+        let mut settings = Settings::new( HashAlgorithm::default() );
+        settings.difficulty = difficulty;
+        Chain::in_memory( settings )
+        
+    }
+
+    // Constructor for a new chain whose difficulty is retargeted every window blocks so that
+    // blocks are produced about target_spacing milliseconds apart
+    #[allow(dead_code)]
+    pub fn with_retarget( difficulty: u32, target_spacing: u64, retarget_window: u64 ) -> Chain
+    {
+
+        let mut settings = Settings::new( HashAlgorithm::default() );
+        settings.difficulty = difficulty;
+        settings.target_spacing = target_spacing;
+        settings.retarget_window = retarget_window;
+        Chain::in_memory( settings )
+
+    }
+
+    // Starts a new chain with the given settings in memory storage
+    fn in_memory( settings: Settings ) -> Chain
+    {
+
+        Chain::start( MemoryStorage::new(), settings ).expect( "Memory storage can't fail" )
+
+    }
+
+    // Moves a chain read from json into memory storage and indexes it, chains from before the
+    // MMR start committing to it with the next block
+    fn from_record( record: ChainRecord ) -> Result< Chain, Error >
+    {
+
+        let mut storage = MemoryStorage::new();
+        for block in record.chain.values()
+        {
+
+            store_block( &mut storage, block )?;
+
+        }
+        let mut chain = Chain::with_settings( storage, record.settings );
+        chain.reindex()?;
+        if chain.mmr_height == legacy_mmr_height()
+        {
+
+            chain.mmr_height = chain.meta.values().map( | meta | meta.height ).max().unwrap_or( 0 ) + 1;
+
+        }
+        store_index( &mut chain.storage, chain.hash_algorithm, &chain.meta )?;
+        let settings = chain.settings( chain.tail_hash );
+        put_settings( &mut chain.storage, &settings )?;
+        Ok( chain )
+
+    }
+
+    // Read in from json
+    #[allow(dead_code)]
+    pub fn read_json( filename: &str ) -> Result< String, Error >
+    {
+
+        // Open a readable file at the filepath
+        let mut file = OpenOptions::new( ).read( true ).open( filename )?;
+        // Read in json
+        let mut json = String::new();
+        file.read_to_string( &mut json )?;
+        // Return the string
+        Ok( json )
+
+    }
+
+    // Read in from json and construct transaction
+    #[allow(dead_code)]
+    pub fn read_and_construct( filename: &str ) -> Result< Chain, Error >
+    {
+
+        // Construct the chain, its indexes are rebuilt as it is read in
+        let string = Chain::read_json( filename )?;
+        let chain : Chain = serde_json::from_str( string.as_ref() )?;
+        // Return the chain
+        Ok( chain )
+
+    }
+
+}
+
+// Impl for Chain
+impl<S: Storage> Chain<S>
+{
+
+    // Makes a chain with the given settings over the given storage, its indexes are left empty
+    fn with_settings( storage: S, settings: Settings ) -> Chain<S>
+    {
+
+        Chain
+        {
+
+            uid: settings.uid,
+            storage,
+            tail_hash: settings.tail_hash,
+            difficulty: settings.difficulty,
+            target_spacing: settings.target_spacing,
+            retarget_window: settings.retarget_window,
+            mmr_height: settings.mmr_height,
+            hash_algorithm: settings.hash_algorithm,
+            meta: HashMap::new(),
+            mmr: Mmr::with_algorithm( settings.hash_algorithm ),
+            best: Vec::new(),
+
+        }
+
+    }
+
+    // Starts a new chain holding just its origin in the given storage, anything already in the
+    // storage is removed
+    fn start( mut storage: S, settings: Settings ) -> Result< Chain<S>, Error >
+    {
+
+        // The origin is the only block whose hash is the empty hash
+        let origin = Block::origin_with_hash_algorithm( settings.hash_algorithm );
+        let meta = Meta::origin( &origin );
+        let nodes = Mmr::with_algorithm( settings.hash_algorithm ).nodes_after( 0, &empty_hash() );
+        storage.clear()?;
+        store_block( &mut storage, &origin )?;
+        put_meta( &mut storage, &empty_hash(), &meta, &nodes )?;
+        put_settings( &mut storage, &settings )?;
+        storage.flush()?;
+        let mut chain = Chain::with_settings( storage, settings );
+        chain.meta.insert( empty_hash(), meta );
+        chain.mmr.append_nodes( &nodes );
+        chain.best.push( empty_hash() );
+        Ok( chain )
+
+    }
+
+    // Opens the chain kept in the given storage, its indexes are rebuilt from the stored index
+    // entries without reading any blocks. Storage kept before index entries were stored has its
+    // blocks read once to index them and the entries are stored for next time. Fails with an
+    // integrity error if no chain is stored
+    #[allow(dead_code)]
+    pub fn open( storage: S ) -> Result< Chain<S>, Error >
+    {
+
+        let settings: Settings = match storage.get_record( SETTINGS_RECORD )?
+        {
+
+            Some( json ) => serde_json::from_str( &json )?,
+            None => return Err( Error::Integrity( "no chain is kept in the storage".to_string() ) )
+
+        };
+        let mut chain = Chain::with_settings( storage, settings );
+        let metas = chain.storage.metas()?;
+        if metas.is_empty()
+        {
+
+            chain.reindex()?;
+            store_index( &mut chain.storage, chain.hash_algorithm, &chain.meta )?;
+            chain.storage.flush()?;
+
+        }
+        else
+        {
+
+            chain.load_index( metas )?;
+
+        }
+        Ok( chain )
+
+    }
+
+    // Moves the chain into other storage. Anything already in the storage is removed, then
+    // every block on every branch, the transactions in them ( by id, along with the block
+    // they're in ), their Merkle Trees, their index entries and the chain's settings are stored
+    // and the storage is flushed
+    #[allow(dead_code)]
+    pub fn into_storage< T: Storage >( self, mut storage: T ) -> Result< Chain<T>, Error >
+    {
+
+        storage.clear()?;
+        for hash in self.storage.block_hashes()?
+        {
+
+            store_block( &mut storage, &self.block( &hash )? )?;
+
+        }
+        store_index( &mut storage, self.hash_algorithm, &self.meta )?;
+        put_settings( &mut storage, &self.settings( self.tail_hash ) )?;
+        storage.flush()?;
+        Ok( Chain
+        {
+
+            uid: self.uid,
+            storage,
+            tail_hash: self.tail_hash,
+            difficulty: self.difficulty,
+            target_spacing: self.target_spacing,
+            retarget_window: self.retarget_window,
+            mmr_height: self.mmr_height,
+            hash_algorithm: self.hash_algorithm,
+            meta: self.meta,
+            mmr: self.mmr,
+            best: self.best,
+
+        } )
+
+    }
+
+    // Get the storage the chain is kept in
+    #[allow(dead_code)]
+    pub fn storage( &self ) -> &S
+    {
+
+        &self.storage
+
+    }
+
+    // The settings of the chain with the given tail
+    fn settings( &self, tail_hash: Digest ) -> Settings
+    {
+
+        Settings
+        {
+
+            uid: self.uid,
+            tail_hash,
+            difficulty: self.difficulty,
+            target_spacing: self.target_spacing,
+            retarget_window: self.retarget_window,
+            mmr_height: self.mmr_height,
+            hash_algorithm: self.hash_algorithm
+
+        }
+
+    }
+
+    // Reads a block of the chain from storage
+    fn block( &self, hash: &Digest ) -> Result< Block, Error >
+    {
+
+        stored_block( &self.storage, hash )
+        
+    }
+
+    // Links a block to the tail of the chain and mines it with the next required difficulty so
+    // it will be accepted by push
+    #[allow(dead_code)]
+    pub fn prepare( &self, block: &mut Block )
+    {
+
+        let tail = self.tail_hash;
+        self.prepare_on( &tail, block );
+        
+    }
+
+    // Links a block to the given parent and mines it with the difficulty required after that
+    // parent so it will be accepted by accept
+    #[allow(dead_code)]
+    pub fn prepare_on( &self, parent: &Digest, block: &mut Block )
+    {
+
+        block.set_hash_algorithm( self.hash_algorithm );
+        block.set_previous_hash( parent );
+        block.set_mmr_root( self.next_mmr_root_after( parent ) );
+        block.mine( self.next_difficulty_after( parent ) );
+        
+    }
+
+    // Computes the MMR root a block extending the given parent needs, the root of the MMR of the
+    // blocks from the origin to the parent ( None below the chain's MMR height )
+    #[allow(dead_code)]
+    pub fn next_mmr_root_after( &self, parent: &Digest ) -> Option<Digest>
+    {
+
+        let height = match self.meta.get( parent )
+        {
+
+            Some( meta ) => meta.height,
+            None => return None
+                
+        };
+        if height + 1 < self.mmr_height
+        {
+
+            None
+            
+        }
+        else
+        {
+
+            Some( self.mmr_root_after( parent ) )
+            
+        }
+        
+    }
+
+    // Computes the root of the MMR of the blocks from the origin to the given block. The best
+    // branch's MMR already holds the blocks of the best branch, so only a block on a side
+    // branch needs an MMR built for it: the best branch's MMR up to the fork point with the
+    // side branch's blocks appended
+    fn mmr_root_after( &self, hash: &Digest ) -> Digest
+    {
+
+        let height = match self.meta.get( hash )
+        {
+
+            Some( meta ) => meta.height as usize,
+            None => return Mmr::with_algorithm( self.hash_algorithm ).root()
+                
+        };
+        if self.contains( hash )
+        {
+
+            return self.mmr.root_at( height + 1 );
+            
+        }
+        self.side_mmr( hash, height ).root()
+        
+    }
+
+    // Computes the MMR nodes appending a block to the branch ending at its parent adds, read off
+    // the best branch's MMR in place when the parent is on it
+    fn mmr_nodes( &self, parent: &Digest, hash: &Digest ) -> Vec<Digest>
+    {
+
+        let height = self.meta.get( parent ).map( | meta | meta.height as usize ).unwrap_or( 0 );
+        if self.contains( parent )
+        {
+
+            self.mmr.nodes_after( height + 1, hash )
+            
+        }
+        else
+        {
+
+            self.side_mmr( parent, height ).nodes_after( height + 1, hash )
+            
+        }
+        
+    }
+
+    // Builds the MMR of the blocks from the origin to a block on a side branch at the given
+    // height: the best branch's MMR up to the fork point with the side branch's blocks appended
+    fn side_mmr( &self, hash: &Digest, height: usize ) -> Mmr
+    {
+
+        // Walks back to the fork point through the index
+        let mut side = Vec::new();
+        let mut key = *hash;
+        while !self.contains( &key )
+        {
+
+            side.push( key );
+            if side.len() > height
+            {
+
+                // The walk reached the origin without meeting the best branch ( a chain whose
+                // tail can't be reached has none ), so the side branch is the whole branch
+                return Mmr::from_hashes_with_algorithm( self.hash_algorithm, side.iter().rev() );
+                    
+            }
+            key = self.meta.get( &key ).map( | meta | meta.previous ).unwrap_or( empty_hash() );
+            
+        }
+        let mut mmr = self.mmr.clone();
+        mmr.truncate( height + 1 - side.len() );
+        for key in side.iter().rev()
+        {
+
+            mmr.append( key );
+            
+        }
+        mmr
+        
+    }
+
+    /* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *
+     * Computes the difficulty required of the block at the given height that follows the     *
+     * given ancestors ( the last blocks up to the new block's parent, see ancestors ).        *
+     *                                                                                         *
+     * The blocks up to and including the first window use the chain's initial difficulty.    *
+     * Every window blocks after that the difficulty is retargeted by comparing the time the   *
+     * last window blocks took with the time they should have taken ( window * spacing ):      *
+     *                                                                                         *
+     *     - If they took less than half the expected time the difficulty goes up by one bit   *
+     *     - If they took more than double the expected time it goes down by one bit           *
+     *     - Otherwise it stays the same                                                       *
+     *                                                                                         *
+     * Each bit of difficulty doubles the work needed for a block, so the difficulty is only   *
+     * moved once blocks are off by a factor of two. Blocks between retargets keep the         *
+     * difficulty of their parent.                                                             *
+     * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+    fn difficulty_after( &self, ancestors: &[&Meta], height: u64 ) -> u32
+    {
+
+        // The difficulty of the new block's parent
+        let parent = ancestors[ ancestors.len() - 1 ];
+        let parent_difficulty = if height == 1 { self.difficulty } else { parent.difficulty };
+        // Only retarget at the start of each window after the first
+        let window = self.retarget_window;
+        if window == 0 || height <= window || !( height - 1 ).is_multiple_of( window )
+        {
+
+            return parent_difficulty;
+            
+        }
+        // Compares the time the last window took against the expected time, the ancestors hold
+        // the parent and the window blocks before it
+        let first = ancestors[ ancestors.len() - 1 - window as usize ];
+        let actual = ( parent.timestamp - first.timestamp ).num_milliseconds();
+        let expected = window.saturating_mul( self.target_spacing ) as i64;
+        if actual.saturating_mul( 2 ) < expected
+        {
+
+            cmp::min( parent_difficulty + 1, 256 )
+            
+        }
+        else if actual > expected.saturating_mul( 2 )
+        {
+
+            parent_difficulty.saturating_sub( 1 )
+            
+        }
+        else
+        {
+
+            parent_difficulty
+            
+        }
+        
+    }
+
+    // The number of ancestors a block is checked against, the blocks its median time past is
+    // taken over and the parent with the retarget window before it
+    fn ancestor_count( &self ) -> usize
+    {
+
+        let window = usize::try_from( self.retarget_window ).unwrap_or( usize::MAX );
+        cmp::max( MEDIAN_TIME_SPAN, window.saturating_add( 1 ) )
+        
+    }
+
+    // Gets the index entries of the last count blocks up to and including the block with the
+    // given hash, oldest first ( every block back to the origin on shorter branches ), by
+    // following the previous hashes back through the index
+    fn ancestors( &self, hash: &Digest, count: usize ) -> Vec<&Meta>
+    {
+
+        let height = match self.meta.get( hash )
+        {
+
+            Some( meta ) => meta.height as usize,
+            None => return Vec::new()
+                
+        };
+        let mut ancestors = Vec::new();
+        let mut key = hash;
+        while ancestors.len() < cmp::min( count, height + 1 )
+        {
+
+            match self.meta.get( key )
+            {
+
+                Some( meta ) =>
+                {
+
+                    ancestors.push( meta );
+                    key = &meta.previous;
+                    
+                },
+                None => break
+                    
+            }
+            
+        }
+        ancestors.reverse();
+        ancestors
+        
+    }
+
+    // Computes the difficulty the next block pushed onto the chain needs
+    #[allow(dead_code)]
+    pub fn next_difficulty( &self ) -> u32
+    {
+
+        self.next_difficulty_after( &self.tail_hash )
+        
+    }
+
+    // Computes the MMR root the next block pushed onto the chain needs
+    #[allow(dead_code)]
+    pub fn next_mmr_root( &self ) -> Option<Digest>
+    {
+
+        let tail = self.tail_hash;
+        self.next_mmr_root_after( &tail )
+        
+    }
+
+    // Computes the difficulty a block extending the given parent needs
+    #[allow(dead_code)]
+    pub fn next_difficulty_after( &self, parent: &Digest ) -> u32
+    {
+
+        match self.meta.get( parent )
+        {
+
+            Some( meta ) => self.difficulty_after( &self.ancestors( parent, self.ancestor_count() ), meta.height + 1 ),
+            None => self.difficulty
+                
+        }
+        
+    }
+
+    // Computes the earliest time a block extending the given parent can be timestamped with,
+    // the median time past of the blocks up to the parent ( None if the parent isn't in the
+    // chain )
+    #[allow(dead_code)]
+    pub fn median_time_past_after( &self, parent: &Digest ) -> Option< DateTime<Utc> >
+    {
+
+        let ancestors = self.ancestors( parent, MEDIAN_TIME_SPAN );
+        if ancestors.is_empty()
+        {
+
+            None
+            
+        }
+        else
+        {
+
+            Some( median_time_past( &ancestors ) )
+            
+        }
+        
+    }
+
+    // Checks a block at the given height against the block before it, that block's last
+    // ancestors ( see ancestor_count ) and the root of the MMR of the blocks from the origin to
+    // the block before it: the link to the previous block, the hash algorithm, the header
+    // version and hash, the difficulty and work, the MMR root, the Merkle Root, the index and
+    // the timestamp ( not before the median time past and not too far ahead of the current
+    // time ).
+    fn check_block( &self, ancestors: &[&Meta], previous: &Block, height: u64, mmr_root: Digest, block: &Block ) -> Result< (), ValidationErrorKind >
+    {
+
+        if block.previous_hash() != previous.hash()
+        {
+
+            return Err( ValidationErrorKind::BrokenLink
+            {
+                
+                expected: *previous.hash(),
+                found: *block.previous_hash()
+                    
+            } );
+            
+        }
+        if block.hash_algorithm() != self.hash_algorithm
+        {
+
+            return Err( ValidationErrorKind::UnexpectedHashAlgorithm{ expected: self.hash_algorithm, found: block.hash_algorithm() } );
+            
+        }
+        if block.header_version() < previous.header_version()
+        {
+
+            return Err( ValidationErrorKind::HeaderVersionDecreasing{ previous: previous.header_version(), found: block.header_version() } );
+            
+        }
+        let header_hash = generate_header_hash( block );
+        if *block.hash() != header_hash
+        {
+
+            return Err( ValidationErrorKind::InvalidHash{ expected: header_hash } );
+            
+        }
+        let difficulty = self.difficulty_after( ancestors, height );
+        if *block.difficulty() != difficulty
+        {
+
+            return Err( ValidationErrorKind::UnexpectedDifficulty{ expected: difficulty, found: *block.difficulty() } );
+            
+        }
+        if !block.meets_difficulty( difficulty )
+        {
+
+            return Err( ValidationErrorKind::InsufficientWork{ difficulty } );
+            
+        }
+        let mmr_root = if height >= self.mmr_height { Some( mmr_root ) } else { None };
+        if block.mmr_root() != mmr_root.as_ref()
+        {
+
+            return Err( ValidationErrorKind::UnexpectedMmrRoot{ expected: mmr_root, found: block.mmr_root().cloned() } );
+            
+        }
+        // Blocks from before bodies were stored have no body to check their root against
+        if !block.predates_body() && !block.verify_merkle_root()
+        {
+
+            return Err( ValidationErrorKind::InvalidMerkleRoot );
+            
+        }
+        if block.has_ambiguous_merkle_root()
+        {
+
+            return Err( ValidationErrorKind::AmbiguousMerkleRoot );
+            
+        }
+        if block.index() <= previous.index()
+        {
+
+            return Err( ValidationErrorKind::IndexNotIncreasing{ previous: *previous.index(), found: *block.index() } );
+            
+        }
+        let median = median_time_past( ancestors );
+        if *block.timestamp() < median
+        {
+
+            return Err( ValidationErrorKind::TimestampBeforeMedian{ median } );
+            
+        }
+        let limit = timestamp::now() + Duration::seconds( MAX_FUTURE_DRIFT );
+        if *block.timestamp() > limit
+        {
+
+            return Err( ValidationErrorKind::TimestampTooFarAhead{ limit } );
+            
+        }
+        Ok( () )
+        
+    }
+
+    // Push a block onto the chain, the block is linked to the tail and then accepted. The block
+    // is rejected if its recorded difficulty isn't the next difficulty or if its hash doesn't
+    // meet that difficulty once it has been linked ( blocks should be mined after linking, see
+    // prepare ). Linking moves the block's index and timestamp up to what the tail needs, so
+    // blocks made without them are still pushed
+    #[allow(dead_code)]
+    pub fn push( &mut self, block: Block ) -> Result< (), Error >
+    {
+
+        let block = self.link( block );
+        self.accept( block ).map( | _ | () )
+        
+    }
+
+    // Links a block to the tail of the chain. An index that doesn't come after the tail's height
+    // is set to the next height and a timestamp before the median time past is set to it, a
+    // block that already has both keeps its hash
+    fn link( &self, mut block: Block ) -> Block
+    {
+
+        block.set_hash_algorithm( self.hash_algorithm );
+        block.set_previous_hash( &self.tail_hash );
+        block.set_mmr_root( self.next_mmr_root() );
+        let height = self.height();
+        if *block.index() <= height
+        {
+
+            block.set_index( height + 1 );
+
+        }
+        if let Some( median ) = self.median_time_past_after( &self.tail_hash )
+        {
+
+            if *block.timestamp() < median
+            {
+
+                block.set_timestamp( median );
+
+            }
+
+        }
+        block
+        
+    }
+
+    /* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *
+     * Accepts a block extending any block connected to the origin, the block is checked      *
+     * against the branch it extends the same way validate checks it and stored.               *
+     *                                                                                         *
+     * Every branch is kept in the chain and the tail follows the branch with the most        *
+     * cumulative work ( the sum of 2^difficulty over its blocks, with no difficulty this is   *
+     * the height ). When the new block gives its branch more work than the current tail, the  *
+     * tail moves to the new block and the returned Reorg lists the blocks disconnected from   *
+     * the old branch and connected from the new one:                                          *
+     *                                                                                         *
+     *            A1 - A2          ( tail )                                                    *
+     *          /                                                                              *
+     *   origin                                                                                *
+     *          \                                                                              *
+     *            B1 - B2 - B3     ( B3 accepted, disconnects A2, A1 and connects B1, B2, B3 )  *
+     *                                                                                         *
+     * Ties keep the tail that was seen first. A block that breaks the chain's rules fails     *
+     * with a validation error, one the storage fails to take leaves the chain and its        *
+     * storage as they were.                                                                   *
+     * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+    #[allow(dead_code)]
+    pub fn accept( &mut self, block: Block ) -> Result< Reorg, Error >
+    {
+
+        let meta = self.check( &block )?;
+        let nodes = self.mmr_nodes( block.previous_hash(), block.hash() );
+        self.store( &block, &meta, &nodes )?;
+        Ok( self.connect( *block.hash(), meta, &nodes ) )
+        
+    }
+
+    // Checks a block accept is given against the branch it extends, returns the index entry it
+    // will have once it is connected
+    fn check( &self, block: &Block ) -> Result< Meta, Error >
+    {
+
+        let hash = *block.hash();
+        // Builds an error for the block at the given height
+        let invalid = | height: u64, kind: ValidationErrorKind |
+        {
+
+            Error::Validation( ValidationError{ height, hash, kind } )
+            
+        };
+        if let Some( meta ) = self.meta.get( &hash )
+        {
+
+            return Err( invalid( meta.height, ValidationErrorKind::DuplicateBlock ) );
+            
+        }
+        let parent = match self.meta.get( block.previous_hash() )
+        {
+
+            Some( parent ) => *parent,
+            None => return Err( invalid( 0, ValidationErrorKind::UnknownParent ) )
+                
+        };
+        // Checks the block against the blocks it extends
+        let height = parent.height + 1;
+        let previous = self.block( block.previous_hash() )?;
+        let ancestors = self.ancestors( block.previous_hash(), self.ancestor_count() );
+        if let Err( kind ) = self.check_block( &ancestors, &previous, height, self.mmr_root_after( block.previous_hash() ), block )
+        {
+
+            return Err( invalid( height, kind ) );
+            
+        }
+        Ok( Meta::of( block ).after( &parent ) )
+        
+    }
+
+    // Stores a checked block with its index entry, along with the settings when the block is
+    // going to move the tail, and flushes the storage. Whatever part of the block was stored is
+    // rolled back if storing it fails, so nothing of it is flushed along with a later block
+    fn store( &mut self, block: &Block, meta: &Meta, nodes: &[Digest] ) -> Result< (), Error >
+    {
+
+        let settings = if meta.work > self.work()
+        {
+
+            Some( self.settings( *block.hash() ) )
+
+        }
+        else
+        {
+
+            None
+
+        };
+        let stored = write_block( &mut self.storage, block, meta, nodes, settings.as_ref() );
+        if stored.is_err()
+        {
+
+            // Storing the block already failed, so a rollback that fails isn't reported
+            let _ = self.storage.rollback();
+
+        }
+        stored
+
+    }
+
+    // Adds a stored block to the index, moving the tail to it if its branch has more work. The
+    // nodes are those appending the block to its branch's MMR adds
+    fn connect( &mut self, hash: Digest, meta: Meta, nodes: &[Digest] ) -> Reorg
+    {
 
-// The chain struct
-#[allow(dead_code)]
-#[derive( Serialize, Deserialize, Debug )]
-pub struct Chain
-{
+        // Moves the tail if the new branch has more work
+        let tail_work = self.work();
+        self.meta.insert( hash, meta );
+        if meta.work > tail_work
+        {
 
-    // Unique ID of the node hosting this chain
-    uid: String,
-    // Chain itself
-    chain: HashMap< String, Block >,
-    // The most recent block 
-    tail_hash: String,
+            let reorg = self.reorg_to( &hash );
+            self.tail_hash = hash;
+            // Moves the best branch's MMR back to the fork point and up the new branch, a block
+            // extending the tail is just appended
+            let size = self.mmr.len() - reorg.disconnected.len();
+            self.mmr.truncate( size );
+            for connected in &reorg.connected
+            {
 
-}
+                if *connected == hash
+                {
 
-// Impl for Chain
-impl Chain
-{
+                    self.mmr.append_nodes( nodes );
 
-    // Constructor for a new chain
-    #[allow(dead_code)]
-    pub fn new() -> Chain
-    {
+                }
+                else
+                {
+
+                    self.mmr.append( connected );
 
-        // Sets the fields to be all empty 
-        let mut chain = Chain
+                }
+                
+            }
+            // Moves the height index the same way
+            let fork = self.best.len().saturating_sub( reorg.disconnected.len() );
+            self.best.truncate( fork );
+            self.best.extend( reorg.connected.iter().cloned() );
+            reorg
+            
+        }
+        else
         {
+
+            Reorg::default()
             
-            uid: empty_hash(),
-            chain: HashMap::new(),
-            tail_hash: empty_hash(),
+        }
+        
+    }
 
-        };
-        // Insert the origin block into the chain
-        chain.chain.insert( String::from("0"), Block::origin() );
-        // Returns the chain 
-        return chain;
+    // Finds the blocks disconnected and connected when the tail moves to the given block by
+    // stepping the higher of the two branches back until they meet at the fork point
+    fn reorg_to( &self, hash: &Digest ) -> Reorg
+    {
+
+        let mut reorg = Reorg::default();
+        let mut old = self.tail_hash;
+        let mut new = *hash;
+        // Missing blocks are treated as being at the origin's height and linking to the origin
+        let height = | key: &Digest | self.meta.get( key ).map( | meta | meta.height ).unwrap_or( 0 );
+        let parent = | key: &Digest | self.meta.get( key ).map( | meta | meta.previous ).unwrap_or( empty_hash() );
+        while old != new
+        {
+
+            if height( &old ) >= height( &new )
+            {
+
+                let next = parent( &old );
+                reorg.disconnected.push( old );
+                old = next;
+                
+            }
+            else
+            {
+
+                let next = parent( &new );
+                reorg.connected.push( new );
+                new = next;
+                
+            }
+            
+        }
+        reorg.connected.reverse();
+        reorg
         
     }
 
-    // Push a block onto the chain
-    // let old = :td::mem::replace(&mut self.tail_hash, *block.hash());  self.chain.insert(old, block);
-    #[allow(dead_code)]
-    pub fn push( &mut self,  mut block: Block )  
+    // Rebuilds the index entry of every block and the indexes of the best branch from the stored
+    // index entries, the best branch is walked back from the tail through them and its MMR is
+    // rebuilt from the MMR nodes stored with its blocks without hashing them again
+    fn load_index( &mut self, metas: Vec< ( Digest, String ) > ) -> Result< (), Error >
     {
 
-        // Clone the tail hash value into a key variable for looking up the block
-        let key1 = self.tail_hash.clone();
-        let key2 = self.tail_hash.clone();
-        block.set_previous_hash( &self.tail_hash );
-        self.chain.insert( key1, block );
-        // This is some rust spaghetti but I'll break down this line for you
-        /* Obviously the tail hash is being given a new value
-         * We are making that value the block we just mapped to "key", and then
-         * we are unwrapping the option to ensure there are no issues with the mapping we just make
-         * finally we are calling the hash of the block we just retrieved from the map and cloning the value
-         * into the new tail hash
-         * ... In short, we are making the header hash of the pushed block the new tail hash
-         */
-        self.tail_hash = self.chain.get( &key2 ).unwrap().hash().clone();
+        let mut nodes = HashMap::new();
+        for ( hash, json ) in metas
+        {
+
+            let ( meta, appended ): ( Meta, Vec<Digest> ) = serde_json::from_str( &json )?;
+            self.meta.insert( hash, meta );
+            nodes.insert( hash, appended );
+
+        }
+        self.best = self.best_branch()?;
+        for hash in &self.best
+        {
+
+            match nodes.get( hash )
+            {
+
+                Some( appended ) => self.mmr.append_nodes( appended ),
+                None => return Err( Error::Integrity( format!( "block {} is on the best branch but isn't indexed", hash ) ) )
+
+            }
+
+        }
+        Ok( () )
+
+    }
+
+    // The hashes of the blocks on the best branch from the origin to the tail, walked back from
+    // the tail through the index. A tail that isn't connected to the origin has none
+    fn best_branch( &self ) -> Result< Vec<Digest>, Error >
+    {
+
+        let mut best = Vec::new();
+        if self.meta.contains_key( &self.tail_hash )
+        {
+
+            let mut key = self.tail_hash;
+            while key != empty_hash()
+            {
+
+                best.push( key );
+                key = match self.meta.get( &key )
+                {
+
+                    Some( meta ) if best.len() <= self.meta.len() => meta.previous,
+                    _ => return Err( Error::Integrity( format!( "block {} is on the best branch but isn't connected to the origin", key ) ) )
+
+                };
+
+            }
+            best.push( empty_hash() );
+            best.reverse();
+
+        }
+        Ok( best )
+
+    }
+
+    // Rebuilds the index entry of every block connected to the origin and the indexes of the
+    // best branch from the blocks in storage, each block is read once
+    fn reindex( &mut self ) -> Result< (), Error >
+    {
+
+        // The entries of the stored blocks grouped by the block they extend, their heights and
+        // work are filled in by walking out from the origin
+        let mut origin = None;
+        let mut children: HashMap< Digest, Vec< ( Digest, Meta ) > > = HashMap::new();
+        for hash in self.storage.block_hashes()?
+        {
+
+            let block = self.block( &hash )?;
+            if hash == empty_hash()
+            {
+
+                origin = Some( Meta::origin( &block ) );
+
+            }
+            else
+            {
+
+                children.entry( *block.previous_hash() ).or_default().push( ( hash, Meta::of( &block ) ) );
+
+            }
+
+        }
+        let mut meta = HashMap::new();
+        if let Some( origin ) = origin
+        {
+
+            // Walks every branch out from the origin
+            let mut stack = vec![ ( empty_hash(), origin ) ];
+            while let Some( ( hash, current ) ) = stack.pop()
+            {
+
+                for ( child, entry ) in children.remove( &hash ).unwrap_or_default()
+                {
+
+                    stack.push( ( child, entry.after( &current ) ) );
+                    
+                }
+                meta.insert( hash, current );
+                
+            }
+            
+        }
+        self.meta = meta;
+        self.best = self.best_branch()?;
+        self.mmr = Mmr::from_hashes_with_algorithm( self.hash_algorithm, self.best.iter() );
+        Ok( () )
         
     }
 
     // Gets the unique id of a chain 
     #[allow(dead_code)]
-    pub fn uid( &self ) -> &String
+    pub fn uid( &self ) -> &Digest
     {
 
         &self.uid
         
     }
 
-    // Set uid
+    // Set uid, the chain's settings are stored again with it
+    #[allow(dead_code)]
+    pub fn set_uid( &mut self, url: String ) -> Result< (), Error >
+    {
+
+        let uid = self.hash_algorithm.leaf_hash( &url );
+        let mut settings = self.settings( self.tail_hash );
+        settings.uid = uid;
+        let stored = put_settings( &mut self.storage, &settings ).and_then( | _ | self.storage.flush() );
+        if stored.is_err()
+        {
+
+            // Storing the settings already failed, so a rollback that fails isn't reported
+            let _ = self.storage.rollback();
+            return stored;
+
+        }
+        self.uid = uid;
+        Ok( () )
+        
+    }
+
+    // Get the hash algorithm the chain's blocks are hashed with
     #[allow(dead_code)]
-    pub fn set_uid( &mut self, url: String )
+    pub fn hash_algorithm( &self ) -> HashAlgorithm
     {
 
-        self.uid =  create_leaf_hash( &url );
+        self.hash_algorithm
         
     }
 
-    // Get the length of the chain
+    // Get the number of blocks connected to the origin ( on every branch )
     pub fn len( &self ) -> usize
     {
 
-        return self.chain.len();
+        self.meta.len()
+        
+    }
+
+    // Determines whether the chain has no blocks connected to the origin, only true when its
+    // origin is missing
+    #[allow(dead_code)]
+    pub fn is_empty( &self ) -> bool
+    {
+
+        self.meta.is_empty()
         
     }
     
     // Get the first block in the chain
     #[allow(dead_code)]
-    pub fn origin( &self ) -> &Block
+    pub fn origin( &self ) -> Result< Block, Error >
     {
 
-        &self.chain.get( "0" ).expect("There is no origin block in this chain.")
+        self.block( &empty_hash() )
             
     }
 
+    // Get the initial difficulty of the chain
+    #[allow(dead_code)]
+    pub fn difficulty( &self ) -> u32
+    {
+
+        self.difficulty
+        
+    }
+
     // Get the tail hash
     #[allow(dead_code)]
-    pub fn tail_hash( &self ) -> &String
+    pub fn tail_hash( &self ) -> &Digest
     {
 
         &self.tail_hash
         
     }
 
+    // Get the height of the tail ( the number of blocks after the origin on the best branch )
+    #[allow(dead_code)]
+    pub fn height( &self ) -> u64
+    {
+
+        self.meta.get( &self.tail_hash ).map( | meta | meta.height ).unwrap_or( 0 )
+        
+    }
+
+    // Get the cumulative work of the best branch
+    #[allow(dead_code)]
+    pub fn work( &self ) -> u128
+    {
+
+        self.meta.get( &self.tail_hash ).map( | meta | meta.work ).unwrap_or( 0 )
+        
+    }
     
+    // Get a block on any branch by its hash, kept for callers from before get_by_hash
+    #[allow(dead_code)]
+    #[deprecated( note = "use get_by_hash" )]
+    pub fn get( &self, hash: &Digest ) -> Result< Option<Block>, Error >
+    {
+
+        self.get_by_hash( hash )
+        
+    }
+
+    // Get a block on any branch by its hash
+    #[allow(dead_code)]
+    pub fn get_by_hash( &self, hash: &Digest ) -> Result< Option<Block>, Error >
+    {
+
+        self.storage.get_block( hash )
+        
+    }
+
+    // Get the block at the given height on the best branch ( the origin is at height 0 )
+    #[allow(dead_code)]
+    pub fn get_by_height( &self, height: u64 ) -> Result< Option<Block>, Error >
+    {
+
+        match usize::try_from( height ).ok().and_then( | index | self.best.get( index ) )
+        {
+
+            Some( hash ) => self.block( hash ).map( Some ),
+            None => Ok( None )
+
+        }
+        
+    }
+
+    // Iterates over the blocks on the best branch from the origin to the tail, use rev to
+    // iterate from the tail back to the origin. Blocks are read from storage as they're reached
+    #[allow(dead_code)]
+    pub fn iter( &self ) -> Blocks<'_, S>
+    {
+
+        Blocks{ storage: &self.storage, hashes: self.best.iter() }
+        
+    }
+
+    // Iterates over the blocks on the best branch whose heights fall in the given range, heights
+    // past the tail are left out
+    #[allow(dead_code)]
+    pub fn range< R: ops::RangeBounds<u64> >( &self, heights: R ) -> Blocks<'_, S>
+    {
+
+        let len = self.best.len();
+        // Converts a height to a position in the index, clamped to the index 
+        let clamp = | height: u64 | usize::try_from( height ).unwrap_or( usize::MAX ).min( len );
+        let start = match heights.start_bound()
+        {
+
+            ops::Bound::Included( &height ) => clamp( height ),
+            ops::Bound::Excluded( &height ) => clamp( height.saturating_add( 1 ) ),
+            ops::Bound::Unbounded => 0
+                
+        };
+        let end = match heights.end_bound()
+        {
+
+            ops::Bound::Included( &height ) => clamp( height.saturating_add( 1 ) ),
+            ops::Bound::Excluded( &height ) => clamp( height ),
+            ops::Bound::Unbounded => len
+                
+        };
+        Blocks{ storage: &self.storage, hashes: self.best[ start .. end.max( start ) ].iter() }
+        
+    }
+
+    // Proves a block on the best branch is committed to by the tail's header, the proof verifies
+    // against the tail's MMR root so a light client holding only the latest header can check a
+    // block without the blocks in between. None if the block isn't on the best branch below
+    // the tail or the tail doesn't commit to an MMR
+    #[allow(dead_code)]
+    pub fn prove_block( &self, hash: &Digest ) -> Option<MmrProof>
+    {
+
+        let index = self.meta.get( hash )?.height as usize;
+        let size = self.height();
+        if size < self.mmr_height || !self.mmr.leaf_is( index, hash )
+        {
+
+            return None;
+            
+        }
+        self.mmr.prove( index, size as usize )
+        
+    }
     
-    // Verifies whether or not a block is contained within the chain 
+    // Verifies whether or not a block is contained within the best branch of the chain, the
+    // block's height is looked up and checked against the height index. A chain whose tail
+    // can't be reached from the origin has no best branch and contains nothing
+    #[allow(dead_code)]
+    pub fn contains( &self, hash: &Digest ) -> bool
+    {
+
+        match self.meta.get( hash )
+        {
+
+            Some( meta ) => self.best.get( meta.height as usize ) == Some( hash ),
+            None => false
+                
+        }
+        
+    }
+
+    // Get a block on any branch and its height by the block's hash, None if the block isn't
+    // stored or can't be reached from the origin ( so has no height )
+    #[allow(dead_code)]
+    pub fn locate( &self, hash: &Digest ) -> Result< Option< ( Block, u64 ) >, Error >
+    {
+
+        match self.meta.get( hash )
+        {
+
+            Some( meta ) => self.block( hash ).map( | block | Some( ( block, meta.height ) ) ),
+            None => Ok( None )
+
+        }
+        
+    }
+
+    // Validates the chain by walking every branch out from the origin. Each block is read from
+    // storage and checked against the branch it extends ( the link to the previous block, the
+    // hash algorithm, the header version and hash, the difficulty, the Merkle Root, the index
+    // and the timestamp ) and the first block that fails is reported with a validation error.
+    // Every stored block must be reachable, each block's index entry must match it and the tail
+    // must be the tip with the most work.
     #[allow(dead_code)]
-    pub fn contains( &self, hash: &String ) -> bool
+    pub fn validate( &self ) -> Result< (), Error >
     {
 
-        // Hash of each block to check against the parameter
-        let mut hash_check = String::from("0");
-        let null_block = Block::new( 0, String::from("-1") );
-        #[allow(unused_assignments)]
-        let mut current_block = null_block.clone();
-        // The check loop through the chain
-        while hash_check != self.tail_hash
+        // Builds an error for the block at the given height 
+        let invalid = | height: u64, hash: &Digest, kind: ValidationErrorKind |
+        {
+
+            Error::Validation( ValidationError{ height, hash: *hash, kind } )
+            
+        };
+        // The walk starts at the origin block
+        let origin = match self.storage.get_block( &empty_hash() )?
+        {
+
+            Some( origin ) => origin,
+            None => return Err( invalid( 0, &empty_hash(), ValidationErrorKind::MissingOrigin ) )
+                
+        };
+        if *origin.index() != 0
+        {
+
+            return Err( invalid( 0, origin.hash(), ValidationErrorKind::InvalidOrigin ) );
+            
+        }
+        if origin.hash_algorithm() != self.hash_algorithm
+        {
+
+            return Err( invalid( 0, origin.hash(), ValidationErrorKind::UnexpectedHashAlgorithm
+            {
+
+                expected: self.hash_algorithm,
+                found: origin.hash_algorithm()
+                    
+            } ) );
+            
+        }
+        if self.meta.get( &empty_hash() ) != Some( &Meta::origin( &origin ) )
+        {
+
+            return Err( Error::Integrity( "the index entry of the origin doesn't match the block".to_string() ) );
+            
+        }
+        // Walks every branch depth first, the path holds the hashes of the blocks from the
+        // origin to the parent of the block being checked and its end the ancestors the block is
+        // checked against. The block checked last is kept, as it is the next block's parent
+        // unless the walk has moved to another branch
+        let count = self.ancestor_count();
+        let children = children( &self.meta );
+        let mut path = vec![ empty_hash() ];
+        let mut mmr = Mmr::from_hashes_with_algorithm( self.hash_algorithm, vec![ origin.hash() ] );
+        let mut stack: Vec< ( Digest, usize ) > = children.get( origin.hash() ).map( | hashes | hashes.iter().rev().map( | hash | ( *hash, 1 ) ).collect() ).unwrap_or_default();
+        let mut previous = origin;
+        while let Some( ( hash, height ) ) = stack.pop()
         {
 
-            // Get the block mapping of the current hash check (start at origin)
-            current_block = self.chain.get( &hash_check ).unwrap_or( &null_block.clone() ).clone();
-            if *current_block.hash() == *hash
+            // Drops the blocks of the branch that was just finished
+            path.truncate( height );
+            mmr.truncate( height );
+            let block = self.block( &hash )?;
+            if previous.hash() != block.previous_hash()
+            {
+
+                previous = self.block( block.previous_hash() )?;
+                
+            }
+            let ancestors: Vec<&Meta> = path[ height.saturating_sub( count ) .. ].iter().filter_map( | key | self.meta.get( key ) ).collect();
+            if let Err( kind ) = self.check_block( &ancestors, &previous, height as u64, mmr.root(), &block )
+            {
+
+                return Err( invalid( height as u64, &hash, kind ) );
+                
+            }
+            // The index entry the chain may have been opened from must match the block
+            let expected = self.meta.get( block.previous_hash() ).map( | parent | Meta::of( &block ).after( parent ) );
+            if self.meta.get( &hash ) != expected.as_ref()
             {
 
-                // If the hash was found return true 
-                return true;
+                return Err( Error::Integrity( format!( "the index entry of block {} doesn't match the block", hash ) ) );
+                
+            }
+            path.push( hash );
+            mmr.append( &hash );
+            for child in children.get( &hash ).map( | hashes | hashes.as_slice() ).unwrap_or( &[] ).iter().rev()
+            {
 
+                stack.push( ( *child, height + 1 ) );
+                
             }
-            hash_check = current_block.hash().clone();
+            previous = block;
+            
+        }
+        // Every stored block should have been reached from the origin
+        let best = self.meta.values().map( | meta | ( meta.work, meta.height ) ).max().unwrap_or( ( 0, 0 ) );
+        let stored = self.storage.block_hashes()?.len();
+        if stored != self.meta.len()
+        {
+
+            return Err( invalid( best.1, &self.tail_hash, ValidationErrorKind::UnreachableBlocks
+            {
+                
+                count: stored - self.meta.len()
+                    
+            } ) );
             
         }
-        // The hash was not found 
-        return false;
+        // The tail should be a block with the most work
+        match self.meta.get( &self.tail_hash )
+        {
+
+            None => Err( invalid( best.1 + 1, &self.tail_hash, ValidationErrorKind::MissingBlock ) ),
+            Some( tail ) if tail.work < best.0 => Err( invalid( self.height(), &self.tail_hash, ValidationErrorKind::NotBestTip ) ),
+            Some( _ ) => Ok( () )
+                
+        }
         
     }
 
@@ -187,49 +1998,14 @@ impl Chain
     }    
 
     
-    // Write the transaction to a file
+    // Write the chain to a file, a file already there is replaced whole or left as it was
     #[allow(dead_code)]
     pub fn write_to( &self, filename: &str ) -> Result< (), Error >
     {
 
-        // Open the filepath with append specification
-        let mut file = OpenOptions::new(  ).write( true ).create( true ).open( filename )?;
-        // Write the json to the filepath
-        #[allow(unused_variables)]
-        let temp = file.write_all( serde_json::to_string( &self )?.as_ref() );
-
-        Ok( () )
-        
-    }
-
-    // Read in from json 
-    #[allow(dead_code)]
-    pub fn read_json( filename: &str ) -> Result< String, Error >
-    {
-
-        // Open a readable file at the filepath
-        let mut file = OpenOptions::new( ).read( true ).open( filename )?;
-        // Read in json
-        let mut json = String::new();
-        #[allow(unused_variables)]
-        let temp = file.read_to_string( &mut json );
-        // Return the string
-        Ok( json )
-        
-    }
-
-    // Read in from json and construct transaction
-    #[allow(dead_code)]
-    pub fn read_and_construct( filename: &str ) -> Result< Chain, Error >
-    {
-
-        // Construct the transaction
-        let string = Chain::read_json( filename ).expect( "Failed to read in the JSON" );
-        let chain : Chain = serde_json::from_str( string.as_ref() ).expect( "Failed to convert the JSON to chain" );
-        // Return the transaction
-        Ok( chain )
+        // Replace the file with the json atomically
+        file::write_atomically( filename, serde_json::to_string( &self )?.as_ref() )
         
     }
 
-    
 }
@@ -0,0 +1,289 @@
+// Crate inclusion
+//
+// Serde used for serialization
+extern crate serde;
+
+// Use statements
+//
+// Standard library
+#[allow(unused_imports)]
+use std::*;
+// Used for displaying digests and parse errors
+use std::fmt;
+// Used for parsing digests from hex
+use std::str::FromStr;
+// Digests serialize as hex strings
+use self::serde::{ Serialize, Serializer, Deserialize, Deserializer };
+use self::serde::de::{ self, Visitor };
+
+/*
+ *
+ * Digest:
+ *     - This file contains the fixed size type every hash in the crate is held in ( block
+ *       hashes, Merkle Roots, proof paths, .. ). A digest is the 32 raw bytes of a 256 bit
+ *       hash, which is half the size of its hex string, compares as bytes and can't hold a
+ *       malformed value.
+ *
+ *     - Digests display, parse and serialize as 64 lowercase hex digits, the form hashes
+ *       were kept in before, so existing json files read in unchanged.
+ *
+ */
+
+// The number of bytes in a digest
+pub const DIGEST_LENGTH: usize = 32;
+
+// Digest struct
+#[derive( Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default )]
+pub struct Digest( [ u8; DIGEST_LENGTH ] );
+
+// Digest impl
+#[allow(dead_code)]
+impl Digest
+{
+
+    // Constructs a digest from its bytes
+    pub fn new( bytes: [ u8; DIGEST_LENGTH ] ) -> Self
+    {
+
+        Digest( bytes )
+
+    }
+
+    // Constructs a digest from a slice of bytes, None if the slice isn't 32 bytes long
+    pub fn from_slice( bytes: &[u8] ) -> Option<Digest>
+    {
+
+        if bytes.len() != DIGEST_LENGTH
+        {
+
+            return None;
+
+        }
+        let mut digest = [ 0; DIGEST_LENGTH ];
+        digest.copy_from_slice( bytes );
+        Some( Digest( digest ) )
+
+    }
+
+    // Returns the bytes of the digest
+    pub fn as_bytes( &self ) -> &[ u8; DIGEST_LENGTH ]
+    {
+
+        &self.0
+
+    }
+
+    // Returns the number of leading zero bits of the digest
+    pub fn leading_zeros( &self ) -> u32
+    {
+
+        let mut zeros = 0;
+        for byte in &self.0
+        {
+
+            zeros += byte.leading_zeros();
+            if *byte != 0
+            {
+
+                break;
+
+            }
+
+        }
+        zeros
+
+    }
+
+    // Determines whether the digest meets a proof of work difficulty ( has at least difficulty
+    // leading zero bits )
+    pub fn meets_difficulty( &self, difficulty: u32 ) -> bool
+    {
+
+        self.leading_zeros() >= difficulty
+
+    }
+
+}
+
+// Displays a digest as 64 lowercase hex digits
+impl fmt::Display for Digest
+{
+
+    fn fmt( &self, f: &mut fmt::Formatter ) -> fmt::Result
+    {
+
+        for byte in &self.0
+        {
+
+            write!( f, "{:02x}", byte )?;
+
+        }
+        Ok( () )
+
+    }
+
+}
+
+// Debugs a digest as its hex digits so errors and assertions show the hash
+impl fmt::Debug for Digest
+{
+
+    fn fmt( &self, f: &mut fmt::Formatter ) -> fmt::Result
+    {
+
+        write!( f, "Digest({})", self )
+
+    }
+
+}
+
+// The reason a string couldn't be parsed as a digest
+#[derive( Debug, Clone, PartialEq )]
+pub struct ParseDigestError
+{
+
+    // The string that was parsed
+    pub value: String
+
+}
+
+// Display impl for parse errors
+impl fmt::Display for ParseDigestError
+{
+
+    fn fmt( &self, f: &mut fmt::Formatter ) -> fmt::Result
+    {
+
+        write!( f, "{:?} is not 64 hex digits", self.value )
+
+    }
+
+}
+
+// Parse errors can be used as standard errors
+impl error::Error for ParseDigestError {}
+
+// Parses a digest from 64 hex digits of either case
+impl FromStr for Digest
+{
+
+    type Err = ParseDigestError;
+
+    fn from_str( value: &str ) -> Result< Digest, ParseDigestError >
+    {
+
+        let invalid = || ParseDigestError{ value: value.to_string() };
+        if value.len() != 2 * DIGEST_LENGTH || !value.is_ascii()
+        {
+
+            return Err( invalid() );
+
+        }
+        let mut digest = [ 0; DIGEST_LENGTH ];
+        for ( i, byte ) in digest.iter_mut().enumerate()
+        {
+
+            *byte = u8::from_str_radix( &value[ 2 * i .. 2 * i + 2 ], 16 ).map_err( | _ | invalid() )?;
+
+        }
+        Ok( Digest( digest ) )
+
+    }
+
+}
+
+// Serializes a digest as its hex string
+impl Serialize for Digest
+{
+
+    fn serialize< S: Serializer >( &self, serializer: S ) -> Result< S::Ok, S::Error >
+    {
+
+        serializer.collect_str( self )
+
+    }
+
+}
+
+// Deserializes a digest from its hex string
+impl<'de> Deserialize<'de> for Digest
+{
+
+    fn deserialize< D: Deserializer<'de> >( deserializer: D ) -> Result< Digest, D::Error >
+    {
+
+        deserializer.deserialize_str( DigestVisitor )
+
+    }
+
+}
+
+// Visits the hex string of a digest
+struct DigestVisitor;
+
+// Visitor impl for digests
+impl<'de> Visitor<'de> for DigestVisitor
+{
+
+    type Value = Digest;
+
+    fn expecting( &self, f: &mut fmt::Formatter ) -> fmt::Result
+    {
+
+        write!( f, "a hash as 64 hex digits" )
+
+    }
+
+    fn visit_str< E: de::Error >( self, value: &str ) -> Result< Digest, E >
+    {
+
+        value.parse().map_err( E::custom )
+
+    }
+
+}
+
+/*
+ *
+ * Optional digest serialization:
+ *     - For fields holding a digest that may be missing, None is written as an empty string
+ *       ( the form these fields had when they were strings ) and an empty string or null is
+ *       read in as None. Used with #[serde(with = "::digest::optional")].
+ *
+ */
+pub mod optional
+{
+
+    // Use statements
+    use super::*;
+
+    // Serializes a missing digest as an empty string
+    pub fn serialize< S: Serializer >( digest: &Option<Digest>, serializer: S ) -> Result< S::Ok, S::Error >
+    {
+
+        match *digest
+        {
+
+            Some( ref digest ) => digest.serialize( serializer ),
+            None => serializer.serialize_str( "" )
+
+        }
+
+    }
+
+    // Deserializes an empty string or null as a missing digest
+    pub fn deserialize< 'de, D: Deserializer<'de> >( deserializer: D ) -> Result< Option<Digest>, D::Error >
+    {
+
+        let value: Option<String> = Option::deserialize( deserializer )?;
+        match value
+        {
+
+            Some( ref value ) if !value.is_empty() => value.parse().map( Some ).map_err( de::Error::custom ),
+            _ => Ok( None )
+
+        }
+
+    }
+
+}
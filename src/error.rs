@@ -0,0 +1,159 @@
+// Crate inclusion
+//
+// Serde json errors are wrapped
+extern crate serde_json;
+
+// Use statements
+//
+// Standard library
+#[allow(unused_imports)]
+use std::*;
+// Used for displaying errors
+use std::fmt;
+// The errors of each part of the crate
+use chain::ValidationError;
+use digest::ParseDigestError;
+use proof::ProofError;
+
+/*
+ *
+ * Error:
+ *     - This file contains the error every fallible operation of the crate can be turned
+ *       into. Reading and writing files returns it directly, operations that can only fail
+ *       one way ( validating a chain, making a proof, .. ) return their own error, which
+ *       converts into it with ?.
+ *
+ *     - Errors are grouped as IO ( the file system ), JSON ( serialization ), validation
+ *       ( a block or chain breaking the chain's rules ) and integrity ( data that doesn't
+ *       match what it commits to or isn't in its encoding ).
+ *
+ */
+#[derive( Debug )]
+pub enum Error
+{
+
+    // A file couldn't be opened, read or written
+    Io( io::Error ),
+    // Json couldn't be serialized or deserialized
+    Json( serde_json::Error ),
+    // A block or chain failed validation
+    Validation( ValidationError ),
+    // A proof couldn't be made or decoded
+    Proof( ProofError ),
+    // A string isn't a hex digest
+    Digest( ParseDigestError ),
+    // Stored data doesn't match the hash or length it was stored with
+    Integrity( String )
+
+}
+
+// Display impl for errors
+impl fmt::Display for Error
+{
+
+    fn fmt( &self, f: &mut fmt::Formatter ) -> fmt::Result
+    {
+
+        match *self
+        {
+
+            Error::Io( ref error ) => write!( f, "io error: {}", error ),
+            Error::Json( ref error ) => write!( f, "json error: {}", error ),
+            Error::Validation( ref error ) => write!( f, "validation error: {}", error ),
+            Error::Proof( ref error ) => write!( f, "proof error: {}", error ),
+            Error::Digest( ref error ) => write!( f, "digest error: {}", error ),
+            Error::Integrity( ref reason ) => write!( f, "integrity error: {}", reason )
+
+        }
+
+    }
+
+}
+
+// Errors can be used as standard errors, the wrapped error is the source
+impl error::Error for Error
+{
+
+    fn source( &self ) -> Option< &( dyn error::Error + 'static ) >
+    {
+
+        match *self
+        {
+
+            Error::Io( ref error ) => Some( error ),
+            Error::Json( ref error ) => Some( error ),
+            Error::Validation( ref error ) => Some( error ),
+            Error::Proof( ref error ) => Some( error ),
+            Error::Digest( ref error ) => Some( error ),
+            Error::Integrity( _ ) => None
+
+        }
+
+    }
+
+}
+
+// IO errors convert into errors
+impl From<io::Error> for Error
+{
+
+    fn from( error: io::Error ) -> Error
+    {
+
+        Error::Io( error )
+
+    }
+
+}
+
+// Json errors convert into errors
+impl From<serde_json::Error> for Error
+{
+
+    fn from( error: serde_json::Error ) -> Error
+    {
+
+        Error::Json( error )
+
+    }
+
+}
+
+// Validation errors convert into errors
+impl From<ValidationError> for Error
+{
+
+    fn from( error: ValidationError ) -> Error
+    {
+
+        Error::Validation( error )
+
+    }
+
+}
+
+// Proof errors convert into errors
+impl From<ProofError> for Error
+{
+
+    fn from( error: ProofError ) -> Error
+    {
+
+        Error::Proof( error )
+
+    }
+
+}
+
+// Digest parse errors convert into errors
+impl From<ParseDigestError> for Error
+{
+
+    fn from( error: ParseDigestError ) -> Error
+    {
+
+        Error::Digest( error )
+
+    }
+
+}
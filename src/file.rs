@@ -0,0 +1,84 @@
+// Use statements
+//
+// Standard library
+#[allow(unused_imports)]
+use std::*;
+// Used for creating, syncing and renaming files
+use std::fs::{ File, OpenOptions };
+// Prelude
+use std::io::prelude::*;
+// Used for finding the directory a file is in
+use std::path::Path;
+// Used for naming temporary files uniquely
+use std::sync::atomic::{ AtomicUsize, Ordering };
+// Writing files fails with the crate's error
+use error::Error;
+
+/*
+ *
+ * File:
+ *     - This file contains the helpers files are replaced through ( every write_to method but
+ *       the transaction's, which appends, and Transaction::replace ). A file is written to a
+ *       temporary file next to it, synced to disk and renamed over it, so a reader ( or a
+ *       restart after a crash ) sees the old file or the new one whole, never a mix of the two
+ *       or a file cut short.
+ *
+ */
+
+// Counts the temporary files made by this process so concurrent writes don't share one
+static TEMPORARY_FILES: AtomicUsize = AtomicUsize::new( 0 );
+
+// Writes the contents to the file, replacing the file atomically. The temporary file is
+// removed if the write fails
+pub fn write_atomically( file_name: &str, contents: &[u8] ) -> Result< (), Error >
+{
+
+    let count = TEMPORARY_FILES.fetch_add( 1, Ordering::SeqCst );
+    let temporary = format!( "{}.{}.{}.tmp", file_name, process::id(), count );
+    let written = write_and_rename( file_name, &temporary, contents );
+    if written.is_err()
+    {
+
+        // The write already failed, so a temporary file that can't be removed isn't reported
+        let _ = fs::remove_file( &temporary );
+
+    }
+    written
+
+}
+
+// Writes the contents to the temporary file, syncs it and renames it over the file
+fn write_and_rename( file_name: &str, temporary: &str, contents: &[u8] ) -> Result< (), Error >
+{
+
+    let mut file = OpenOptions::new( ).write( true ).create_new( true ).open( temporary )?;
+    file.write_all( contents )?;
+    file.sync_all()?;
+    drop( file );
+    fs::rename( temporary, file_name )?;
+    // The rename is only durable once the directory holding the file is synced
+    let directory = match Path::new( file_name ).parent()
+    {
+
+        Some( parent ) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new( "." )
+
+    };
+    sync_directory( directory )
+
+}
+
+// Syncs a directory so files created in it or renamed into it survive a crash ( directories
+// can only be synced this way on unix )
+pub fn sync_directory( directory: &Path ) -> Result< (), Error >
+{
+
+    if cfg!( unix )
+    {
+
+        File::open( directory )?.sync_all()?;
+
+    }
+    Ok( () )
+
+}
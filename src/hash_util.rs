@@ -2,15 +2,22 @@
 //
 // Sha3 used for hashing 
 extern crate sha3;
+// Sha2 and BLAKE2b used as alternative hash algorithms
+extern crate sha2;
+extern crate blake2b_simd;
 
 // Use statements
 //
 // For hashing using SHA256 
-use sha3::{ Digest, Sha3_256 };
+use sha3::{ Digest as Sha3Digest, Sha3_256 };
+// For hashing using SHA-256
+use self::sha2::Sha256;
 // For enforcing the trait ToString and for general String functionality
 use std::string::{ ToString, String };
 // For block tests
 use block::*;
+// Hashes are returned as fixed size digests
+pub use digest::Digest;
 
 /*
  *
@@ -19,8 +26,236 @@ use block::*;
  *
  */
 
+// The byte fed to the hasher before a leaf's value in domain separated trees ( RFC 6962 )
+pub const LEAF_PREFIX: u8 = 0x00;
+// The byte fed to the hasher before a node's children in domain separated trees ( RFC 6962 )
+pub const NODE_PREFIX: u8 = 0x01;
+
+// A hash function being fed the bytes of one digest
+pub trait Hasher
+{
+
+    // Feeds bytes to the hasher
+    fn update( &mut self, bytes: &[u8] );
+
+    // Returns the digest of every byte fed to the hasher
+    fn finish( self: Box<Self> ) -> Digest;
+    
+}
+
+// SHA3-256 hasher
+impl Hasher for Sha3_256
+{
+
+    fn update( &mut self, bytes: &[u8] )
+    {
+
+        self.input( bytes );
+        
+    }
+
+    fn finish( self: Box<Self> ) -> Digest
+    {
+
+        Digest::from_slice( &self.result() ).expect( "a 256 bit hasher" )
+        
+    }
+    
+}
+
+// SHA-256 hasher
+impl Hasher for Sha256
+{
+
+    fn update( &mut self, bytes: &[u8] )
+    {
+
+        self.input( bytes );
+        
+    }
+
+    fn finish( self: Box<Self> ) -> Digest
+    {
+
+        Digest::from_slice( &self.result() ).expect( "a 256 bit hasher" )
+        
+    }
+    
+}
+
+// BLAKE2b hasher
+impl Hasher for blake2b_simd::State
+{
+
+    fn update( &mut self, bytes: &[u8] )
+    {
+
+        blake2b_simd::State::update( self, bytes );
+        
+    }
+
+    fn finish( self: Box<Self> ) -> Digest
+    {
+
+        Digest::from_slice( self.finalize().as_bytes() ).expect( "a 256 bit hasher" )
+        
+    }
+    
+}
+
+/*
+ *
+ * Hash Algorithm:
+ *     - The hash function every hash of a chain is made with ( block headers, Merkle Trees,
+ *       proofs, the MMR and the chain's uid ). The algorithm is recorded in blocks, proofs and
+ *       chains, anything serialized before algorithms were recorded is read in as SHA3-256.
+ *
+ *     - Every algorithm gives a 256 bit digest, so hashes fit a Digest whichever one is
+ *       used ( BLAKE2b is run with a 32 byte output ).
+ *
+ */
+#[derive( Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize )]
+pub enum HashAlgorithm
+{
+
+    // SHA3-256 ( Keccak ), the algorithm used when none is given
+    #[default]
+    Sha3_256,
+    // SHA-256 ( SHA-2 )
+    Sha256,
+    // BLAKE2b with a 256 bit output
+    Blake2b
+
+}
+
+// Hash Algorithm impl 
+impl HashAlgorithm
+{
+
+    // Creates a new hasher for the algorithm
+    pub fn hasher( &self ) -> Box<dyn Hasher>
+    {
+
+        match *self
+        {
+
+            HashAlgorithm::Sha3_256 => Box::new( Sha3_256::default() ),
+            HashAlgorithm::Sha256 => Box::new( Sha256::default() ),
+            HashAlgorithm::Blake2b => Box::new( blake2b_simd::Params::new().hash_length( 32 ).to_state() )
+                
+        }
+        
+    }
+
+    // Hashes the concatenation of the given byte strings
+    pub fn digest( &self, parts: &[ &[u8] ] ) -> Digest
+    {
+
+        let mut hasher = self.hasher();
+        for part in parts
+        {
+
+            hasher.update( part );
+            
+        }
+        hasher.finish()
+        
+    }
+
+    // The byte the algorithm is encoded as
+    pub fn to_byte( &self ) -> u8
+    {
+
+        match *self
+        {
+
+            HashAlgorithm::Sha3_256 => 0,
+            HashAlgorithm::Sha256 => 1,
+            HashAlgorithm::Blake2b => 2
+                
+        }
+        
+    }
+
+    // The algorithm encoded as the given byte, if there is one
+    pub fn from_byte( byte: u8 ) -> Option<HashAlgorithm>
+    {
+
+        match byte
+        {
+
+            0 => Some( HashAlgorithm::Sha3_256 ),
+            1 => Some( HashAlgorithm::Sha256 ),
+            2 => Some( HashAlgorithm::Blake2b ),
+            _ => None
+                
+        }
+        
+    }
+
+    // Create a hash of 0 
+    pub fn empty_hash( &self ) -> Digest
+    {
+
+        self.leaf_hash( &0 )
+        
+    }
+
+    // Create a hash of a leaf
+    pub fn leaf_hash< T: ToString >( &self, value: &T ) -> Digest
+    {
+
+        self.digest( &[ value.to_string().as_bytes() ] )
+        
+    }
+
+    // Create a hash of a node from the hex strings of its children
+    pub fn node_hash< T: ToString >( &self, left: &T, right: &T ) -> Digest
+    {
+
+        self.digest( &[ left.to_string().as_bytes(), right.to_string().as_bytes() ] )
+        
+    }
+
+    // Create a domain separated hash of a leaf, the value is prefixed with the leaf prefix so a
+    // leaf can never have the same preimage as a node
+    pub fn prefixed_leaf_hash< T: ToString >( &self, value: &T ) -> Digest
+    {
+
+        self.digest( &[ &[ LEAF_PREFIX ], value.to_string().as_bytes() ] )
+        
+    }
+
+    // Create a domain separated hash of a node, the children's digests are prefixed with the
+    // node prefix and fed to the hasher as bytes rather than as hex strings
+    pub fn prefixed_node_hash( &self, left: &Digest, right: &Digest ) -> Digest
+    {
+
+        self.digest( &[ &[ NODE_PREFIX ], left.as_bytes(), right.as_bytes() ] )
+        
+    }
+
+    // Create the id of a transaction from its canonical encoding 
+    pub fn transaction_id( &self, encoding: &[u8] ) -> Digest
+    {
+
+        self.digest( &[ encoding ] )
+        
+    }
+
+    // Create a domain separated hash of a leaf from a transaction id, the id's digest is fed to
+    // the hasher as bytes after the leaf prefix
+    pub fn id_leaf_hash( &self, id: &Digest ) -> Digest
+    {
+
+        self.digest( &[ &[ LEAF_PREFIX ], id.as_bytes() ] )
+        
+    }
+    
+}
+
 // Create a hash of 0 
-pub fn empty_hash() -> String
+pub fn empty_hash() -> Digest
 {
     
     // Create a leaf hash of the null byte
@@ -28,64 +263,149 @@ pub fn empty_hash() -> String
 
 }
 
-// Create a hash of a leaf
-pub fn create_leaf_hash< T: ToString >( value: &T ) -> String
+// Create a hash of a leaf with the default algorithm
+pub fn create_leaf_hash< T: ToString >( value: &T ) -> Digest
 {
 
-    // Initialize a hasher and input the value as a byte array ref
-    let mut hasher = Sha3_256::default();
-    hasher.input( &value.to_string().as_ref() );
-    // Return the hash of the value as a string
-    format!( "{:x}", hasher.result() )
+    HashAlgorithm::default().leaf_hash( value )
         
 }
 
-// Creat a hash of a node
-pub fn create_node_hash< T: ToString >( left: &T, right: &T ) -> String
+// Creat a hash of a node with the default algorithm
+pub fn create_node_hash< T: ToString >( left: &T, right: &T ) -> Digest
 {
 
-    // Initiate hasher
-    let mut hasher = Sha3_256::default();
-    // Feed the hasher the two children strings
-    hasher.input( &left.to_string().as_ref() );
-    hasher.input( &right.to_string().as_ref() );
-    // Return the hash as a string 
-    format!( "{:x}", hasher.result() )
+    HashAlgorithm::default().node_hash( left, right )
+    
+}
+
+
+// Create a domain separated hash of a leaf with the default algorithm
+pub fn create_prefixed_leaf_hash< T: ToString >( value: &T ) -> Digest
+{
+
+    HashAlgorithm::default().prefixed_leaf_hash( value )
+    
+}
+
+// Create a domain separated hash of a node with the default algorithm
+pub fn create_prefixed_node_hash( left: &Digest, right: &Digest ) -> Digest
+{
+
+    HashAlgorithm::default().prefixed_node_hash( left, right )
+    
+}
+
+// Create the id of a transaction from its canonical encoding with the default algorithm
+pub fn create_transaction_id( encoding: &[u8] ) -> Digest
+{
+
+    HashAlgorithm::default().transaction_id( encoding )
+    
+}
+
+// Create a domain separated hash of a leaf from a transaction id with the default algorithm
+pub fn create_id_leaf_hash( id: &Digest ) -> Digest
+{
+
+    HashAlgorithm::default().id_leaf_hash( id )
+    
+}
+
+// Decodes a hex hash into its bytes, a string that isn't hex is returned as its own bytes
+pub fn hex_bytes( hash: &str ) -> Vec<u8>
+{
+
+    // Decodes each pair of hex digits into a byte
+    let decoded: Option< Vec<u8> > = if hash.len().is_multiple_of( 2 ) && hash.is_ascii()
+    {
+
+        ( 0 .. hash.len() ).step_by( 2 ).map( | i | u8::from_str_radix( &hash[ i .. i + 2 ], 16 ).ok() ).collect()
+            
+    }
+    else
+    {
+
+        None
+        
+    };
+    decoded.unwrap_or_else( || hash.as_bytes().to_vec() )
+    
+}
+
+// Encodes bytes as a lowercase hex string, the inverse of hex_bytes
+pub fn hex_string( bytes: &[u8] ) -> String
+{
+
+    bytes.iter().map( | byte | format!( "{:02x}", byte ) ).collect()
     
 }
 
 
 /* Block specific hashing functions */
 #[allow(dead_code)]
-pub fn generate_header_hash( block: &Block ) -> String
+pub fn generate_header_hash( block: &Block ) -> Digest
+{
+
+    // Hash the header with the block's own nonce
+    generate_header_hash_with_nonce( block, *block.nonce() )
+    
+}
+
+// Hashes the header of a block as if it had the given nonce with the block's hash algorithm and
+// header version, used while mining so candidate nonces can be tried without modifying the block
+#[allow(dead_code)]
+pub fn generate_header_hash_with_nonce( block: &Block, nonce: u64 ) -> Digest
+{
+
+    match block.header_version()
+    {
+
+        HeaderVersion::V0 => generate_legacy_header_hash( block, nonce ),
+        HeaderVersion::V1 => block.hash_algorithm().digest( &[ &block.encode_header_with_nonce( nonce ) ] )
+            
+    }
+    
+}
+
+// Hashes the concatenated strings of a legacy ( V0 ) header
+fn generate_legacy_header_hash( block: &Block, nonce: u64 ) -> Digest
 {
 
     // Concatenate everything into one string
     let mut temp = String::new();
-    temp += &block.index().to_string().as_ref();
-    temp += &block.timestamp().to_string().as_ref();
-    temp += &block.merkle_root().as_ref();
-    temp += &block.previous_hash().as_ref();
-    // Create a new string to add everything to
-    let mut hasher = Sha3_256::default();
-    // Concat the items of the block
-    hasher.input( temp.as_bytes() );
-    // Return the hash
-    format!( "{:x}", hasher.result() )
+    temp += block.index().to_string().as_ref();
+    temp += block.timestamp().to_string().as_ref();
+    temp += block.merkle_root().to_string().as_ref();
+    temp += block.previous_hash().to_string().as_ref();
+    // Headers from before proof of work hashed only the fields above, so the difficulty and
+    // nonce are only added once either is set
+    if *block.difficulty() != 0 || nonce != 0
+    {
+
+        temp += block.difficulty().to_string().as_ref();
+        temp += nonce.to_string().as_ref();
+        
+    }
+    // A missing MMR root adds nothing, so headers from before the MMR hash as they did
+    if let Some( root ) = block.mmr_root()
+    {
+
+        temp += root.to_string().as_ref();
+        
+    }
+    // Hash the concatenated items of the block
+    block.hash_algorithm().digest( &[ temp.as_bytes() ] )
     
 }
 
 
 /* Chain hash utilities */
 #[allow(dead_code)]
-pub fn generate_unique_id( token: u64 ) -> String
+pub fn generate_unique_id( token: u64 ) -> Digest
 {
 
-    // Sets the hasher to the SHA 256 default 
-    let mut hasher = Sha3_256::default();
-    // Uses the given token as input for the hasher 
-    hasher.input( token.to_string().as_ref() );
-    // Formats the hasher 
-    format!( "{:x}", hasher.result() )
+    // Hashes the given token with the default algorithm
+    create_leaf_hash( &token )
     
 }
@@ -1,5 +1,7 @@
 // Extern crate inclusion 
 extern crate sha3;
+// Chrono used for timestamping
+extern crate chrono;
 // Serde used for serialization 
 #[macro_use]
 extern crate serde_derive;
@@ -41,6 +43,24 @@ pub mod proof;
 pub mod chain;
 // Transaction acces 
 pub mod transaction;
+// Sparse Merkle access
+pub mod sparse;
+// Merkle Mountain Range access
+pub mod mmr;
+// Digest access
+pub mod digest;
+// Timestamp access
+pub mod timestamp;
+// Error access
+pub mod error;
+// Block store access
+pub mod store;
+// Storage backend access
+pub mod storage;
+// Atomic file write access
+pub mod file;
+// The error every fallible operation converts into
+pub use error::Error;
 
 /*
  *
@@ -58,8 +78,9 @@ pub mod transaction;
  *
  * Hashing:
  *     - The predetermined hashes we use are obtained from Decrane.io 
- *       ( https://www.decrane.io/sha-3 ) with the SHA3-256 variant. Non-leaf  
- *       hashes are made by hashing the concatenation of the child hashes.
+ *       ( https://www.decrane.io/sha-3 ) with the SHA3-256 variant. In legacy ( V0 )
+ *       trees non-leaf hashes are made by hashing the concatenation of the child hashes,
+ *       in V1 trees leaves are prefixed with 0x00 and nodes with 0x01 ( RFC 6962 ).
  * 
  *
  */
@@ -90,16 +111,16 @@ mod tree_tests
 
     // Test flag indicating the next method is a test function
     #[test]
-    // Unit test for a tree leaf
+    // Unit test for a legacy ( V0 ) tree leaf
     fn test_tree_leaf()
     {
 
         // The hash value for the leaf
         let digest_hash = hash_util::create_leaf_hash( &9 );
         // The transaction
-        let transaction = transaction::Transaction::new( 0, "zac".to_string(),  "9".to_string(), "now".to_string(), "verified".to_string() );
+        let transaction = transaction::Transaction::new( 0, "zac".to_string(),  "9".to_string(), timestamp::from_millis( 0 ).unwrap(), "verified".to_string() );
         // The tree leaf constructed with this hash and value 
-        let tree_leaf: tree::Tree = tree::Tree::leaf( transaction );
+        let tree_leaf: tree::Tree = tree::Tree::versioned_leaf( tree::TreeVersion::V0, transaction );
         // Comparing the tree's hash with the computed hash
         assert_eq!( *tree_leaf.hash(), digest_hash );
 
@@ -107,7 +128,7 @@ mod tree_tests
 
     // Test flag indicating the next method is a test function
     #[test]
-    // Unit test for a tree node
+    // Unit test for a legacy ( V0 ) tree node
     fn test_tree_node()
     {
 
@@ -123,24 +144,67 @@ mod tree_tests
         let right_value: u8 = 1;
         // Left transaction 
         let left_transaction = transaction::Transaction::new( 0, "zac".to_string(),
-                                                              "0".to_string(), "now".to_string(), "verified".to_string() );
+                                                              "0".to_string(), timestamp::from_millis( 0 ).unwrap(), "verified".to_string() );
         // Right transaction 
         let right_transaction = transaction::Transaction::new( 0, "zac".to_string(),
-      "1".to_string(), "now".to_string(), "verified".to_string() );
+      "1".to_string(), timestamp::from_millis( 0 ).unwrap(), "verified".to_string() );
         // The tree's left and right children 
-        let left_child: tree::Tree = tree::Tree::leaf( left_transaction );
+        let left_child: tree::Tree = tree::Tree::versioned_leaf( tree::TreeVersion::V0, left_transaction );
         // Comparing the left child's hash with its computed hash
         assert_eq!( *left_child.hash(), left_hash );
-        let right_child: tree::Tree = tree::Tree::leaf( right_transaction );
+        let right_child: tree::Tree = tree::Tree::versioned_leaf( tree::TreeVersion::V0, right_transaction );
         // Comparing the right child's hash with its computed hash
         assert_eq!( *right_child.hash(), right_hash );
         // The root node with the calculated hash and left and right children 
-        let root_node: tree::Tree = tree::Tree::node( left_child, right_child );
+        let root_node: tree::Tree = tree::Tree::versioned_node( tree::TreeVersion::V0, left_child, right_child );
         // Comparing the root's hash with the computed hash 
         assert_eq!( *root_node.hash(), digest_hash );
 
     }
 
+    // Test flag indicating the next method is a test function
+    #[test]
+    // Unit test for domain separated ( V1 ) leaves and nodes
+    fn test_prefixed_tree()
+    {
+
+        // The leaf hashes are SHA3-256( 0x00 || value )
+        let left_transaction = transaction::Transaction::new( 0, "zac".to_string(),
+                                                              "0".to_string(), timestamp::from_millis( 0 ).unwrap(), "verified".to_string() );
+        let right_transaction = transaction::Transaction::new( 0, "zac".to_string(),
+                                                               "1".to_string(), timestamp::from_millis( 0 ).unwrap(), "verified".to_string() );
+        let left_child = tree::Tree::versioned_leaf( tree::TreeVersion::V1, left_transaction );
+        let right_child = tree::Tree::versioned_leaf( tree::TreeVersion::V1, right_transaction );
+        assert_eq!( "3d122ea15b883943dc304e5a7525c8fd9e1236ea756dc83fda60bf5603e93230", left_child.hash().to_string() );
+        assert_eq!( "2e6ca10c11589dafe1923f509d4459fb9e1fdd9449f6a09f8c9f4c1ced5276ce", right_child.hash().to_string() );
+        // The node hash is SHA3-256( 0x01 || left digest || right digest )
+        let root_node = tree::Tree::versioned_node( tree::TreeVersion::V1, left_child, right_child );
+        assert_eq!( "b4ea3a22110b08a73cabbf27ac30c771b74bebd8797f8e15ed579b3f3ccaa387", root_node.hash().to_string() );
+        // The leaf only covers the transaction's content
+        let leaf = tree::Tree::versioned_leaf( tree::TreeVersion::V1, transaction::dummy() );
+        assert_eq!( "4c85d7487436e389493205f2b46e4a880190e9c87fc9dd308e1552e37152b741", leaf.hash().to_string() );
+
+    }
+
+    // Test flag indicating the next method is a test function
+    #[test]
+    // Unit test showing a node's children can't be passed off as a leaf once hashing is
+    // domain separated
+    fn test_second_preimage()
+    {
+
+        // A leaf whose value is the concatenation of two child hashes
+        let left_hash = hash_util::create_leaf_hash( &0 );
+        let right_hash = hash_util::create_leaf_hash( &1 );
+        let forged = transaction::Transaction::new( 0, "mallory".to_string(), format!( "{}{}", left_hash, right_hash ),
+                                                    timestamp::from_millis( 0 ).unwrap(), "verified".to_string() );
+        // Without prefixes the forged leaf has the same hash as the node
+        assert_eq!( tree::TreeVersion::V0.leaf_hash( &forged ), tree::TreeVersion::V0.node_hash( &left_hash, &right_hash ) );
+        // With prefixes it doesn't
+        assert!( tree::TreeVersion::V1.leaf_hash( &forged ) != tree::TreeVersion::V1.node_hash( &left_hash, &right_hash ) );
+
+    }
+
 }
 
 // Test flag indicating this module contains test methods
@@ -158,10 +222,19 @@ mod block_tests
     fn create_block()
     {
 
-        // Creates a new block with the leaf hash of 9  
-        let block = block::Block::new( 0, create_leaf_hash( &9 ) );
-        // Asserts that the block's Merkle root has the proper hash 
-        assert_eq!( block.merkle_root, "7609430974b087595488c154bf5c079887ead0e8efd4055cd136fda96a5ccbf8" );
+        // Creates a new block with a transaction holding 9
+        let transaction = transaction::Transaction::new( 0, "zac".to_string(), "9".to_string(), timestamp::from_millis( 0 ).unwrap(), "verified".to_string() );
+        let block = block::Block::new( 0, vec![ transaction ] );
+        // Asserts that the block's Merkle root has the proper hash ( the prefixed hash of the transaction's id ) 
+        assert_eq!( block.merkle_root.to_string(), "45df1b4857c9c2f618c8469b020ac8e52d9e94f15442e2ccc93dd194fd4c598e" );
+        // A block built with the V1 tree version has the prefixed leaf hash of 9
+        let mut prefixed = block.clone();
+        prefixed.tree_version = tree::TreeVersion::V1;
+        assert_eq!( prefixed.merkle().root_hash().to_string(), "0a3e01359d8a98c5941605c78641458c243501559f58c8da9133ab6213562c96" );
+        // A block built with the legacy tree version has the unprefixed leaf hash of 9
+        let mut legacy = block.clone();
+        legacy.tree_version = tree::TreeVersion::V0;
+        assert_eq!( legacy.merkle().root_hash().to_string(), "7609430974b087595488c154bf5c079887ead0e8efd4055cd136fda96a5ccbf8" );
         
     }
 
@@ -171,8 +244,8 @@ mod block_tests
     fn test_print()
     {
 
-        // Creates a new block with the leaf hash of 9 
-        let block = block::Block::new( 0, create_leaf_hash( &9 ) );
+        // Creates a new block with a dummy transaction
+        let block = block::Block::new( 0, vec![ transaction::dummy() ] );
         // Prints the json serialization
         block.print_block().unwrap()
         
@@ -184,13 +257,13 @@ mod block_tests
     fn test_write_to()
     {
 
-        // Creates a new block with the leaf hash of 9
-        let block = block::Block::new( 0, create_leaf_hash( &9 ) );
+        // Creates a new block with a dummy transaction
+        let block = block::Block::new( 0, vec![ transaction::dummy() ] );
         // Creates the file name
         let file_name = "testing-block.json";
         // Writes the serialization to the output file
         #[allow(unused_variables)]
-        let temp = block.write_to( &file_name );
+        let temp = block.write_to( file_name );
         // Reads in the file with serialization to make sure it has been
         // properly created.
         let mut file = File::open( file_name ).unwrap();
@@ -212,16 +285,194 @@ mod block_tests
         
         // Erase the currently existing file
         #[allow(unused_variables)]
-        let status = Command::new( "rm" ).args( &[ "-rf", "testing-block.json" ]
+        let status = Command::new( "rm" ).args( [ "-rf", "testing-block.json" ]
         ).status().expect( "Process failed ");
         // Creates a new block 
-        let block = block::Block::new( 0, create_leaf_hash( &9 ) );
+        let block = block::Block::new( 0, vec![ transaction::dummy(), transaction::dummy() ] );
         // Writes to output file
         #[allow(unused_variables)]
         let temp = block.write_to( "testing-block.json" );
         // Build a block from the json
-        #[allow(unused_variables)]
         let json_block = block::Block::read_and_construct( "testing-block.json" ).expect( "Did not convert json_block" );
+        // The body is read back in with the block and still matches its Merkle Root
+        assert_eq!( block.transactions(), json_block.transactions() );
+        assert!( json_block.verify_merkle_root() );
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests that the Merkle Root is computed from and validated against the body 
+    fn test_verify_merkle_root()
+    {
+
+        // Creates a new block with two transactions 
+        let mut block = block::Block::new( 1, vec![ transaction::dummy(), transaction::dummy() ] );
+        // The stored root matches the root of the body's Merkle Tree
+        assert_eq!( *block.merkle_root(), *merkle::Merkle::new( block.transactions().clone() ).root_hash() );
+        assert!( block.verify_merkle_root() );
+        // Tampering with the body is detected when the root is recomputed
+        block.transactions[ 1 ] = transaction::Transaction::new( 5, "name".to_string(), "goodbye".to_string(),
+                                                                 timestamp::from_millis( 0 ).unwrap(), "unverified".to_string() );
+        assert!( !block.verify_merkle_root() );
+        // The origin block has an empty body and the empty hash as its root
+        let origin = block::Block::origin();
+        assert!( origin.transactions().is_empty() );
+        assert_eq!( *origin.merkle_root(), empty_hash() );
+        assert!( origin.verify_merkle_root() );
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests that a block written before blocks carried their body is recognized, its root can't
+    // be recomputed but isn't treated as tampered with
+    fn test_predates_body()
+    {
+
+        // The block written by the first version of the crate has no body or tree version
+        let legacy = block::Block::read_and_construct( "tests/fixtures/baseline-block.json" ).unwrap();
+        assert!( legacy.transactions().is_empty() );
+        assert!( legacy.predates_body() );
+        assert!( !legacy.verify_merkle_root() );
+        // Blocks with a body, or an empty body under the current tree version, have a root that
+        // can be checked
+        assert!( !block::Block::new( 1, vec![ transaction::dummy() ] ).predates_body() );
+        assert!( !block::Block::new( 1, vec![] ).predates_body() );
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests that mining finds a nonce meeting the difficulty
+    fn test_mine()
+    {
+
+        // Creates a new block and mines it
+        let mut block = block::Block::new( 1, vec![ transaction::dummy() ] );
+        block.mine( 12 );
+        // The hash starts with three zero hex digits and is the header hash with the nonce
+        assert!( block.hash().to_string().starts_with( "000" ) );
+        assert!( block.meets_difficulty( 12 ) );
+        assert_eq!( *block.hash(), generate_header_hash( &block ) );
+        // Mining with no difficulty leaves the nonce alone
+        let mut easy = block::Block::new( 1, vec![ transaction::dummy() ] );
+        easy.mine( 0 );
+        assert_eq!( 0, *easy.nonce() );
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests that a cancelled search stops and leaves the block unchanged
+    fn test_mine_cancelled()
+    {
+
+        // Creates a new block and a cancel flag that has already been raised
+        let mut block = block::Block::new( 1, vec![ transaction::dummy() ] );
+        let hash = *block.hash();
+        let cancel = std::sync::atomic::AtomicBool::new( true );
+        // No block could ever meet this difficulty so only cancelling ends the search
+        assert!( !block.mine_until( 256, &cancel ) );
+        assert_eq!( hash, *block.hash() );
+        assert_eq!( 0, *block.nonce() );
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests that headers sharing a legacy preimage hash differently once canonically encoded
+    fn test_header_collision()
+    {
+
+        // Difficulty 1 with nonce 23 and difficulty 12 with nonce 3 concatenate to "123"
+        let mut first = block::Block::new( 1, vec![ transaction::dummy() ] );
+        first.difficulty = 1;
+        first.nonce = 23;
+        let mut second = first.clone();
+        second.difficulty = 12;
+        second.nonce = 3;
+        first.set_header_version( block::HeaderVersion::V0 );
+        second.set_header_version( block::HeaderVersion::V0 );
+        assert_eq!( first.hash(), second.hash() );
+        // The canonical encodings differ, so the hashes do as well
+        first.set_header_version( block::HeaderVersion::V1 );
+        second.set_header_version( block::HeaderVersion::V1 );
+        assert!( first.encode_header() != second.encode_header() );
+        assert!( first.hash() != second.hash() );
+        assert_eq!( *first.hash(), first.hash_algorithm().digest( &[ &first.encode_header() ] ) );
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests the layout of the canonical header encoding
+    fn test_encode_header()
+    {
+
+        let mut block = block::Block::new( 7, vec![ transaction::dummy() ] );
+        block.timestamp = timestamp::from_millis( 0 ).unwrap();
+        block.difficulty = 3;
+        block.nonce = 9;
+        let bytes = block.encode_header();
+        // Versions, index, timestamp, roots and hashes, difficulty, nonce and the missing MMR root
+        let time = "1970-01-01 00:00:00 UTC";
+        assert_eq!( 3 + 8 + 4 + time.len() + 32 + 32 + 4 + 8 + 1, bytes.len() );
+        assert_eq!( &[ 1, 0, 3 ], &bytes[ .. 3 ] );
+        assert_eq!( &7u64.to_be_bytes(), &bytes[ 3 .. 11 ] );
+        assert_eq!( &( time.len() as u32 ).to_be_bytes(), &bytes[ 11 .. 15 ] );
+        assert_eq!( time.as_bytes(), &bytes[ 15 .. 38 ] );
+        assert_eq!( block.merkle_root.as_bytes(), &bytes[ 38 .. 70 ] );
+        assert_eq!( block.previous_hash.as_bytes(), &bytes[ 70 .. 102 ] );
+        assert_eq!( &3u32.to_be_bytes(), &bytes[ 102 .. 106 ] );
+        assert_eq!( &9u64.to_be_bytes(), &bytes[ 106 .. 114 ] );
+        assert_eq!( &[ 0 ], &bytes[ 114 .. ] );
+        // An MMR root is flagged and appended, so no root and a root can't be confused
+        block.set_mmr_root( Some( empty_hash() ) );
+        let bytes = block.encode_header();
+        assert_eq!( 115 + 32, bytes.len() );
+        assert_eq!( 1, bytes[ 114 ] );
+        assert_eq!( empty_hash().as_bytes(), &bytes[ 115 .. ] );
+        // Mining tries nonces without changing the block
+        assert_eq!( &5u64.to_be_bytes(), &block.encode_header_with_nonce( 5 )[ 106 .. 114 ] );
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests that a block written before header versions existed keeps its legacy header hash
+    fn test_read_legacy_header()
+    {
+
+        let mut block = block::Block::new( 1, vec![ transaction::dummy() ] );
+        block.set_header_version( block::HeaderVersion::V0 );
+        block.write_to( "testing-legacy-header.json" ).unwrap();
+        let json = block::Block::read_json( "testing-legacy-header.json" ).unwrap().replace( ",\"header_version\":\"V0\"", "" );
+        let mut file = File::create( "testing-legacy-header.json" ).unwrap();
+        file.write_all( json.as_bytes() ).unwrap();
+        let read = block::Block::read_and_construct( "testing-legacy-header.json" ).unwrap();
+        fs::remove_file( "testing-legacy-header.json" ).unwrap();
+        assert!( !json.contains( "header_version" ) );
+        assert_eq!( block::HeaderVersion::V0, read.header_version() );
+        assert_eq!( *read.hash(), generate_header_hash( &read ) );
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests that a block hashed by the first version of the crate verifies under the legacy
+    // header ( the SHA3-256 of its index, timestamp, Merkle Root and previous hash )
+    fn test_baseline_header_hash()
+    {
+
+        let block = block::Block::read_and_construct( "tests/fixtures/baseline-block.json" ).unwrap();
+        assert_eq!( block::HeaderVersion::V0, block.header_version() );
+        assert_eq!( "063597d1e709aabf1026bfb5417d1d0b0efd99460b047516b898173883144e0a", generate_header_hash( &block ).to_string() );
+        assert_eq!( *block.hash(), generate_header_hash( &block ) );
+        // The same header with a nonce no longer hashes the same
+        let mut mined = block.clone();
+        mined.set_nonce( 1 );
+        assert!( *block.hash() != generate_header_hash( &mined ) );
         
     }
     
@@ -245,7 +496,7 @@ mod hash_util_tests
         // Creates an empty hash value 
         let hash = hash_util::empty_hash();
         // Asserts that this is equal with the predetermined hash value
-        assert_eq!( hash, "f9e2eaaa42d9fe9e558a9b8ef1bf366f190aacaa83bad2641ee106e9041096e4".to_string() );
+        assert_eq!( hash.to_string(), "f9e2eaaa42d9fe9e558a9b8ef1bf366f190aacaa83bad2641ee106e9041096e4" );
         
     }
 
@@ -258,7 +509,7 @@ mod hash_util_tests
         // Creates a hash with the value 9 
         let hash = hash_util::create_leaf_hash( &9 );
         // Asserts that this is equal with the predetermined hash value 
-        assert_eq!( hash , "7609430974b087595488c154bf5c079887ead0e8efd4055cd136fda96a5ccbf8".to_string() );
+        assert_eq!( hash.to_string(), "7609430974b087595488c154bf5c079887ead0e8efd4055cd136fda96a5ccbf8" );
         
     }
 
@@ -271,7 +522,27 @@ mod hash_util_tests
         // Creates a hash with the hashes for 0 and 1
         let hash = hash_util::create_node_hash( &"f9e2eaaa42d9fe9e558a9b8ef1bf366f190aacaa83bad2641ee106e9041096e4", &"67b176705b46206614219f47a05aee7ae6a3edbe850bbbe214c536b989aea4d2" );
         // Asserts that the created hash is equal to the true hash 
-        assert_eq!( hash, "b6698473bbe17ece4f1bdb6ade7218f775c4a53120c5d98c0ec0e354806f8c7f".to_string() );
+        assert_eq!( hash.to_string(), "b6698473bbe17ece4f1bdb6ade7218f775c4a53120c5d98c0ec0e354806f8c7f" );
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests each hash algorithm against a known digest of "0"
+    fn hash_algorithm_test()
+    {
+
+        assert_eq!( hash_util::empty_hash(), hash_util::HashAlgorithm::Sha3_256.empty_hash() );
+        assert_eq!( "5feceb66ffc86f38d952786c6d696c79c2dbc239dd4e91b46729d73a27fb57e9", hash_util::HashAlgorithm::Sha256.empty_hash().to_string() );
+        assert_eq!( "0fd923ca5e7218c4ba3c3801c26a617ecdbfdaebb9c76ce2eca166e7855efbb8", hash_util::HashAlgorithm::Blake2b.empty_hash().to_string() );
+        // The algorithms round trip through their bytes 
+        for &algorithm in &[ hash_util::HashAlgorithm::Sha3_256, hash_util::HashAlgorithm::Sha256, hash_util::HashAlgorithm::Blake2b ]
+        {
+
+            assert_eq!( Some( algorithm ), hash_util::HashAlgorithm::from_byte( algorithm.to_byte() ) );
+            
+        }
+        assert_eq!( None, hash_util::HashAlgorithm::from_byte( 3 ) );
         
     }
     
@@ -321,7 +592,7 @@ mod merkle_tests
         // Creates a new type u8 Merkle Tree
         let merkle: merkle::Merkle = merkle::Merkle::empty();
         // Confirms that is_empty returns true
-        assert_eq!( true, merkle.is_empty() );
+        assert!( merkle.is_empty() );
         
     }
 
@@ -339,7 +610,7 @@ mod merkle_tests
         // Confirms that the tree's hash is the same as a calculated empty hash
         assert_eq!( *merkle.root_hash(), empty_hash );
         // Confirms that the tree is empty
-        assert_eq!( true, merkle.is_empty() );
+        assert!( merkle.is_empty() );
         
     }
     
@@ -350,20 +621,156 @@ mod merkle_tests
     {
         
         // Creates a list of values to be hashed and constructed into a Merkle Tree
-        let mut values = Vec::new();
-        // Pushes our names ( zac and ezra ) to the vector 
-        values.push( transaction::dummy() );
-        values.push( transaction::dummy() );
-        // Creates a new full Merkle Tree with these values
-        let merkle = merkle::Merkle::new( values );
+        // Our names ( zac and ezra )
+        let values = vec![ transaction::dummy(), transaction::dummy() ];
+        // Creates a new full legacy ( V0 ) Merkle Tree with these values
+        let merkle = merkle::Merkle::with_version( values.clone(), tree::TreeVersion::V0 );
         // Makes sure the Merkle Tree isn't empty 
-        assert_eq!( false, merkle.is_empty() );
+        assert!( !merkle.is_empty() );
         // Verifies the height 
         assert_eq!( 1, merkle.height() );
         // Verifies the leaf count
         assert_eq!( 2, merkle.leaf_count() );
         // Verifies the root hash ( thereby verifying the hashes on all other levels )
-        assert_eq!( "4529305fcf7c742e1b531bb2e743ca8450c9c6ac7423317c662f8ca62c506548", merkle.root_hash() );
+        assert_eq!( "4529305fcf7c742e1b531bb2e743ca8450c9c6ac7423317c662f8ca62c506548", merkle.root_hash().to_string() );
+        // The same values in a domain separated ( V1 ) tree have a different root 
+        let merkle = merkle::Merkle::with_version( values, tree::TreeVersion::V1 );
+        assert_eq!( tree::TreeVersion::V1, merkle.version() );
+        assert_eq!( "e7cc1760d2692c8b213944303c02bd1b65956818f162ed8339128cd270935b3e", merkle.root_hash().to_string() );
+        
+    }
+
+    // Creates a transaction with the given content
+    fn post( content: &str ) -> transaction::Transaction
+    {
+
+        transaction::Transaction::new( 0, "zac".to_string(), content.to_string(), timestamp::from_millis( 0 ).unwrap(), "verified".to_string() )
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Unit test showing that duplicating the last leaf no longer gives the same root
+    fn test_promote_unpaired()
+    {
+
+        let odd = vec![ post( "a" ), post( "b" ), post( "c" ) ];
+        let duplicated = vec![ post( "a" ), post( "b" ), post( "c" ), post( "c" ) ];
+        // V1 pairs the unpaired leaf with itself so both lists share a root
+        let v1_odd = merkle::Merkle::with_version( odd.clone(), tree::TreeVersion::V1 );
+        let v1_duplicated = merkle::Merkle::with_version( duplicated.clone(), tree::TreeVersion::V1 );
+        assert_eq!( v1_odd.root_hash(), v1_duplicated.root_hash() );
+        // The duplicated siblings are detected
+        assert!( !v1_odd.is_ambiguous() );
+        assert!( v1_duplicated.is_ambiguous() );
+        // V2 promotes the unpaired leaf so the roots differ
+        let v2_odd = merkle::Merkle::with_version( odd, tree::TreeVersion::V2 );
+        let v2_duplicated = merkle::Merkle::with_version( duplicated, tree::TreeVersion::V2 );
+        assert_eq!( tree::TreeVersion::V2, v2_odd.version() );
+        assert!( v2_odd.root_hash() != v2_duplicated.root_hash() );
+        assert!( !v2_duplicated.is_ambiguous() );
+        // The root of the odd tree is the node of ( a, b ) and the promoted c
+        let version = tree::TreeVersion::V2;
+        let left = version.node_hash( &version.leaf_hash( &post( "a" ) ), &version.leaf_hash( &post( "b" ) ) );
+        assert_eq!( version.node_hash( &left, &version.leaf_hash( &post( "c" ) ) ), *v2_odd.root_hash() );
+        assert_eq!( 2, v2_odd.height() );
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Unit test showing appending leaves one at a time gives the same tree as building it
+    fn test_append()
+    {
+
+        for &version in &[ tree::TreeVersion::V0, tree::TreeVersion::V1, tree::TreeVersion::current() ]
+        {
+
+            let mut merkle = merkle::Merkle::empty_with_version( version );
+            let mut values = Vec::new();
+            for i in 0 .. 20
+            {
+
+                values.push( post( &i.to_string() ) );
+                merkle.append( post( &i.to_string() ) );
+                let rebuilt = merkle::Merkle::with_version( values.clone(), version );
+                assert_eq!( rebuilt.root_hash(), merkle.root_hash() );
+                assert_eq!( rebuilt.height(), merkle.height() );
+                assert_eq!( rebuilt.leaf_count(), merkle.leaf_count() );
+                // Every level matches, so proofs do too
+                for index in 0 .. values.len()
+                {
+
+                    assert_eq!( rebuilt.get_proof_at( index ), merkle.get_proof_at( index ) );
+                    
+                }
+                
+            }
+            
+        }
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Unit test showing a tree read back in from json can be appended to and updated
+    fn test_round_trip_then_append()
+    {
+
+        for &version in &[ tree::TreeVersion::V0, tree::TreeVersion::V1, tree::TreeVersion::current() ]
+        {
+
+            let values: Vec<transaction::Transaction> = ( 0 .. 5 ).map( | i | post( &i.to_string() ) ).collect();
+            let merkle = merkle::Merkle::with_version( values.clone(), version );
+            let json = serde_json::to_string( &merkle ).unwrap();
+            let mut read: merkle::Merkle = serde_json::from_str( &json ).unwrap();
+            assert_eq!( merkle.root_hash(), read.root_hash() );
+            assert_eq!( merkle.height(), read.height() );
+            assert_eq!( merkle.leaf_count(), read.leaf_count() );
+            // Appending gives the tree built with the extra leaf
+            read.append( post( "5" ) );
+            let mut appended = values.clone();
+            appended.push( post( "5" ) );
+            assert_eq!( merkle::Merkle::with_version( appended.clone(), version ).root_hash(), read.root_hash() );
+            // As does updating
+            assert!( read.update( 0, post( "updated" ) ) );
+            appended[ 0 ] = post( "updated" );
+            assert_eq!( merkle::Merkle::with_version( appended, version ).root_hash(), read.root_hash() );
+
+        }
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Unit test showing updating a leaf gives the same tree as building it
+    fn test_update()
+    {
+
+        for &version in &[ tree::TreeVersion::V0, tree::TreeVersion::V1, tree::TreeVersion::current() ]
+        {
+
+            for count in 1 .. 10
+            {
+
+                let mut values: Vec<transaction::Transaction> = ( 0 .. count ).map( | i | post( &i.to_string() ) ).collect();
+                let mut merkle = merkle::Merkle::with_version( values.clone(), version );
+                for index in 0 .. count
+                {
+
+                    values[ index ] = post( "updated" );
+                    assert!( merkle.update( index, post( "updated" ) ) );
+                    let rebuilt = merkle::Merkle::with_version( values.clone(), version );
+                    assert_eq!( rebuilt.root_hash(), merkle.root_hash() );
+                    assert_eq!( rebuilt.get_multi_proof( &( 0 .. count ).collect::< Vec<usize> >() ), merkle.get_multi_proof( &( 0 .. count ).collect::< Vec<usize> >() ) );
+                    
+                }
+                // There is no leaf to update past the end
+                assert!( !merkle.update( count, post( "updated" ) ) );
+                
+            }
+            
+        }
         
     }
 
@@ -383,17 +790,16 @@ mod merkle_tests
             values.push( transaction::dummy() );
             
         }
-        // Creates a new full Merkle Tree with these values
-        let merkle = merkle::Merkle::new( values );
+        // Creates a new full legacy ( V0 ) Merkle Tree with these values
+        let merkle = merkle::Merkle::with_version( values, tree::TreeVersion::V0 );
         // Makes sure the Merkle Tree isn't empty
-        assert_eq!( false, merkle.is_empty() );
+        assert!( !merkle.is_empty() );
         // Verifies the height
         assert_eq!( 4, merkle.height() );
         // Verifies the leaf count
         assert_eq!( 16, merkle.leaf_count() );
         // Verifies the root hash ( thereby verifying the hashes on all other levels )
-        assert_eq!( "08afda110d1caedcde7c9bde98232e01fd44ff2d4e89bf2bed8d6c38aecf6bae",
-      merkle.root_hash() );
+        assert_eq!( "08afda110d1caedcde7c9bde98232e01fd44ff2d4e89bf2bed8d6c38aecf6bae", merkle.root_hash().to_string() );
         
     }
     
@@ -413,7 +819,7 @@ mod merkle_tests
         // Verifies it created an empty Merkle Tree by checking the size and
         // the hash
         assert_eq!( *merkle.root_hash(), empty_hash );
-        assert_eq!( true, merkle.is_empty() );
+        assert!( merkle.is_empty() );
         
     }
     
@@ -457,7 +863,7 @@ mod merkle_tests
         }
         // Creates a new full Merkle Tree with these values
         let merkle = merkle::Merkle::new( values );
-        assert_eq!( false, merkle.is_empty() );
+        assert!( !merkle.is_empty() );
         
     }
 
@@ -545,14 +951,14 @@ mod merkle_tests
         }
         // Makes sure false is returned for invalid indicies
         let false_return = merkle.remove( 10 );
-        assert_eq!( false, false_return );
+        assert!( !false_return );
         // Removes all the values and asserts that they were removed correclty
         #[allow(unused_variables)]
         for i in 0 .. 8 
         {
 
             let return_val = merkle.remove( 0 );
-            assert_eq!( true, return_val );
+            assert!( return_val );
             
         }
         // Empty hash to compare with Merkle hash 
@@ -574,8 +980,8 @@ mod merkle_tests
 
         // Creates a list of values to be hashed and constructed into a Merkle Tree
         let values = Vec::new();
-        // Creates a new full Merkle Tree with these values
-        let mut merkle = merkle::Merkle::new( values );
+        // Creates a new full legacy ( V0 ) Merkle Tree with these values
+        let mut merkle = merkle::Merkle::with_version( values, tree::TreeVersion::V0 );
         // Inserts values into the Merkle Tree
         #[allow(unused_variables)]
         for i in 0 .. 8
@@ -585,21 +991,21 @@ mod merkle_tests
             
         }
         // Hash for the string hello 
-        let hello: String = "3338be694f50c5f338814986cdf0686453a888b84f424d792af4b9202398f392".to_string();
+        let hello: Digest = "3338be694f50c5f338814986cdf0686453a888b84f424d792af4b9202398f392".parse().unwrap();
         // Return value for hello
         let hello_return = merkle.hash_found_at_level( 3, hello );
         // Makes sure the value was found
-        assert_eq!( true, hello_return );
+        assert!( hello_return );
         // To ensure that hashes not found within the tree return a false value when searched
         // for, we create a hash out of the hashes for our names ( "zac" and "ezra" ).
-        let zac: String = "f296b0a2ba1d206049d67ce9e6cbabedcecf63b6b4b86742b6ab94e305e64991".to_string();
-        let ezra: String = "7c06507a16cabd54e266c3e9b33d2b059575cdbb9c54d5d345a5d99959194e69".to_string();
+        let zac: Digest = "f296b0a2ba1d206049d67ce9e6cbabedcecf63b6b4b86742b6ab94e305e64991".parse().unwrap();
+        let ezra: Digest = "7c06507a16cabd54e266c3e9b33d2b059575cdbb9c54d5d345a5d99959194e69".parse().unwrap();
         // We then search for the hashes in the tree
         let zac_return = merkle.hash_found_at_level( 3, zac );
         let ezra_return = merkle.hash_found_at_level( 3, ezra );
         // Then we ensure these return values are both false
-        assert_eq!( false, zac_return );
-        assert_eq!( false, ezra_return );
+        assert!( !zac_return );
+        assert!( !ezra_return );
         
     }
 
@@ -610,8 +1016,8 @@ mod merkle_tests
     pub fn test_get_hash_index()
     {
 
-        // Creates a new full Merkle Tree with empty values
-        let mut merkle = merkle::Merkle::new( Vec::new() );
+        // Creates a new full legacy ( V0 ) Merkle Tree with empty values
+        let mut merkle = merkle::Merkle::with_version( Vec::new(), tree::TreeVersion::V0 );
         // Inserts values into the Merkle Tree
         #[allow(unused_variables)]
         for i in 0 .. 4
@@ -621,9 +1027,9 @@ mod merkle_tests
             
         }
         // The index ( return value ) of the hash's index 
-        let first_return: i32 = merkle.get_hash_index( 2, "3338be694f50c5f338814986cdf0686453a888b84f424d792af4b9202398f392".to_string() );
+        let first_return: i32 = merkle.get_hash_index( 2, "3338be694f50c5f338814986cdf0686453a888b84f424d792af4b9202398f392".parse().unwrap() );
         // The index( return value ) of the false hash's index 
-        let false_return: i32 = merkle.get_hash_index( 1, "08afda110d1caedcde7c9bde98232e01fd44ff2d4e89bf2bed8d6c38aecf6bae".to_string() );
+        let false_return: i32 = merkle.get_hash_index( 1, "08afda110d1caedcde7c9bde98232e01fd44ff2d4e89bf2bed8d6c38aecf6bae".parse().unwrap() );
         // Verifies the first hash is found at index 0 on level 1
         assert_eq!( 0, first_return );
         // Verifies the false hash wasn't found at level 1 as the function
@@ -667,7 +1073,7 @@ mod merkle_tests
         let file_name = "testing-merkle.json";
         // Writes the serialization to the output file
         #[allow(unused_variables)]
-        let temp = merkle.write_to( &file_name );
+        let temp = merkle.write_to( file_name );
         // Reads in the file with serialization to make sure it has been
         // properly created.
         let mut file = File::open( file_name ).unwrap();
@@ -689,7 +1095,7 @@ mod merkle_tests
   
         // Erase the currently existing file
         #[allow(unused_variables)]
-        let status = Command::new( "rm" ).args( &[ "-rf", "testing-merkle.json" ]
+        let status = Command::new( "rm" ).args( [ "-rf", "testing-merkle.json" ]
         ).status().expect( "Process failed ");
         // Creates a new Merkle Tree
         let mut merkle = merkle::Merkle::empty();
@@ -715,55 +1121,6 @@ mod chain_tests
     // Includes super directory 
     use super::*;
 
-    // Test flag indicating the next function contains tests
-    #[allow(dead_code)]
-    #[test]
-    // Test the chain constructor 
-    fn test_new()
-    {
-
-        // Creates a new chain 
-        let chain = chain::Chain::new();
-        // Verifies the unique id of this chain is equal to an empty hash 
-        assert_eq!( *chain.uid(), empty_hash() );
-        
-    }
-
-    // Test flag indicating the next function contains tests
-    #[allow(dead_code)]
-    #[test]
-    // Check the origin construction
-    fn test_origin()
-    {
-
-        // Creates a new chain 
-        let chain = chain::Chain::new();
-        // Creates a block with the chain's origin 
-        let block = &*chain.origin();
-        // Verifies that the block hash is the same as the empty hash 
-        assert_eq!( *block.hash(), empty_hash() );
-        
-    }
-
-    // Test flag indicating the next function contains tests
-    #[allow(dead_code)]
-    #[test]
-    // Test the contain function for the chain
-    fn test_contain()
-    {
-
-        // The chain should be mutable to add to it
-        let mut chain = chain::Chain::new();
-        let block = block::Block::new( 0, empty_hash() );
-        let key = block.hash().clone();
-        chain.push( block );
-        // This should be true because we just added this block                     
-        assert!( chain.contains( &key ) );
-        // This should be false (should not be in the tree)
-        assert!(  chain.contains( &String::from("9") ) == false );
-        
-    }
-
     // Test flag indicating the next function contains tests
     #[test]
     // JSON tests 
@@ -776,7 +1133,7 @@ mod chain_tests
         for i in 1 .. 8
         {
             
-            chain.push( block::Block::new( i, create_leaf_hash( &i ) ) );
+            chain.push( block::Block::new( i, vec![ transaction::dummy() ] ) ).unwrap();
 
         }
         // Writes to the file 
@@ -802,14 +1159,14 @@ mod chain_tests
 
         // Erase the currently existing file
         #[allow(unused_variables)]
-        let status = Command::new( "rm" ).args( &[ "-rf", "testing-chain.json" ] ).status().expect( "Process failed ");
+        let status = Command::new( "rm" ).args( [ "-rf", "testing-chain.json" ] ).status().expect( "Process failed ");
         // Creates a new chain 
         let mut chain = chain::Chain::new();
         // Inserts blocks into the chain 
         for i in 1 .. 8
         {
             
-            chain.push( block::Block::new( i, create_leaf_hash( &i ) ) );
+            chain.push( block::Block::new( i, vec![ transaction::dummy() ] ) ).unwrap();
             
         }
         // Writes to the output file
@@ -820,94 +1177,1432 @@ mod chain_tests
         let d_chain = chain::Chain::read_and_construct( "testing-chain.json" ).expect("Did not convert to d_chain");
 
     }
-}
 
-// Test flag indicating this module contains test methods
-#[cfg(test)]
-//Module for unit testing proofs
-mod proof_tests
-{
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests that a chain written before the MMR validates and starts committing with its next block
+    pub fn test_read_legacy_mmr()
+    {
 
-    // Includes super directory
-    use super::*;
+        // Blocks linked without MMR roots, as they used to be 
+        let origin = block::Block::origin();
+        let mut first = block::Block::new( 1, vec![ transaction::dummy() ] );
+        first.set_previous_hash( origin.hash() );
+        let json = | block: &block::Block, file_name: &str |
+        {
+
+            block.write_to( file_name ).unwrap();
+            let json = block::Block::read_json( file_name ).unwrap();
+            fs::remove_file( file_name ).unwrap();
+            json
+                
+        };
+        let legacy = format!( "{{\"uid\":\"{}\",\"chain\":{{\"{}\":{},\"{}\":{}}},\"tail_hash\":\"{}\"}}", empty_hash(),
+                              origin.hash(), json( &origin, "testing-legacy-origin.json" ), first.hash(), json( &first, "testing-legacy-first.json" ), first.hash() );
+        let mut file = File::create( "testing-legacy-mmr.json" ).unwrap();
+        file.write_all( legacy.as_bytes() ).unwrap();
+        let mut chain = chain::Chain::read_and_construct( "testing-legacy-mmr.json" ).unwrap();
+        fs::remove_file( "testing-legacy-mmr.json" ).unwrap();
+        chain.validate().unwrap();
+        // The tail doesn't commit, the next block does 
+        assert_eq!( None, chain.prove_block( origin.hash() ) );
+        chain.push( block::Block::new( 2, vec![ transaction::dummy() ] ) ).unwrap();
+        chain.validate().unwrap();
+        let root = *chain.get_by_hash( chain.tail_hash() ).unwrap().unwrap().mmr_root().unwrap();
+        assert!( chain.prove_block( first.hash() ).unwrap().verify( first.hash(), &root ) );
+        
+    }
 
     // Test flag indicating the next function contains tests
     #[test]
-    // Verifies that the proof process is working for small trees
-    pub fn small_tree_proof_test()
+    // Tests that the chain written by the first version of the crate ( before proof of work,
+    // bodies and the MMR ) still reads in and validates
+    pub fn test_validate_baseline()
     {
 
-        // The Merkle Tree to test against
-        let mut merkle = merkle::Merkle::new( Vec::new() );
-        // Inserts strings into the tree
-        merkle.insert( transaction::dummy() );
-        merkle.insert( transaction::dummy() );
-        merkle.insert( transaction::dummy() );
-        merkle.insert( transaction::dummy() );
-        // The proof for the value we are trying to verify 
-        let proof = merkle.get_proof( transaction::dummy() );
-        // The return value of the proof
-        let return_val = proof.verify( merkle.root_hash() );
-        // Verifies that this hash was found in the tree
-        assert_eq!( true, return_val );
+        let chain = chain::Chain::read_and_construct( "tests/fixtures/baseline-chain.json" ).unwrap();
+        chain.validate().unwrap();
+        assert_eq!( 7, chain.height() );
+        assert_eq!( 0, chain.next_difficulty() );
+        // Every header hashes as it did without a difficulty or nonce
+        for block in chain.iter()
+        {
+
+            let block = block.unwrap();
+            assert_eq!( block::HeaderVersion::V0, block.header_version() );
+            assert_eq!( 0, *block.nonce() );
+            
+        }
         
     }
 
     // Test flag indicating the next function contains tests
     #[test]
-    // Verifies that the proof process won't accept a false value
-    pub fn false_proof_test()
+    // Tests that a chain written before branches were kept ( blocks keyed by the hash of the
+    // block before them and the origin keyed by "0" ) is read in
+    pub fn test_read_legacy_keys()
     {
+
+        // Creates a chain with two blocks
+        let mut chain = chain::Chain::new();
+        chain.push( block::Block::new( 1, vec![ transaction::dummy() ] ) ).unwrap();
+        let first = *chain.tail_hash();
+        chain.push( block::Block::new( 2, vec![ transaction::dummy() ] ) ).unwrap();
+        let second = *chain.tail_hash();
+        // Rewrites the map keys the way they used to be written 
+        let key = | hash: &Digest | format!( "\"{}\":{{", hash );
+        let legacy = hand_edit( &chain, "testing-legacy.json", | json |
+        {
+
+            assert!( json.contains( &key( &second ) ) );
+            json.replace( &key( &empty_hash() ), "\"ORIGIN\":{" )
+                .replace( &key( &first ), &key( &empty_hash() ) )
+                .replace( &key( &second ), &key( &first ) )
+                .replace( "\"ORIGIN\":{", "\"0\":{" )
+                
+        } );
+        // The blocks are keyed by their own hashes again
+        assert_eq!( empty_hash(), *legacy.origin().unwrap().hash() );
+        assert_eq!( 2, legacy.height() );
+        assert!( legacy.contains( &first ) );
+        legacy.validate().unwrap();
         
-        // The Merkle Tree to test against
-        let mut merkle = merkle::Merkle::new( Vec::new() );
-        // Inserts strings into the tree
-        merkle.insert( transaction::dummy() );
-        merkle.insert( transaction::dummy() );
-        merkle.insert( transaction::dummy() );
-        merkle.insert( transaction::dummy() );
-        // False transaction
-        let false_transaction = transaction::Transaction::new( 0, "zac".to_string(),
-      "false".to_string(), "now".to_string(), "unverified".to_string() );
-   	    // The proof for the value we are trying to verify
-        //
-        // The string "false" was not entered into the tree so it should return false when
-        // trying to verify this proof. 
-        let proof = merkle.get_proof( false_transaction );
-        // The return value of the proof
-        let return_val = proof.verify( merkle.root_hash() );
-        // Verifies that this hash was found in the tree
-        assert_eq!( false, return_val );
-       
     }
-    
-}
 
+    // Builds a chain with seven blocks after the origin for the validation tests
+    pub fn validation_chain() -> chain::Chain
+    {
 
-// Tests for transaction class
-mod transaction_tests
-{
+        // Creates a new chain 
+        let mut chain = chain::Chain::new();
+        // Inserts blocks into the chain 
+        for i in 1 .. 8
+        {
+            
+            chain.push( block::Block::new( i, vec![ transaction::dummy() ] ) ).unwrap();
+            
+        }
+        chain
+        
+    }
 
-    #[allow(unused_imports)]
-    // Includes super directory 
-    use super::*;
+    // Writes the chain to a file, applies a hand edit to the JSON and reads the chain back in
+    pub fn hand_edit< S: storage::Storage, F: FnOnce( String ) -> String >( chain: &chain::Chain<S>, file_name: &str, edit: F ) -> chain::Chain
+    {
+
+        // Writes the chain out and reads its JSON back in
+        chain.write_to( file_name ).expect( "Failed to write the chain" );
+        let json = chain::Chain::read_json( file_name ).expect( "Failed to read the chain" );
+        // Writes the edited JSON over the chain and constructs a chain from it
+        let mut file = File::create( file_name ).unwrap();
+        file.write_all( edit( json ).as_bytes() ).unwrap();
+        let edited = chain::Chain::read_and_construct( file_name ).expect( "Did not convert to edited" );
+        // Cleans up the file
+        fs::remove_file( file_name ).unwrap();
+        edited
+        
+    }
+
+    // Gets the validation error a block or chain was rejected with
+    pub fn rejection<T: fmt::Debug>( result: result::Result< T, Error > ) -> chain::ValidationError
+    {
+
+        match result
+        {
+
+            Err( Error::Validation( error ) ) => error,
+            other => panic!( "Expected a validation error, got {:?}", other )
+
+        }
+
+    }
 
     // Test flag indicating the next function contains tests
     #[test]
-    // Write to test 
-    pub fn test_write_to()
+    // Tests that a hand edited header is reported as the first offending block
+    pub fn test_validate_edited_header()
     {
 
-        // Sample username 
-        let username = "ezrasucks";
-        // Sample content 
-        let content = "Hi my name is Ezra and I just sort of suck. Any advice?";
-        // Sample timestamp 
-        let timestamp = "all day every day";
+        // Changes the index of the third block without recomputing its hash
+        let chain = validation_chain();
+        let edited = hand_edit( &chain, "testing-validate-index.json",
+                                | json | json.replacen( "\"index\":3,", "\"index\":30,", 1 ) );
+        let error = rejection( edited.validate() );
+        assert_eq!( 3, error.height );
+        match error.kind
+        {
+
+            chain::ValidationErrorKind::InvalidHash{ .. } => (),
+            kind => panic!( "Unexpected validation error {:?}", kind )
+                
+        }
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests that a hand edited body is reported as the first offending block
+    pub fn test_validate_edited_body()
+    {
+
+        // Changes the content of the fifth block's transaction
+        let chain = validation_chain();
+        let edited = hand_edit( &chain, "testing-validate-body.json", | mut json |
+        {
+
+            let block = json.find( "\"index\":5," ).unwrap();
+            let content = block + json[ block .. ].find( "hello" ).unwrap();
+            json.replace_range( content .. content + 5, "HELLO" );
+            json
+                
+        } );
+        let error = rejection( edited.validate() );
+        assert_eq!( 5, error.height );
+        assert_eq!( chain::ValidationErrorKind::InvalidMerkleRoot, error.kind );
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests that hand editing a transaction field other than its content is detected
+    pub fn test_validate_edited_status()
+    {
+
+        // Changes the status of the fifth block's transaction
+        let chain = validation_chain();
+        let edited = hand_edit( &chain, "testing-validate-status.json", | mut json |
+        {
+
+            let block = json.find( "\"index\":5," ).unwrap();
+            let status = block + json[ block .. ].find( "\"unverified\"" ).unwrap();
+            json.replace_range( status .. status + 12, "\"verified\"" );
+            json
+                
+        } );
+        let error = rejection( edited.validate() );
+        assert_eq!( 5, error.height );
+        assert_eq!( chain::ValidationErrorKind::InvalidMerkleRoot, error.kind );
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests that a tail hash that can't be reached is reported
+    pub fn test_validate_missing_tail()
+    {
+
+        // Points the tail at a block that doesn't exist
+        let chain = validation_chain();
+        let tail = format!( "\"tail_hash\":\"{}\"", chain.tail_hash() );
+        let edited = hand_edit( &chain, "testing-validate-tail.json",
+                                | json | json.replacen( &tail, &format!( "\"tail_hash\":\"{}\"", create_leaf_hash( &9 ) ), 1 ) );
+        let error = rejection( edited.validate() );
+        assert_eq!( 8, error.height );
+        assert_eq!( chain::ValidationErrorKind::MissingBlock, error.kind );
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests that lookups on a chain with a missing link end and only locate the blocks that can
+    // be reached from the origin
+    pub fn test_locate_broken_chain()
+    {
+
+        let chain = validation_chain();
+        let hashes: Vec<Digest> = chain.iter().map( | block | *block.unwrap().hash() ).collect();
+        // Links the fourth block to a block that doesn't exist
+        let link = format!( "\"previous_hash\":\"{}\"", hashes[ 3 ] );
+        let edited = hand_edit( &chain, "testing-locate.json",
+                                | json | json.replacen( &link, &format!( "\"previous_hash\":\"{}\"", create_leaf_hash( &9 ) ), 1 ) );
+        // The blocks before the missing link are located, the blocks after it have no height
+        assert_eq!( 3, edited.locate( &hashes[ 3 ] ).unwrap().unwrap().1 );
+        assert!( edited.locate( &hashes[ 4 ] ).unwrap().is_none() );
+        assert!( edited.locate( &hashes[ 7 ] ).unwrap().is_none() );
+        assert!( edited.get_by_hash( &hashes[ 7 ] ).unwrap().is_some() );
+        // The tail can't be reached so there is no best branch to contain the blocks
+        assert!( !edited.contains( &hashes[ 2 ] ) );
+        assert!( !edited.contains( &hashes[ 7 ] ) );
+        assert_eq!( 0, edited.iter().len() );
+        
+    }
+    
+}
+
+// Runs the tests of what chains do against a storage backend, a module named by the first
+// argument is made with a copy of the tests. Each chain is made in memory and moved into the
+// storage the last argument opens before it is tested
+#[cfg(test)]
+macro_rules! chain_behaviour_tests
+{
+
+    ( $module: ident, $storage: ty, $open: expr ) =>
+    {
+
+        // Module for testing chains kept in the storage backend
+        mod $module
+        {
+
+            // Includes super directory 
+            use super::*;
+            // Chains are written out, hand edited and checked the same way as the chain tests
+            use chain_tests::{ hand_edit, rejection, validation_chain };
+
+            // The storage backend the tests are run against
+            type Backend = $storage;
+
+            // Moves a new chain into the storage backend
+            fn backed( chain: chain::Chain ) -> chain::Chain<Backend>
+            {
+
+                chain.into_storage( $open ).unwrap()
+
+            }
+
+            // The name of a file a test writes, the tests of each backend write their own
+            fn file_name( name: &str ) -> String
+            {
+
+                format!( "testing-{}-{}.json", stringify!( $module ), name )
+
+            }
+
+            // Test flag indicating the next function contains tests
+            #[allow(dead_code)]
+            #[test]
+            // Test the chain constructor 
+            fn test_new()
+            {
+
+                // Creates a new chain 
+                let chain = backed( chain::Chain::new() );
+                // Verifies the unique id of this chain is equal to an empty hash 
+                assert_eq!( *chain.uid(), empty_hash() );
+                
+            }
+
+            // Test flag indicating the next function contains tests
+            #[allow(dead_code)]
+            #[test]
+            // Check the origin construction
+            fn test_origin()
+            {
+
+                // Creates a new chain 
+                let chain = backed( chain::Chain::new() );
+                // Creates a block with the chain's origin 
+                let block = chain.origin().unwrap();
+                // Verifies that the block hash is the same as the empty hash 
+                assert_eq!( *block.hash(), empty_hash() );
+                
+            }
+
+            // Test flag indicating the next function contains tests
+            #[allow(dead_code)]
+            #[test]
+            // Test the contain function for the chain
+            fn test_contain()
+            {
+
+                // The chain should be mutable to add to it
+                let mut chain = backed( chain::Chain::new() );
+                let block = block::Block::new( 1, Vec::new() );
+                chain.push( block ).unwrap();
+                // Pushing links the block and commits it to the MMR, which changes its hash 
+                let key = *chain.tail_hash();
+                // This should be true because we just added this block                     
+                assert!( chain.contains( &key ) );
+                // This should be false (should not be in the tree)
+                assert!(  !chain.contains( &create_leaf_hash( &9 ) ) );
+                
+            }
+
+            // Test flag indicating the next function contains tests
+            #[test]
+            // Tests that a chain with a difficulty only accepts mined blocks
+            pub fn test_push_difficulty()
+            {
+
+                // Creates a chain that requires 8 leading zero bits
+                let mut chain = backed( chain::Chain::with_difficulty( 8 ) );
+                assert_eq!( 8, chain.difficulty() );
+                // A block that doesn't record the difficulty is rejected
+                let mut block = block::Block::new( 1, vec![ transaction::dummy() ] );
+                let error = rejection( chain.push( block.clone() ) );
+                assert_eq!( chain::ValidationErrorKind::UnexpectedDifficulty{ expected: 8, found: 0 }, error.kind );
+                // A linked block whose hash doesn't meet the difficulty is rejected
+                block.difficulty = 8;
+                block.set_previous_hash( chain.tail_hash() );
+                let mut nonce = 0;
+                while block.meets_difficulty( 8 )
+                {
+
+                    nonce += 1;
+                    block.set_nonce( nonce );
+                    
+                }
+                let error = rejection( chain.push( block.clone() ) );
+                assert_eq!( chain::ValidationErrorKind::InsufficientWork{ difficulty: 8 }, error.kind );
+                assert_eq!( 1, chain.len() );
+                // Once the block has been mined it is accepted
+                chain.prepare( &mut block );
+                chain.push( block ).unwrap();
+                let mut next = block::Block::new( 2, vec![ transaction::dummy() ] );
+                chain.prepare( &mut next );
+                chain.push( next ).unwrap();
+                assert_eq!( 3, chain.len() );
+                chain.validate().unwrap();
+                
+            }
+
+            // Pushes a mined block onto the chain that was created the given number of seconds after
+            // the origin
+            fn push_at( chain: &mut chain::Chain<Backend>, index: u64, seconds: i64 )
+            {
+
+                let origin = *chain.origin().unwrap().timestamp();
+                let mut block = block::Block::new( index, vec![ transaction::dummy() ] );
+                block.timestamp = origin + chrono::Duration::seconds( seconds );
+                chain.prepare( &mut block );
+                chain.push( block ).unwrap();
+                
+            }
+
+            // Test flag indicating the next function contains tests
+            #[test]
+            // Tests that the difficulty follows the time blocks take and that it is replayed by validate
+            pub fn test_retarget()
+            {
+
+                // Blocks should be a minute apart and the difficulty is retargeted every 4 blocks
+                let mut chain = backed( chain::Chain::with_retarget( 2, 60_000, 4 ) );
+                // The first window uses the initial difficulty
+                for i in 1 .. 5
+                {
+
+                    assert_eq!( 2, chain.next_difficulty() );
+                    push_at( &mut chain, i, i as i64 );
+                    
+                }
+                // The first window took 4 seconds instead of 4 minutes so the difficulty goes up
+                assert_eq!( 3, chain.next_difficulty() );
+                for i in 5 .. 9
+                {
+
+                    assert_eq!( 3, chain.next_difficulty() );
+                    push_at( &mut chain, i, i as i64 );
+                    
+                }
+                // Still too fast so the difficulty goes up again
+                assert_eq!( 4, chain.next_difficulty() );
+                for i in 9 .. 13
+                {
+
+                    push_at( &mut chain, i, 8 + ( i as i64 - 8 ) * 60 );
+                    
+                }
+                // On target so the difficulty is kept
+                assert_eq!( 4, chain.next_difficulty() );
+                for i in 13 .. 17
+                {
+
+                    push_at( &mut chain, i, 248 + ( i as i64 - 12 ) * 600 );
+                    
+                }
+                // Far too slow so the difficulty goes down
+                assert_eq!( 3, chain.next_difficulty() );
+                // The recorded difficulties are replayed from the origin
+                chain.validate().unwrap();
+                // A block mined with the old difficulty is rejected
+                let mut block = block::Block::new( 17, vec![ transaction::dummy() ] );
+                block.set_previous_hash( chain.tail_hash() );
+                block.mine( 4 );
+                let error = rejection( chain.push( block ) );
+                assert_eq!( chain::ValidationErrorKind::UnexpectedDifficulty{ expected: 3, found: 4 }, error.kind );
+                
+            }
+
+            // Test flag indicating the next function contains tests
+            #[test]
+            // Tests that a side branch is retargeted from the times of its own blocks
+            pub fn test_retarget_side_branch()
+            {
+
+                // The main branch is too fast twice so its next block needs a difficulty of 4
+                let mut chain = backed( chain::Chain::with_retarget( 2, 60_000, 4 ) );
+                for i in 1 .. 9
+                {
+
+                    push_at( &mut chain, i, i as i64 );
+                    
+                }
+                assert_eq!( 4, chain.next_difficulty() );
+                // A side branch from height 4 takes ten minutes a block
+                let origin = *chain.origin().unwrap().timestamp();
+                let mut parent = *chain.get_by_height( 4 ).unwrap().unwrap().hash();
+                for i in 5 .. 10
+                {
+
+                    let mut block = block::Block::new( i, vec![ transaction::dummy() ] );
+                    block.timestamp = origin + chrono::Duration::seconds( 4 + ( i as i64 - 4 ) * 600 );
+                    chain.prepare_on( &parent, &mut block );
+                    parent = *block.hash();
+                    if i == 9
+                    {
+
+                        // Its last window was far too slow so the difficulty goes down
+                        assert_eq!( 2, *block.difficulty() );
+                        
+                    }
+                    chain.accept( block ).unwrap();
+                    
+                }
+                // The side branch has more work once it is a block longer
+                assert_eq!( parent, *chain.tail_hash() );
+                assert_eq!( 2, chain.next_difficulty() );
+                chain.validate().unwrap();
+                
+            }
+
+            // Test flag indicating the next function contains tests
+            #[test]
+            // Tests that pushed blocks are given the index and timestamp the tail needs
+            pub fn test_push_links()
+            {
+
+                let mut chain = backed( chain::Chain::new() );
+                for i in 1 .. 5
+                {
+
+                    push_at( &mut chain, i, i as i64 * 10 );
+                    
+                }
+                // Blocks made without an index or with a timestamp before the median time past
+                let origin = *chain.origin().unwrap().timestamp();
+                for _ in 0 .. 3
+                {
+
+                    let mut block = block::Block::new( 0, vec![ transaction::dummy() ] );
+                    block.timestamp = origin;
+                    chain.push( block ).unwrap();
+                    
+                }
+                assert_eq!( 7, chain.height() );
+                let tail = chain.get_by_height( 7 ).unwrap().unwrap();
+                assert_eq!( 7, *tail.index() );
+                assert!( *tail.timestamp() > origin );
+                chain.validate().unwrap();
+                // A block that already comes after the tail keeps its index
+                chain.push( block::Block::new( 10, vec![ transaction::dummy() ] ) ).unwrap();
+                assert_eq!( 10, *chain.get_by_height( 8 ).unwrap().unwrap().index() );
+                
+            }
+
+            // Test flag indicating the next function contains tests
+            #[test]
+            // Tests that blocks can't be timestamped before the median time past or far in the future
+            pub fn test_timestamp_rules()
+            {
+
+                let mut chain = backed( chain::Chain::new() );
+                for i in 1 .. 5
+                {
+
+                    push_at( &mut chain, i, i as i64 * 10 );
+                    
+                }
+                // The median of the origin and the blocks 10, 20, 30 and 40 seconds after it
+                let origin = *chain.origin().unwrap().timestamp();
+                let median = origin + chrono::Duration::seconds( 20 );
+                assert_eq!( Some( median ), chain.median_time_past_after( chain.tail_hash() ) );
+                assert_eq!( None, chain.median_time_past_after( &create_leaf_hash( &9 ) ) );
+                let at = | chain: &chain::Chain<Backend>, seconds: i64 |
+                {
+
+                    let mut block = block::Block::new( 5, vec![ transaction::dummy() ] );
+                    block.timestamp = origin + chrono::Duration::seconds( seconds );
+                    chain.prepare( &mut block );
+                    block
+                        
+                };
+                // Earlier than the median is rejected
+                let error = rejection( chain.accept( at( &chain, 15 ) ) );
+                assert_eq!( chain::ValidationErrorKind::TimestampBeforeMedian{ median }, error.kind );
+                // Earlier than the block before it but not the median is accepted
+                chain.accept( at( &chain, 25 ) ).unwrap();
+                // Too far ahead of the current time is rejected
+                let drift = chain::MAX_FUTURE_DRIFT + 60;
+                let mut block = block::Block::new( 6, vec![ transaction::dummy() ] );
+                block.timestamp = timestamp::now() + chrono::Duration::seconds( drift );
+                chain.prepare( &mut block );
+                match rejection( chain.accept( block ) ).kind
+                {
+
+                    chain::ValidationErrorKind::TimestampTooFarAhead{ .. } => (),
+                    kind => panic!( "Unexpected validation error {:?}", kind )
+                        
+                }
+                chain.validate().unwrap();
+                
+            }
+
+            // Test flag indicating the next function contains tests
+            #[test]
+            // Tests that a block whose legacy Merkle Tree duplicates siblings is rejected
+            pub fn test_ambiguous_merkle_root()
+            {
+
+                let mut chain = backed( chain::Chain::new() );
+                let post = | content: &str | transaction::Transaction::new( 0, "zac".to_string(), content.to_string(),
+                                                                             timestamp::from_millis( 0 ).unwrap(), "verified".to_string() );
+                // A V1 block whose last transaction is duplicated
+                let mut block = block::Block::new( 1, vec![ post( "a" ), post( "b" ), post( "c" ), post( "c" ) ] );
+                block.tree_version = tree::TreeVersion::V1;
+                block.merkle_root = *block.merkle().root_hash();
+                assert!( block.verify_merkle_root() );
+                assert!( block.has_ambiguous_merkle_root() );
+                chain.prepare( &mut block );
+                let error = rejection( chain.push( block ) );
+                assert_eq!( chain::ValidationErrorKind::AmbiguousMerkleRoot, error.kind );
+                // The same body in a current block is accepted
+                chain.push( block::Block::new( 1, vec![ post( "a" ), post( "b" ), post( "c" ), post( "c" ) ] ) ).unwrap();
+                
+            }
+
+            // Test flag indicating the next function contains tests
+            #[test]
+            // Tests that each header commits to the blocks before it and past blocks can be proven
+            // against the latest header 
+            pub fn test_mmr_commitment()
+            {
+
+                let mut chain = backed( chain::Chain::new() );
+                let mut hashes = vec![ empty_hash() ];
+                for index in 1 .. 10
+                {
+
+                    chain.push( block::Block::new( index, vec![ transaction::dummy() ] ) ).unwrap();
+                    let tail = chain.get_by_hash( chain.tail_hash() ).unwrap().unwrap();
+                    // The header's root is the MMR of every block before it 
+                    assert_eq!( mmr::Mmr::from_hashes( &hashes ).root(), *tail.mmr_root().unwrap() );
+                    hashes.push( *tail.hash() );
+                    
+                }
+                // Every block before the tail is proven against the tail's header 
+                let tail = chain.get_by_hash( chain.tail_hash() ).unwrap().unwrap();
+                for hash in &hashes[ .. 9 ]
+                {
+
+                    let proof = chain.prove_block( hash ).unwrap();
+                    assert!( proof.verify( hash, tail.mmr_root().unwrap() ) );
+                    assert!( !proof.verify( chain.tail_hash(), tail.mmr_root().unwrap() ) );
+                    
+                }
+                // The tail itself is the header and unknown blocks have no proof
+                assert_eq!( None, chain.prove_block( chain.tail_hash() ) );
+                assert_eq!( None, chain.prove_block( &create_leaf_hash( &9 ) ) );
+                // A block committing to the wrong root is rejected 
+                let mut block = block::Block::new( 10, vec![ transaction::dummy() ] );
+                chain.prepare( &mut block );
+                block.set_mmr_root( Some( empty_hash() ) );
+                let error = rejection( chain.accept( block ) );
+                assert_eq!( chain::ValidationErrorKind::UnexpectedMmrRoot{ expected: Some( mmr::Mmr::from_hashes( &hashes ).root() ), found: Some( empty_hash() ) }, error.kind );
+                chain.validate().unwrap();
+                
+            }
+
+            // Test flag indicating the next function contains tests
+            #[test]
+            // Tests that a block extending any block of the best branch commits to the best
+            // branch's MMR up to that block, which is read in place rather than rebuilt
+            pub fn test_mmr_root_on_best_branch()
+            {
+
+                let mut chain = backed( chain::Chain::new() );
+                let mut hashes = vec![ empty_hash() ];
+                for index in 1 .. 8
+                {
+
+                    chain.push( block::Block::new( index, vec![ transaction::dummy() ] ) ).unwrap();
+                    hashes.push( *chain.tail_hash() );
+                    
+                }
+                for ( height, hash ) in hashes.iter().enumerate()
+                {
+
+                    assert_eq!( Some( mmr::Mmr::from_hashes( &hashes[ .. height + 1 ] ).root() ), chain.next_mmr_root_after( hash ) );
+                    
+                }
+                // A side branch forking from the middle of the best branch commits to the best
+                // branch up to the fork point
+                let mut block = block::Block::new( 9, vec![ transaction::dummy() ] );
+                chain.prepare_on( &hashes[ 3 ], &mut block );
+                assert_eq!( Some( &mmr::Mmr::from_hashes( &hashes[ .. 4 ] ).root() ), block.mmr_root() );
+                let side = *block.hash();
+                chain.accept( block ).unwrap();
+                let mut expected = hashes[ .. 4 ].to_vec();
+                expected.push( side );
+                assert_eq!( Some( mmr::Mmr::from_hashes( &expected ).root() ), chain.next_mmr_root_after( &side ) );
+                
+            }
+
+            // Test flag indicating the next function contains tests
+            #[test]
+            // Tests that the MMR follows the best branch through a reorganization 
+            pub fn test_mmr_reorg()
+            {
+
+                let mut chain = backed( chain::Chain::new() );
+                let origin = *chain.origin().unwrap().hash();
+                let a1 = branch( &chain, 1, &origin );
+                let a1_hash = *a1.hash();
+                chain.accept( a1 ).unwrap();
+                let a2 = branch( &chain, 2, &a1_hash );
+                chain.accept( a2 ).unwrap();
+                // A longer branch from the origin 
+                let mut parent = origin;
+                let mut side = vec![ origin ];
+                for index in 1 .. 4
+                {
+
+                    let block = branch( &chain, index, &parent );
+                    parent = *block.hash();
+                    side.push( parent );
+                    chain.accept( block ).unwrap();
+                    
+                }
+                assert_eq!( parent, *chain.tail_hash() );
+                // The old branch can't be proven, the new one can 
+                assert_eq!( None, chain.prove_block( &a1_hash ) );
+                let root = *chain.get_by_hash( chain.tail_hash() ).unwrap().unwrap().mmr_root().unwrap();
+                for hash in &side[ .. 3 ]
+                {
+
+                    assert!( chain.prove_block( hash ).unwrap().verify( hash, &root ) );
+                    
+                }
+                // The next block commits to the new branch 
+                chain.push( block::Block::new( 4, vec![ transaction::dummy() ] ) ).unwrap();
+                assert_eq!( mmr::Mmr::from_hashes( &side ).root(), *chain.get_by_hash( chain.tail_hash() ).unwrap().unwrap().mmr_root().unwrap() );
+                chain.validate().unwrap();
+                
+            }
+
+            // Test flag indicating the next function contains tests
+            #[test]
+            // Tests that a chain hashes its blocks with its algorithm and keeps it when written out
+            pub fn test_hash_algorithm()
+            {
+
+                let algorithm = hash_util::HashAlgorithm::Blake2b;
+                let mut chain = backed( chain::Chain::with_hash_algorithm( algorithm ) );
+                assert_eq!( algorithm, chain.origin().unwrap().hash_algorithm() );
+                // Pushed blocks are rehashed with the chain's algorithm 
+                for index in 1 .. 4
+                {
+
+                    chain.push( block::Block::new( index, vec![ transaction::dummy() ] ) ).unwrap();
+                    
+                }
+                let tail = chain.get_by_hash( chain.tail_hash() ).unwrap().unwrap().clone();
+                assert_eq!( algorithm, tail.hash_algorithm() );
+                assert_eq!( *merkle::Merkle::with_algorithm( vec![ transaction::dummy() ], tree::TreeVersion::current(), algorithm ).root_hash(), tail.merkle_root );
+                chain.validate().unwrap();
+                let proof = chain.prove_block( tail.previous_hash() ).unwrap();
+                assert_eq!( algorithm, proof.algorithm() );
+                assert!( proof.verify( tail.previous_hash(), tail.mmr_root().unwrap() ) );
+                // A block hashed with another algorithm is rejected
+                let mut block = block::Block::new( 4, vec![ transaction::dummy() ] );
+                chain.prepare( &mut block );
+                block.set_hash_algorithm( hash_util::HashAlgorithm::Sha256 );
+                let error = rejection( chain.accept( block ) );
+                assert_eq!( chain::ValidationErrorKind::UnexpectedHashAlgorithm{ expected: algorithm, found: hash_util::HashAlgorithm::Sha256 }, error.kind );
+                // The algorithm is read back in with the chain
+                chain.write_to( &file_name( "algorithm" ) ).unwrap();
+                let read = chain::Chain::read_and_construct( &file_name( "algorithm" ) ).unwrap();
+                assert_eq!( algorithm, read.hash_algorithm() );
+                read.validate().unwrap();
+                // A file claiming another algorithm doesn't validate, the chain's algorithm is written
+                // after its blocks
+                let json = chain::Chain::read_json( &file_name( "algorithm" ) ).unwrap();
+                let at = json.rfind( "Blake2b" ).unwrap();
+                let mut file = File::create( &file_name( "algorithm" ) ).unwrap();
+                file.write_all( format!( "{}Sha256{}", &json[ .. at ], &json[ at + 7 .. ] ).as_bytes() ).unwrap();
+                let edited = chain::Chain::read_and_construct( &file_name( "algorithm" ) ).unwrap();
+                fs::remove_file( &file_name( "algorithm" ) ).unwrap();
+                let error = rejection( edited.validate() );
+                assert_eq!( 0, error.height );
+                assert_eq!( chain::ValidationErrorKind::UnexpectedHashAlgorithm{ expected: hash_util::HashAlgorithm::Sha256, found: algorithm }, error.kind );
+                
+            }
+
+            // Test flag indicating the next function contains tests
+            #[test]
+            // Tests that a branch can't go back to the legacy header encoding
+            pub fn test_header_version_decreasing()
+            {
+
+                let mut chain = backed( chain::Chain::new() );
+                chain.push( block::Block::new( 1, vec![ transaction::dummy() ] ) ).unwrap();
+                let mut block = block::Block::new( 2, vec![ transaction::dummy() ] );
+                chain.prepare( &mut block );
+                block.set_header_version( block::HeaderVersion::V0 );
+                let error = rejection( chain.accept( block ) );
+                assert_eq!( chain::ValidationErrorKind::HeaderVersionDecreasing{ previous: block::HeaderVersion::V1, found: block::HeaderVersion::V0 }, error.kind );
+                chain.validate().unwrap();
+                
+            }
+
+            // Creates a block extending the given parent on any branch
+            fn branch( chain: &chain::Chain<Backend>, index: u64, parent: &Digest ) -> block::Block
+            {
+
+                let mut block = block::Block::new( index, vec![ transaction::dummy() ] );
+                chain.prepare_on( parent, &mut block );
+                block
+                
+            }
+
+            // Test flag indicating the next function contains tests
+            #[test]
+            // Tests that competing branches are kept and the tail follows the branch with the most work
+            pub fn test_fork()
+            {
+
+                // The main branch has two blocks
+                let mut chain = backed( chain::Chain::new() );
+                let origin = *chain.origin().unwrap().hash();
+                let a1 = branch( &chain, 1, &origin );
+                let a1_hash = *a1.hash();
+                assert_eq!( chain::Reorg{ disconnected: vec![], connected: vec![ a1_hash ] }, chain.accept( a1 ).unwrap() );
+                let a2 = branch( &chain, 2, &a1_hash );
+                let a2_hash = *a2.hash();
+                chain.accept( a2 ).unwrap();
+                // A competing branch from the origin doesn't move the tail until it has more work 
+                let b1 = branch( &chain, 1, &origin );
+                let b1_hash = *b1.hash();
+                assert_eq!( chain::Reorg::default(), chain.accept( b1 ).unwrap() );
+                let b2 = branch( &chain, 2, &b1_hash );
+                let b2_hash = *b2.hash();
+                // Ties keep the tail that was seen first
+                assert_eq!( chain::Reorg::default(), chain.accept( b2 ).unwrap() );
+                assert_eq!( a2_hash, *chain.tail_hash() );
+                // The third block overtakes the main branch
+                let b3 = branch( &chain, 3, &b2_hash );
+                let b3_hash = *b3.hash();
+                let reorg = chain.accept( b3 ).unwrap();
+                assert_eq!( vec![ a2_hash, a1_hash ], reorg.disconnected );
+                assert_eq!( vec![ b1_hash, b2_hash, b3_hash ], reorg.connected );
+                assert_eq!( b3_hash, *chain.tail_hash() );
+                assert_eq!( 3, chain.height() );
+                assert_eq!( 3, chain.work() );
+                // Every branch is kept but only the best branch is contained
+                assert_eq!( 6, chain.len() );
+                assert!( chain.contains( &b2_hash ) );
+                assert!( !chain.contains( &a1_hash ) );
+                // Pushing extends the new tail
+                chain.push( block::Block::new( 4, vec![ transaction::dummy() ] ) ).unwrap();
+                assert_eq!( 4, chain.height() );
+                chain.validate().unwrap();
+                // The branches and tail survive serialization
+                let d_chain = hand_edit( &chain, &file_name( "fork" ), | json | json );
+                assert_eq!( chain.tail_hash(), d_chain.tail_hash() );
+                assert_eq!( 4, d_chain.height() );
+                assert_eq!( 7, d_chain.len() );
+                d_chain.validate().unwrap();
+                
+            }
+
+            // Test flag indicating the next function contains tests
+            #[test]
+            // Tests that blocks that don't extend a known block or are already stored are rejected
+            pub fn test_accept_rejects()
+            {
+
+                let mut chain = backed( chain::Chain::new() );
+                let origin = *chain.origin().unwrap().hash();
+                let block = branch( &chain, 1, &origin );
+                chain.accept( block.clone() ).unwrap();
+                // The same block can't be accepted twice
+                let error = rejection( chain.accept( block.clone() ) );
+                assert_eq!( chain::ValidationErrorKind::DuplicateBlock, error.kind );
+                assert_eq!( 1, error.height );
+                // A block whose parent isn't in the chain is rejected
+                let orphan = branch( &chain, 2, &create_leaf_hash( &9 ) );
+                let error = rejection( chain.accept( orphan ) );
+                assert_eq!( chain::ValidationErrorKind::UnknownParent, error.kind );
+                // A block with an index that doesn't increase is rejected
+                let stale = branch( &chain, 1, block.hash() );
+                let error = rejection( chain.accept( stale ) );
+                assert_eq!( chain::ValidationErrorKind::IndexNotIncreasing{ previous: 1, found: 1 }, error.kind );
+                assert_eq!( 2, chain.len() );
+                
+            }
+
+            // Test flag indicating the next function contains tests
+            #[test]
+            // Tests that a well formed chain validates before and after serialization
+            pub fn test_validate()
+            {
+
+                // An empty chain is valid
+                backed( chain::Chain::new() ).validate().unwrap();
+                // A chain with blocks is valid
+                let chain = backed( validation_chain() );
+                chain.validate().unwrap();
+                // A chain that has been written and read back in is still valid
+                let d_chain = hand_edit( &chain, &file_name( "validate" ), | json | json );
+                d_chain.validate().unwrap();
+                
+            }
+
+            // Test flag indicating the next function contains tests
+            #[test]
+            // Tests that blocks on the best branch are found by height and iterated in order
+            pub fn test_height_index()
+            {
+
+                let mut chain = backed( chain::Chain::new() );
+                let mut hashes = vec![ *chain.origin().unwrap().hash() ];
+                for index in 1 .. 6
+                {
+
+                    chain.push( block::Block::new( index, vec![ transaction::dummy() ] ) ).unwrap();
+                    hashes.push( *chain.tail_hash() );
+                    
+                }
+                // Blocks are found by height and by hash
+                assert_eq!( empty_hash(), *chain.get_by_height( 0 ).unwrap().unwrap().hash() );
+                assert_eq!( hashes[ 3 ], *chain.get_by_height( 3 ).unwrap().unwrap().hash() );
+                assert_eq!( 3, *chain.get_by_hash( &hashes[ 3 ] ).unwrap().unwrap().index() );
+                assert!( chain.get_by_height( 6 ).unwrap().is_none() );
+                assert!( chain.get_by_hash( &create_leaf_hash( &9 ) ).unwrap().is_none() );
+                // Iterates from the origin to the tail and back
+                let forward: Vec<Digest> = chain.iter().map( | block | *block.unwrap().hash() ).collect();
+                assert_eq!( hashes, forward );
+                let reverse: Vec<Digest> = chain.iter().rev().map( | block | *block.unwrap().hash() ).collect();
+                assert_eq!( hashes.iter().rev().cloned().collect::< Vec<Digest> >(), reverse );
+                assert_eq!( 6, chain.iter().len() );
+                // Ranges are clamped to the tail
+                let heights = | blocks: chain::Blocks<Backend> | blocks.map( | block | *block.unwrap().index() ).collect::< Vec<u64> >();
+                assert_eq!( vec![ 2, 3, 4 ], heights( chain.range( 2 .. 5 ) ) );
+                assert_eq!( vec![ 2, 3, 4, 5 ], heights( chain.range( 2 ..= 5 ) ) );
+                assert_eq!( vec![ 4, 5 ], heights( chain.range( 4 .. ) ) );
+                assert_eq!( vec![ 0, 1 ], heights( chain.range( .. 2 ) ) );
+                assert_eq!( vec![ 5, 4 ], chain.range( 4 .. 100 ).rev().map( | block | *block.unwrap().index() ).collect::< Vec<u64> >() );
+                assert!( chain.range( 7 .. 9 ).next().is_none() );
+                assert!( chain.range( ( ops::Bound::Included( 4 ), ops::Bound::Excluded( 2 ) ) ).next().is_none() );
+                
+            }
+
+            // Test flag indicating the next function contains tests
+            #[test]
+            // Tests that the height index follows the tail through a reorg and is rebuilt when a chain
+            // is read in
+            pub fn test_height_index_reorg()
+            {
+
+                let mut chain = backed( chain::Chain::new() );
+                let origin = *chain.origin().unwrap().hash();
+                let a1 = branch( &chain, 1, &origin );
+                let a1_hash = *a1.hash();
+                chain.accept( a1 ).unwrap();
+                let a2 = branch( &chain, 2, &a1_hash );
+                chain.accept( a2 ).unwrap();
+                // A side branch isn't indexed until it overtakes the best branch
+                let b1 = branch( &chain, 1, &origin );
+                let b1_hash = *b1.hash();
+                chain.accept( b1 ).unwrap();
+                let b2 = branch( &chain, 2, &b1_hash );
+                let b2_hash = *b2.hash();
+                chain.accept( b2 ).unwrap();
+                assert_eq!( a1_hash, *chain.get_by_height( 1 ).unwrap().unwrap().hash() );
+                // The side branch is still found by hash
+                assert_eq!( b1_hash, *chain.get_by_hash( &b1_hash ).unwrap().unwrap().hash() );
+                let b3 = branch( &chain, 3, &b2_hash );
+                let b3_hash = *b3.hash();
+                chain.accept( b3 ).unwrap();
+                let best = vec![ origin, b1_hash, b2_hash, b3_hash ];
+                fn hashes< S: storage::Storage >( chain: &chain::Chain<S> ) -> Vec<Digest>
+                {
+
+                    chain.iter().map( | block | *block.unwrap().hash() ).collect()
+
+                }
+                assert_eq!( best, hashes( &chain ) );
+                assert_eq!( b3_hash, *chain.get_by_height( 3 ).unwrap().unwrap().hash() );
+                // The index is rebuilt when the chain is read in
+                let d_chain = hand_edit( &chain, &file_name( "height" ), | json | json );
+                assert_eq!( best, hashes( &d_chain ) );
+                assert_eq!( b2_hash, *d_chain.get_by_height( 2 ).unwrap().unwrap().hash() );
+                
+            }
+
+            // Test flag indicating the next function contains tests
+            #[test]
+            // Tests that blocks are located with their heights and membership is checked against the
+            // best branch
+            pub fn test_locate()
+            {
+
+                let mut chain = backed( chain::Chain::new() );
+                let origin = *chain.origin().unwrap().hash();
+                let a1 = branch( &chain, 1, &origin );
+                let a1_hash = *a1.hash();
+                chain.accept( a1 ).unwrap();
+                let b1 = branch( &chain, 1, &origin );
+                let b1_hash = *b1.hash();
+                chain.accept( b1 ).unwrap();
+                let a2 = branch( &chain, 2, &a1_hash );
+                let a2_hash = *a2.hash();
+                chain.accept( a2 ).unwrap();
+                // Blocks on every branch are located with their heights
+                let ( block, height ) = chain.locate( &a2_hash ).unwrap().unwrap();
+                assert_eq!( a2_hash, *block.hash() );
+                assert_eq!( 2, height );
+                assert_eq!( 1, chain.locate( &b1_hash ).unwrap().unwrap().1 );
+                assert_eq!( 0, chain.locate( &origin ).unwrap().unwrap().1 );
+                assert!( chain.locate( &create_leaf_hash( &9 ) ).unwrap().is_none() );
+                // Only blocks on the best branch are contained
+                assert!( chain.contains( &origin ) );
+                assert!( chain.contains( &a1_hash ) );
+                assert!( chain.contains( &a2_hash ) );
+                assert!( !chain.contains( &b1_hash ) );
+                assert!( !chain.contains( &create_leaf_hash( &9 ) ) );
+                
+            }
+            
+        }
+
+    }
+
+}
+
+// Runs the chain tests against chains kept in memory
+#[cfg(test)]
+chain_behaviour_tests!( memory_chain_tests, storage::MemoryStorage, storage::MemoryStorage::new() );
+
+// Runs the chain tests against chains kept in sled
+#[cfg(all(test, feature = "sled"))]
+chain_behaviour_tests!( sled_chain_tests, storage::SledStorage, storage::SledStorage::temporary().unwrap() );
+
+// Test flag indicating this module contains test methods
+#[cfg(test)]
+//Module for unit testing proofs
+mod proof_tests
+{
+
+    // Includes super directory
+    use super::*;
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Verifies that the proof process is working for small trees
+    pub fn small_tree_proof_test()
+    {
+
+        // The Merkle Tree to test against
+        let mut merkle = merkle::Merkle::new( Vec::new() );
+        // Inserts strings into the tree
+        merkle.insert( transaction::dummy() );
+        merkle.insert( transaction::dummy() );
+        merkle.insert( transaction::dummy() );
+        merkle.insert( transaction::dummy() );
+        // The proof for the value we are trying to verify 
+        let proof = merkle.get_proof( transaction::dummy() ).unwrap();
+        // The return value of the proof
+        let return_val = proof.verify( merkle.root_hash() );
+        // Verifies that this hash was found in the tree
+        assert!( return_val );
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Verifies that proofs against legacy ( V0 ) roots still verify and that a proof is only
+    // valid for the version it was made with
+    pub fn legacy_proof_test()
+    {
+
+        // A legacy tree and a domain separated tree over the same values
+        let values = vec![ transaction::dummy(), transaction::dummy(), transaction::dummy() ];
+        let legacy = merkle::Merkle::with_version( values.clone(), tree::TreeVersion::V0 );
+        let current = merkle::Merkle::new( values );
+        // The legacy proof verifies against the legacy root only 
+        let proof = legacy.get_proof( transaction::dummy() ).unwrap();
+        assert!( proof.verify( legacy.root_hash() ) );
+        assert!( !proof.verify( current.root_hash() ) );
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Verifies proofs for every leaf of trees with promoted nodes 
+    pub fn promoted_proof_test()
+    {
+
+        for count in 1 .. 12
+        {
+
+            // A tree with a distinct value in each leaf
+            let values: Vec<transaction::Transaction> = ( 0 .. count ).map( | i |
+            {
+
+                transaction::Transaction::new( i, "zac".to_string(), i.to_string(), timestamp::from_millis( 0 ).unwrap(), "verified".to_string() )
+                    
+            } ).collect();
+            let merkle = merkle::Merkle::new( values.clone() );
+            // Every leaf proves against the root
+            for value in values
+            {
+
+                let proof = merkle.get_proof( value ).unwrap();
+                assert!( proof.verify( merkle.root_hash() ) );
+                
+            }
+            
+        }
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Verifies that a proof commits to every field of the transaction, not just its content
+    pub fn id_proof_test()
+    {
+
+        let merkle = merkle::Merkle::new( vec![ transaction::dummy(), transaction::dummy(), transaction::dummy() ] );
+        assert!( merkle.get_proof( transaction::dummy() ).unwrap().verify( merkle.root_hash() ) );
+        // The same content with a different status isn't in the tree
+        let mut verified = transaction::dummy();
+        verified.verify();
+        assert_eq!( verified.get_value(), transaction::dummy().get_value() );
+        assert_eq!( Err( proof::ProofError::LeafNotFound ), merkle.get_proof( verified ).map( | proof | proof.index() ) );
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Verifies that proofs are made by position, so leaves with the same content each get
+    // their own proof and a proof only verifies at its own index
+    pub fn index_proof_test()
+    {
+
+        for &version in &[ tree::TreeVersion::V0, tree::TreeVersion::V1, tree::TreeVersion::current() ]
+        {
+
+            for count in 1 .. 10
+            {
+
+                let merkle = merkle::Merkle::with_version( vec![ transaction::dummy(); count ], version );
+                for index in 0 .. count
+                {
+
+                    // Every position proves against the root
+                    let proof = merkle.get_proof_at( index ).unwrap();
+                    assert_eq!( index, proof.index() );
+                    assert_eq!( count, proof.leaf_count() );
+                    assert!( proof.verify( merkle.root_hash() ) );
+                    // The same path doesn't verify at any other position
+                    for other in 0 .. count + 1
+                    {
+
+                        if other != index
+                        {
+
+                            let moved = proof::Proof::new( version, HashAlgorithm::default(), transaction::dummy(), *merkle.root_hash(), other, count,
+                                                           merkle.get_proof_hashes_at( index ).unwrap() );
+                            assert!( !moved.verify( merkle.root_hash() ) );
+                            
+                        }
+                        
+                    }
+                    
+                }
+                // There is no leaf past the end of the tree
+                assert_eq!( Err( proof::ProofError::IndexOutOfRange{ index: count, leaf_count: count } ),
+                            merkle.get_proof_at( count ).map( | proof | proof.index() ) );
+                
+            }
+            
+        }
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Verifies that proofs survive the json and binary encodings and can be checked against
+    // a block's Merkle Root without the tree
+    pub fn encoded_proof_test()
+    {
+
+        // A block with five posts
+        let posts: Vec<transaction::Transaction> = ( 0 .. 5 ).map( | i |
+        {
+
+            transaction::Transaction::new( i, "zac".to_string(), format!( "post {}", i ), timestamp::from_millis( 0 ).unwrap(), "verified".to_string() )
+                
+        } ).collect();
+        let block = block::Block::new( 1, posts.clone() );
+        let proof = block.merkle().get_proof_at( 4 ).unwrap();
+        // The accessors describe the proof
+        assert_eq!( posts[ 4 ], *proof.value() );
+        assert_eq!( block.merkle_root, *proof.root() );
+        assert_eq!( tree::TreeVersion::current(), proof.version() );
+        assert_eq!( 1, proof.path().len() );
+        // Json round trip
+        let json = proof.to_json().unwrap();
+        assert!( json.contains( "\"index\":4" ) );
+        let from_json = proof::Proof::from_json( &json ).unwrap();
+        assert_eq!( proof, from_json );
+        // Binary round trip 
+        let bytes = proof.to_bytes();
+        assert_eq!( 1 + 8 + 8 + 32 + 1 + 33 + posts[ 4 ].encode().len(), bytes.len() );
+        let from_bytes = proof::Proof::from_bytes( &bytes ).unwrap();
+        assert_eq!( proof, from_bytes );
+        // The decoded proof verifies against the block's root without the tree 
+        assert!( proof::verify_inclusion( from_bytes.version(), from_bytes.algorithm(), from_bytes.value(), from_bytes.index(),
+                                                    from_bytes.leaf_count(), from_bytes.path(), &block.merkle_root ) );
+        // Truncated or padded bytes aren't a proof 
+        assert_eq!( Err( proof::ProofError::InvalidEncoding ), proof::Proof::from_bytes( &bytes[ .. bytes.len() - 1 ] ) );
+        let mut padded = bytes.clone();
+        padded.push( 0 );
+        assert_eq!( Err( proof::ProofError::InvalidEncoding ), proof::Proof::from_bytes( &padded ) );
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests that proofs carry the tree's hash algorithm and only verify with it
+    pub fn algorithm_proof_test()
+    {
+
+        let posts: Vec<transaction::Transaction> = ( 0 .. 5 ).map( | i |
+        {
+
+            transaction::Transaction::new( i, "zac".to_string(), format!( "post {}", i ), timestamp::from_millis( 0 ).unwrap(), "verified".to_string() )
+                
+        } ).collect();
+        let default = merkle::Merkle::new( posts.clone() );
+        for &algorithm in &[ hash_util::HashAlgorithm::Sha256, hash_util::HashAlgorithm::Blake2b ]
+        {
+
+            let merkle = merkle::Merkle::with_algorithm( posts.clone(), tree::TreeVersion::current(), algorithm );
+            assert!( merkle.root_hash() != default.root_hash() );
+            let proof = merkle.get_proof_at( 3 ).unwrap();
+            assert_eq!( algorithm, proof.algorithm() );
+            assert!( proof.verify( merkle.root_hash() ) );
+            // The algorithm survives both encodings
+            assert_eq!( proof, proof::Proof::from_json( &proof.to_json().unwrap() ).unwrap() );
+            assert_eq!( proof, proof::Proof::from_bytes( &proof.to_bytes() ).unwrap() );
+            // The same path hashed with the default algorithm doesn't verify 
+            assert!( !proof::verify_inclusion( proof.version(), hash_util::HashAlgorithm::default(), proof.value(), proof.index(),
+                                                        proof.leaf_count(), proof.path(), merkle.root_hash() ) );
+            // Multi-proofs and consistency proofs use it too 
+            assert!( merkle.get_multi_proof( &[ 0, 4 ] ).unwrap().verify( merkle.root_hash() ) );
+            let first = merkle.prefix_root_hash( 2 ).unwrap();
+            assert!( merkle.get_consistency_proof( 2 ).unwrap().verify( &first, merkle.root_hash() ) );
+            
+        }
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Verifies multi-proofs for every set of leaves in small trees of each version
+    pub fn multi_proof_test()
+    {
+
+        for &version in &[ tree::TreeVersion::V0, tree::TreeVersion::V1, tree::TreeVersion::current() ]
+        {
+
+            for count in 1 .. 9
+            {
+
+                let values: Vec<transaction::Transaction> = ( 0 .. count ).map( | i |
+                {
+
+                    transaction::Transaction::new( i as u64, "zac".to_string(), i.to_string(), timestamp::from_millis( 0 ).unwrap(), "verified".to_string() )
+                        
+                } ).collect();
+                let merkle = merkle::Merkle::with_version( values, version );
+                // Every non empty set of leaves 
+                for set in 1 .. 1 << count
+                {
+
+                    let indices: Vec<usize> = ( 0 .. count ).filter( | i | set & ( 1 << i ) != 0 ).collect();
+                    let proof = merkle.get_multi_proof( &indices ).unwrap();
+                    assert!( proof.verify( merkle.root_hash() ) );
+                    // No more hashes than the separate proofs need
+                    let separate: usize = indices.iter().map( | &i | merkle.get_proof_at( i ).unwrap().path().len() ).sum();
+                    assert!( proof.hashes().len() <= separate );
+                    // A missing or extra hash fails
+                    let mut hashes = proof.hashes().clone();
+                    if hashes.pop().is_some()
+                    {
+
+                        let short = proof::MultiProof::new( version, proof.algorithm(), *proof.root(), count, indices.clone(), proof.values().clone(), hashes );
+                        assert!( !short.verify( merkle.root_hash() ) );
+                        
+                    }
+                    let mut hashes = proof.hashes().clone();
+                    hashes.push( *merkle.root_hash() );
+                    let long = proof::MultiProof::new( version, proof.algorithm(), *proof.root(), count, indices.clone(), proof.values().clone(), hashes );
+                    assert!( !long.verify( merkle.root_hash() ) );
+                    
+                }
+                
+            }
+            
+        }
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Verifies that multi-proofs share hashes and reject altered leaves 
+    pub fn shared_multi_proof_test()
+    {
+
+        let values: Vec<transaction::Transaction> = ( 0 .. 4 ).map( | i |
+        {
+
+            transaction::Transaction::new( i, "zac".to_string(), i.to_string(), timestamp::from_millis( 0 ).unwrap(), "verified".to_string() )
+                
+        } ).collect();
+        let merkle = merkle::Merkle::new( values.clone() );
+        // Two siblings only need the hash of the other half of the tree 
+        let proof = merkle.get_multi_proof( &[ 1, 0, 1 ] ).unwrap();
+        assert_eq!( vec![ 0, 1 ], *proof.indices() );
+        assert_eq!( 1, proof.hashes().len() );
+        // Swapping the values fails
+        let swapped = proof::MultiProof::new( proof.version(), proof.algorithm(), *proof.root(), 4, vec![ 0, 1 ],
+                                              vec![ values[ 1 ].clone(), values[ 0 ].clone() ], proof.hashes().clone() );
+        assert!( !swapped.verify( merkle.root_hash() ) );
+        // Leaves outside the tree or no leaves at all can't be proven
+        assert_eq!( Err( proof::ProofError::IndexOutOfRange{ index: 4, leaf_count: 4 } ), merkle.get_multi_proof( &[ 0, 4 ] ).map( | _ | () ) );
+        assert_eq!( Err( proof::ProofError::NoLeaves ), merkle.get_multi_proof( &[] ).map( | _ | () ) );
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Verifies consistency proofs between every pair of sizes of small trees
+    pub fn consistency_proof_test()
+    {
+
+        let values: Vec<transaction::Transaction> = ( 0 .. 17 ).map( | i |
+        {
+
+            transaction::Transaction::new( i, "zac".to_string(), i.to_string(), timestamp::from_millis( 0 ).unwrap(), "verified".to_string() )
+                
+        } ).collect();
+        for second in 1 .. values.len() + 1
+        {
+
+            let merkle = merkle::Merkle::new( values[ .. second ].to_vec() );
+            for first in 1 .. second + 1
+            {
+
+                // The root of the earlier tree, rebuilt and from the later tree 
+                let first_root = *merkle::Merkle::new( values[ .. first ].to_vec() ).root_hash();
+                assert_eq!( Some( first_root ), merkle.prefix_root_hash( first ) );
+                let proof = merkle.get_consistency_proof( first ).unwrap();
+                assert_eq!( ( first, second ), ( proof.first_size(), proof.second_size() ) );
+                assert!( proof.verify( &first_root, merkle.root_hash() ) );
+                // The proof is logarithmic in the size of the later tree 
+                assert!( proof.hashes().len() <= merkle.height() + 1 );
+                // Other roots fail 
+                if first < second
+                {
+
+                    assert!( !proof.verify( merkle.root_hash(), &first_root ) );
+                    let mut edited = values[ .. second ].to_vec();
+                    edited[ first - 1 ] = transaction::dummy();
+                    assert!( !proof.verify( &first_root, merkle::Merkle::new( edited ).root_hash() ) );
+                    
+                }
+                
+            }
+            
+        }
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Verifies the sizes and versions consistency proofs can't be made for
+    pub fn consistency_proof_errors_test()
+    {
+
+        let values = vec![ transaction::dummy(), transaction::dummy(), transaction::dummy() ];
+        let merkle = merkle::Merkle::new( values.clone() );
+        assert_eq!( Err( proof::ProofError::InvalidSizes{ first: 0, second: 3 } ), merkle.get_consistency_proof( 0 ) );
+        assert_eq!( Err( proof::ProofError::InvalidSizes{ first: 4, second: 3 } ), merkle.get_consistency_proof( 4 ) );
+        assert_eq!( None, merkle.prefix_root_hash( 4 ) );
+        // Trees that fuse unpaired nodes with themselves aren't RFC 6962 trees 
+        let legacy = merkle::Merkle::with_version( values, tree::TreeVersion::V1 );
+        assert_eq!( Err( proof::ProofError::UnsupportedVersion( tree::TreeVersion::V1 ) ), legacy.get_consistency_proof( 2 ) );
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Verifies that the proof process won't accept a false value
+    pub fn false_proof_test()
+    {
+        
+        // The Merkle Tree to test against
+        let mut merkle = merkle::Merkle::new( Vec::new() );
+        // Inserts strings into the tree
+        merkle.insert( transaction::dummy() );
+        merkle.insert( transaction::dummy() );
+        merkle.insert( transaction::dummy() );
+        merkle.insert( transaction::dummy() );
+        // False transaction
+        let false_transaction = transaction::Transaction::new( 0, "zac".to_string(),
+      "false".to_string(), timestamp::from_millis( 0 ).unwrap(), "unverified".to_string() );
+   	    // The proof for the value we are trying to verify
+        //
+        // The string "false" was not entered into the tree so no proof can be made for it
+        assert_eq!( Err( proof::ProofError::LeafNotFound ), merkle.get_proof( false_transaction.clone() ).map( | proof | proof.index() ) );
+        // A proof that pairs it with a real path returns false when verified
+        let path = merkle.get_proof_hashes_at( 0 ).unwrap();
+        let proof = proof::Proof::new( merkle.version(), merkle.algorithm(), false_transaction, *merkle.root_hash(), 0, 4, path );
+        // The return value of the proof
+        let return_val = proof.verify( merkle.root_hash() );
+        // Verifies that this hash was not found in the tree
+        assert!( !return_val );
+       
+    }
+    
+}
+
+
+// Tests for transaction class
+mod transaction_tests
+{
+
+    #[allow(unused_imports)]
+    // Includes super directory 
+    use super::*;
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Write to test 
+    pub fn test_write_to()
+    {
+
+        // Sample username 
+        let username = "ezrasucks";
+        // Sample content 
+        let content = "Hi my name is Ezra and I just sort of suck. Any advice?";
+        // Sample timestamp 
+        let timestamp = "2017-12-16 01:55:49.707511 UTC";
         // Sample verification status
         let verification = "verified";
         // Creates a new transaction with the sample data 
-        let transaction = transaction::Transaction::new( 0, username.to_string(), content.to_string(), timestamp.to_string(), verification.to_string() );
+        let transaction = transaction::Transaction::new( 0, username.to_string(), content.to_string(), timestamp::parse( timestamp ).unwrap(), verification.to_string() );
         // Writes to output file 
         #[allow(unused_variables)]
         let temp = transaction.write_to( "testing-write.json" );
@@ -931,17 +2626,17 @@ mod transaction_tests
 
         // Erase the currently existing file
         #[allow(unused_variables)]
-        let status = Command::new( "rm" ).args( &[ "testing-write.json" ] ).status().expect( "Filed to delete the json files" );
+        let status = Command::new( "rm" ).args( [ "testing-write.json" ] ).status().expect( "Filed to delete the json files" );
         // Sample username 
         let username = "ezrasucks";
         // Sample content
         let content = "Hi my name is Ezra and I just sort of suck. Any advice?";
         // Sample timestamp 
-        let timestamp = "all day every day";
+        let timestamp = "2017-12-16 01:55:49.707511 UTC";
         // Sample verification status
         let verification = "verified";
         // Creates a transaction from the sample data 
-        let transaction = transaction::Transaction::new( 0, username.to_string(), content.to_string(), timestamp.to_string(), verification.to_string() );
+        let transaction = transaction::Transaction::new( 0, username.to_string(), content.to_string(), timestamp::parse( timestamp ).unwrap(), verification.to_string() );
         // Writes the transaction to output file 
         #[allow(unused_variables)]
         let temp = transaction.write_to( "testing-write.json" );
@@ -951,5 +2646,1291 @@ mod transaction_tests
         assert_eq!( transaction, d_transaction );
             
     }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests the canonical encoding and id of a transaction
+    pub fn test_encode_and_id()
+    {
+
+        // The id is the hash of the length prefixed fields 
+        let transaction = transaction::dummy();
+        assert_eq!( 8 + 4 * 4 + "namehello1970-01-01 00:00:00 UTCunverified".len(), transaction.encode().len() );
+        assert_eq!( "c1608c2e47020869b5f352255b5fe862e367a9ef598e9da8192b2c1f1fbaaf8f", transaction.id().to_string() );
+        // Its leaf in a current tree is the prefixed hash of the id
+        assert_eq!( "39bb19e4a77f8df3935eefab62c1f5149745afe4bf961ca7b4a7e04504d65647", tree::Tree::leaf( transaction.clone() ).hash().to_string() );
+        // Every field changes the id
+        let mut verified = transaction.clone();
+        verified.verify();
+        assert!( verified.id() != transaction.id() );
+        // Moving bytes between fields changes the id
+        let first = transaction::Transaction::new( 0, "ab".to_string(), "c".to_string(), timestamp::from_millis( 0 ).unwrap(), "verified".to_string() );
+        let second = transaction::Transaction::new( 0, "a".to_string(), "bc".to_string(), timestamp::from_millis( 0 ).unwrap(), "verified".to_string() );
+        assert!( first.id() != second.id() );
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests decoding the canonical encoding of a transaction
+    pub fn test_decode()
+    {
+
+        let transaction = transaction::Transaction::new( 7, "zac".to_string(), "content".to_string(), timestamp::from_millis( 0 ).unwrap(), "verified".to_string() );
+        let bytes = transaction.encode();
+        assert_eq!( Some( transaction ), transaction::Transaction::decode( &bytes ) );
+        // Anything but exactly one transaction fails
+        assert_eq!( None, transaction::Transaction::decode( &bytes[ .. bytes.len() - 1 ] ) );
+        assert_eq!( None, transaction::Transaction::decode( &[ bytes.clone(), vec![ 0 ] ].concat() ) );
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests reading the transactions written with free form timestamps before they were times
+    pub fn test_legacy_timestamps()
+    {
+
+        // A transaction written on its own
+        let written = transaction::Transaction::read_and_construct( "tests/fixtures/baseline-write.json" ).unwrap();
+        assert_eq!( "ezrasucks", written.username() );
+        assert_eq!( Some( &"all day every day".to_string() ), written.legacy_timestamp() );
+        assert_eq!( timestamp::from_millis( 0 ).unwrap(), *written.timestamp() );
+        // The transactions of a Merkle Tree
+        let nodes = merkle::Merkle::read_and_construct( "tests/fixtures/baseline-merkle.json" ).unwrap();
+        assert_eq!( 1, nodes.len() );
+        assert_eq!( Some( &"now".to_string() ), nodes[ 0 ].legacy_timestamp() );
+        // The original string is written back out and encoded
+        let json = serde_json::to_string( &written ).unwrap();
+        assert!( json.contains( "\"timestamp\":\"all day every day\"" ) );
+        assert_eq!( written, serde_json::from_str( &json ).unwrap() );
+        assert_eq!( Some( written.clone() ), transaction::Transaction::decode( &written.encode() ) );
+        assert!( written.id() != nodes[ 0 ].id() );
+        // Times are not legacy timestamps
+        assert_eq!( None, transaction::dummy().legacy_timestamp() );
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests that a timestamp written as an RFC 3339 time is read in as that time and written back
+    // out as it was
+    pub fn test_rfc3339_timestamps()
+    {
+
+        let json = "{\"uid\":7,\"username\":\"zac\",\"content\":\"hi\",\"timestamp\":\"2017-12-16T01:55:49.707511+00:00\",\"status\":\"unverified\"}";
+        let read: transaction::Transaction = serde_json::from_str( json ).unwrap();
+        assert_eq!( timestamp::parse( "2017-12-16 01:55:49.707511 UTC" ).unwrap(), *read.timestamp() );
+        assert_eq!( Some( &"2017-12-16T01:55:49.707511+00:00".to_string() ), read.legacy_timestamp() );
+        assert_eq!( json, serde_json::to_string( &read ).unwrap() );
+        assert_eq!( Some( read.clone() ), transaction::Transaction::decode( &read.encode() ) );
+        // The same time in the display format isn't a legacy timestamp and has another id
+        let displayed = transaction::Transaction::new( 7, "zac".to_string(), "hi".to_string(), *read.timestamp(), "unverified".to_string() );
+        assert_eq!( None, displayed.legacy_timestamp() );
+        assert!( read.id() != displayed.id() );
+        
+    }
+    
+}
+
+
+// Tests for the sparse Merkle Tree
+#[cfg(test)]
+mod sparse_tests
+{
+
+    // Includes super directory 
+    use super::*;
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests the root of an empty tree is the empty hash of the top level
+    pub fn test_empty_root()
+    {
+
+        let tree = sparse::SparseMerkle::new();
+        let empty = sparse::empty_hashes();
+        assert_eq!( sparse::DEPTH + 1, empty.len() );
+        assert_eq!( hash_util::empty_hash(), empty[ 0 ] );
+        assert_eq!( empty[ sparse::DEPTH ], *tree.root_hash() );
+        assert!( tree.is_empty() );
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests inclusion and non-inclusion proofs for registered usernames
+    pub fn test_inclusion_and_non_inclusion()
+    {
+
+        let mut tree = sparse::SparseMerkle::new();
+        let zac = sparse::SparseMerkle::key_of( "zac" );
+        let ezra = sparse::SparseMerkle::key_of( "ezra" );
+        tree.insert( &zac, "registered".to_string() );
+        assert_eq!( Some( &"registered".to_string() ), tree.get( &zac ) );
+        // zac is registered 
+        let proof = tree.prove( &zac );
+        assert!( proof.is_inclusion() );
+        assert!( proof.verify( tree.root_hash() ) );
+        // ezra isn't 
+        let absent = tree.prove( &ezra );
+        assert!( !absent.is_inclusion() );
+        assert!( absent.verify( tree.root_hash() ) );
+        // Claiming the opposite fails 
+        let forged = sparse::SparseProof::new( ezra, Some( "registered".to_string() ), absent.siblings().clone() );
+        assert!( !forged.verify( tree.root_hash() ) );
+        let forged = sparse::SparseProof::new( zac, None, proof.siblings().clone() );
+        assert!( !forged.verify( tree.root_hash() ) );
+        // Only the one non-empty sibling is kept in each proof
+        assert_eq!( 1, absent.siblings().len() );
+        assert_eq!( 0, proof.siblings().len() );
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests the root only depends on the keys and values, not the order they were set in
+    pub fn test_updates()
+    {
+
+        let keys: Vec<Digest> = ( 0 .. 20 ).map( | i | sparse::SparseMerkle::key_of( &i.to_string() ) ).collect();
+        let mut forward = sparse::SparseMerkle::new();
+        let mut backward = sparse::SparseMerkle::new();
+        for key in &keys
+        {
+
+            forward.insert( key, key.to_string() );
+            
+        }
+        for key in keys.iter().rev()
+        {
+
+            backward.insert( key, "old".to_string() );
+            backward.insert( key, key.to_string() );
+            
+        }
+        assert_eq!( forward.root_hash(), backward.root_hash() );
+        assert_eq!( 20, forward.len() );
+        // Every key proves against the root
+        for key in &keys
+        {
+
+            assert!( forward.prove( key ).verify( forward.root_hash() ) );
+            
+        }
+        // Removing every key empties the tree 
+        for key in &keys
+        {
+
+            assert_eq!( Some( key.to_string() ), forward.remove( key ) );
+            
+        }
+        assert_eq!( sparse::SparseMerkle::new().root_hash(), forward.root_hash() );
+        // A removed key proves absent against the old root only 
+        let proof = forward.prove( &keys[ 0 ] );
+        assert!( proof.verify( forward.root_hash() ) );
+        assert!( !proof.verify( backward.root_hash() ) );
+        
+    }
+    
+}
+
+
+// Tests for the Merkle Mountain Range
+#[cfg(test)]
+mod mmr_tests
+{
+
+    // Includes super directory 
+    use super::*;
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests every leaf proves against every later size
+    pub fn test_prove()
+    {
+
+        let hashes: Vec<Digest> = ( 0 .. 33 ).map( | i | hash_util::create_leaf_hash( &i ) ).collect();
+        let mmr = mmr::Mmr::from_hashes( &hashes );
+        assert_eq!( 33, mmr.len() );
+        assert_eq!( 2, mmr.peaks_at( 33 ).len() );
+        for size in 1 .. hashes.len() + 1
+        {
+
+            // The root of a prefix is the root of an MMR of just those hashes
+            let root = mmr::Mmr::from_hashes( &hashes[ .. size ] ).root();
+            assert_eq!( root, mmr.root_at( size ) );
+            for index in 0 .. size
+            {
+
+                let proof = mmr.prove( index, size ).unwrap();
+                assert!( proof.verify( &hashes[ index ], &root ) );
+                assert!( !proof.verify( &hashes[ ( index + 1 ) % hashes.len() ], &root ) );
+                
+            }
+            assert_eq!( None, mmr.prove( size, size ) );
+            
+        }
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests truncating and appending again gives the same MMR as appending from scratch
+    pub fn test_truncate()
+    {
+
+        let hashes: Vec<Digest> = ( 0 .. 20 ).map( | i | hash_util::create_leaf_hash( &i ) ).collect();
+        for size in 0 .. hashes.len() + 1
+        {
+
+            let mut mmr = mmr::Mmr::from_hashes( &hashes );
+            mmr.truncate( size );
+            assert_eq!( size, mmr.len() );
+            assert_eq!( mmr::Mmr::from_hashes( &hashes[ .. size ] ).root(), mmr.root() );
+            for hash in &hashes[ size .. ]
+            {
+
+                mmr.append( hash );
+                
+            }
+            assert_eq!( mmr::Mmr::from_hashes( &hashes ).root(), mmr.root() );
+            
+        }
+        // An empty MMR has the empty hash as its root
+        assert_eq!( empty_hash(), mmr::Mmr::new().root() );
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests appending the nodes a hash adds gives the same MMR as appending the hash, whether
+    // they're taken after the whole MMR or after a prefix of a longer one
+    pub fn test_append_nodes()
+    {
+
+        let hashes: Vec<Digest> = ( 0 .. 20 ).map( | i | hash_util::create_leaf_hash( &i ) ).collect();
+        let longer = mmr::Mmr::from_hashes( &hashes );
+        for size in 0 .. hashes.len()
+        {
+
+            let mut mmr = mmr::Mmr::from_hashes( &hashes[ .. size ] );
+            let nodes = mmr.nodes_after( size, &hashes[ size ] );
+            assert_eq!( nodes, longer.nodes_after( size, &hashes[ size ] ) );
+            mmr.append_nodes( &nodes );
+            let appended = mmr::Mmr::from_hashes( &hashes[ .. size + 1 ] );
+            assert_eq!( size + 1, mmr.len() );
+            assert_eq!( appended.root(), mmr.root() );
+            assert_eq!( appended.prove( size, size + 1 ), mmr.prove( size, size + 1 ) );
+            
+        }
+        
+    }
+    
+}
+
+// Tests for digests
+#[cfg(test)]
+mod digest_tests
+{
+
+    // Includes super directory 
+    use super::*;
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests digests display and parse as 64 hex digits
+    pub fn test_parse()
+    {
+
+        let hash = empty_hash();
+        let hex = "f9e2eaaa42d9fe9e558a9b8ef1bf366f190aacaa83bad2641ee106e9041096e4";
+        assert_eq!( hex, hash.to_string() );
+        assert_eq!( Ok( hash ), hex.parse::<Digest>() );
+        // Upper case digits parse to the same digest
+        assert_eq!( Ok( hash ), hex.to_uppercase().parse::<Digest>() );
+        // Strings that aren't 64 hex digits are rejected
+        assert!( "".parse::<Digest>().is_err() );
+        assert!( "0".parse::<Digest>().is_err() );
+        assert!( hex[ 1 .. ].parse::<Digest>().is_err() );
+        assert!( format!( "{}0", hex ).parse::<Digest>().is_err() );
+        assert!( hex.replace( "f", "g" ).parse::<Digest>().is_err() );
+        // Digests are built from exactly 32 bytes
+        assert_eq!( Some( hash ), digest::Digest::from_slice( hash.as_bytes() ) );
+        assert_eq!( None, digest::Digest::from_slice( &hash.as_bytes()[ 1 .. ] ) );
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests counting the leading zero bits of a digest
+    pub fn test_leading_zeros()
+    {
+
+        let mut bytes = [ 0; digest::DIGEST_LENGTH ];
+        assert_eq!( 256, digest::Digest::new( bytes ).leading_zeros() );
+        bytes[ 1 ] = 0x1f;
+        let hash = digest::Digest::new( bytes );
+        assert_eq!( 11, hash.leading_zeros() );
+        assert!( hash.meets_difficulty( 11 ) );
+        assert!( !hash.meets_difficulty( 12 ) );
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests digests are written as hex strings and a missing MMR root as an empty one
+    pub fn test_json()
+    {
+
+        let block = block::Block::new( 1, vec![ transaction::dummy() ] );
+        block.write_to( "testing-digest.json" ).unwrap();
+        let json = block::Block::read_json( "testing-digest.json" ).unwrap();
+        let read = block::Block::read_and_construct( "testing-digest.json" ).unwrap();
+        fs::remove_file( "testing-digest.json" ).unwrap();
+        assert!( json.contains( &format!( "\"merkle_root\":\"{}\"", block.merkle_root ) ) );
+        assert!( json.contains( "\"mmr_root\":\"\"" ) );
+        assert_eq!( None, read.mmr_root() );
+        assert_eq!( block.hash(), read.hash() );
+        assert_eq!( block.merkle_root, read.merkle_root );
+        
+    }
+    
+}
+
+// Tests for timestamps
+#[cfg(test)]
+mod timestamp_tests
+{
+
+    // Includes super directory 
+    use super::*;
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests timestamps are read in from the display format and RFC 3339
+    pub fn test_parse()
+    {
+
+        let time = timestamp::parse( "2017-12-16 01:55:49.707511 UTC" ).unwrap();
+        assert_eq!( time, timestamp::parse( "2017-12-16T01:55:49.707511Z" ).unwrap() );
+        assert_eq!( time, timestamp::parse( "2017-12-16T02:55:49.707511+01:00" ).unwrap() );
+        assert_eq!( 1513389349707, time.timestamp_millis() );
+        // Displaying a time gives back the string it was parsed from
+        assert_eq!( "2017-12-16 01:55:49.707511 UTC", time.to_string() );
+        let now = timestamp::now();
+        assert_eq!( Some( now ), timestamp::parse( &now.to_string() ) );
+        // Times that aren't in either format are rejected
+        assert_eq!( None, timestamp::parse( "now" ) );
+        assert_eq!( None, timestamp::parse( "2017-12-16 01:55:49" ) );
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests blocks and transactions are written with the display format and compare by time
+    pub fn test_json()
+    {
+
+        let mut block = block::Block::new( 1, vec![ transaction::dummy() ] );
+        block.set_timestamp( timestamp::from_millis( 1513389349707 ).unwrap() );
+        block.write_to( "testing-timestamp.json" ).unwrap();
+        let json = block::Block::read_json( "testing-timestamp.json" ).unwrap();
+        let read = block::Block::read_and_construct( "testing-timestamp.json" ).unwrap();
+        fs::remove_file( "testing-timestamp.json" ).unwrap();
+        assert!( json.contains( "\"timestamp\":\"2017-12-16 01:55:49.707 UTC\"" ) );
+        assert!( json.contains( "\"timestamp\":\"1970-01-01 00:00:00 UTC\"" ) );
+        assert_eq!( block.timestamp(), read.timestamp() );
+        assert_eq!( block.hash(), read.hash() );
+        assert!( read.timestamp() > read.transactions()[ 0 ].timestamp() );
+        // A transaction whose encoded timestamp isn't a time doesn't decode
+        let bytes = transaction::dummy().encode();
+        let at = bytes.len() - "1970-01-01 00:00:00 UTCunverified".len();
+        let edited = [ &bytes[ .. at ], b"1970-01-01 00:00:00 UTZ", &bytes[ at + 23 .. ] ].concat();
+        assert_eq!( None, transaction::Transaction::decode( &edited ) );
+        
+    }
+    
+}
+
+// Tests for the crate's error
+#[cfg(test)]
+mod error_tests
+{
+
+    // Includes super directory 
+    use super::*;
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests that reading a missing file returns an IO error rather than panicking
+    pub fn test_missing_file()
+    {
+
+        let missing = "testing-missing.json";
+        let is_io = | error: Error | matches!( error, Error::Io( ref error ) if error.kind() == io::ErrorKind::NotFound );
+        assert!( is_io( block::Block::read_and_construct( missing ).unwrap_err() ) );
+        assert!( is_io( merkle::Merkle::read_and_construct( missing ).unwrap_err() ) );
+        assert!( is_io( chain::Chain::read_and_construct( missing ).unwrap_err() ) );
+        assert!( is_io( transaction::Transaction::read_and_construct( missing ).unwrap_err() ) );
+        // Writing into a directory that doesn't exist fails the same way
+        assert!( is_io( block::Block::origin().write_to( "testing-missing/block.json" ).unwrap_err() ) );
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests that a file that isn't the expected json returns a json error
+    pub fn test_malformed_json()
+    {
+
+        let mut file = File::create( "testing-malformed.json" ).unwrap();
+        file.write_all( b"{\"index\":" ).unwrap();
+        let is_json = | error: Error | matches!( error, Error::Json( _ ) );
+        assert!( is_json( block::Block::read_and_construct( "testing-malformed.json" ).unwrap_err() ) );
+        assert!( is_json( chain::Chain::read_and_construct( "testing-malformed.json" ).unwrap_err() ) );
+        assert!( is_json( transaction::Transaction::read_and_construct( "testing-malformed.json" ).unwrap_err() ) );
+        fs::remove_file( "testing-malformed.json" ).unwrap();
+        assert!( is_json( proof::Proof::from_json( "[]" ).unwrap_err() ) );
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests that the errors of each part of the crate convert into the crate's error
+    pub fn test_conversions()
+    {
+
+        // Each fallible step is joined with ?
+        let prove = | index: usize | -> result::Result< proof::Proof, Error >
+        {
+
+            let mut chain = chain::Chain::new();
+            chain.push( block::Block::new( 1, vec![ transaction::dummy() ] ) )?;
+            let key: Digest = chain.tail_hash().to_string().parse()?;
+            let block = chain.get_by_hash( &key )?.unwrap();
+            Ok( block.merkle().get_proof_at( index )? )
+                
+        };
+        assert!( prove( 0 ).is_ok() );
+        let error = prove( 1 ).unwrap_err();
+        assert_eq!( "proof error: leaf 1 is out of range for a tree with 1 leaves", error.to_string() );
+        assert!( std::error::Error::source( &error ).is_some() );
+        match "0".parse::<Digest>().map_err( Error::from )
+        {
+
+            Err( Error::Digest( error ) ) => assert_eq!( "0", error.value ),
+            other => panic!( "Unexpected result {:?}", other )
+                
+        }
+        
+    }
+    
+}
+
+// Test flag indicating this module contains test methods
+#[cfg(test)]
+// Module for unit testing the block store
+mod store_tests
+{
+
+    // Includes super directory 
+    use super::*;
+    // Storage backends
+    use storage::Storage;
+
+    // Removes a store left over from an earlier run and opens a new one
+    fn open_fresh( directory: &str, segment_size: u64 ) -> store::Store
+    {
+
+        if fs::metadata( directory ).is_ok()
+        {
+
+            fs::remove_dir_all( directory ).unwrap();
+            
+        }
+        store::Store::with_segment_size( directory, segment_size ).unwrap()
+        
+    }
+
+    // Moves a new chain into the store and pushes the given number of blocks onto it
+    fn stored_chain( store: store::Store, count: u64 ) -> chain::Chain<store::Store>
+    {
+
+        let mut chain = chain::Chain::new().into_storage( store ).unwrap();
+        for index in 1 .. count + 1
+        {
+
+            chain.push( block::Block::new( index, vec![ transaction::dummy() ] ) ).unwrap();
+            
+        }
+        chain
+        
+    }
+
+    // Opens the chain kept in the store in the given directory
+    fn reopen( directory: &str, segment_size: u64 ) -> chain::Chain<store::Store>
+    {
+
+        chain::Chain::open( store::Store::with_segment_size( directory, segment_size ).unwrap() ).unwrap()
+        
+    }
+
+    // The hashes of the blocks on the chain's best branch
+    fn hashes< S: Storage >( chain: &chain::Chain<S> ) -> Vec<Digest>
+    {
+
+        chain.iter().map( | block | *block.unwrap().hash() ).collect()
+        
+    }
+
+    // The size of the segment file with the given number
+    fn segment_len( directory: &str, segment: u32 ) -> u64
+    {
+
+        fs::metadata( format!( "{}/segment-{:08}.log", directory, segment ) ).unwrap().len()
+        
+    }
+
+    // The number of segment files in the directory
+    fn segment_count( directory: &str ) -> u32
+    {
+
+        ( 0 .. ).take_while( | segment | fs::metadata( format!( "{}/segment-{:08}.log", directory, segment ) ).is_ok() ).count() as u32
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests that a chain kept in the store opens again with the same blocks, transactions and
+    // settings
+    pub fn test_reopen()
+    {
+
+        let directory = "testing-store-reopen";
+        let store = open_fresh( directory, store::SEGMENT_SIZE );
+        assert!( store.is_empty() );
+        // Nothing is opened from an empty store
+        assert!( matches!( chain::Chain::open( store ), Err( Error::Integrity( _ ) ) ) );
+        let chain = stored_chain( open_fresh( directory, store::SEGMENT_SIZE ), 4 );
+        let best = hashes( &chain );
+        let transaction = chain.get_by_height( 2 ).unwrap().unwrap().transactions()[ 0 ].id();
+        drop( chain );
+        let chain = reopen( directory, store::SEGMENT_SIZE );
+        assert_eq!( best, hashes( &chain ) );
+        assert_eq!( &best[ 4 ], chain.tail_hash() );
+        assert_eq!( 4, chain.height() );
+        // Every block holds the same dummy transaction, so it's found with the last one stored
+        let ( _, in_block ) = chain.storage().get_transaction( &transaction ).unwrap().unwrap();
+        assert_eq!( best[ 4 ], in_block );
+        chain.validate().unwrap();
+        fs::remove_dir_all( directory ).unwrap();
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests that a new segment is started once the current one is full and that moving a chain
+    // into the store removes them
+    pub fn test_segments()
+    {
+
+        let directory = "testing-store-segments";
+        // Every record fills a segment
+        let chain = stored_chain( open_fresh( directory, 1 ), 3 );
+        let count = segment_count( directory );
+        assert!( count > 4 );
+        for segment in 0 .. count
+        {
+
+            assert!( segment_len( directory, segment ) > 0 );
+            
+        }
+        // Blocks are read from their own segments after reopening
+        let tail = *chain.tail_hash();
+        drop( chain );
+        let mut chain = reopen( directory, 1 );
+        assert_eq!( &tail, chain.tail_hash() );
+        // Appending carries on in new segments
+        chain.push( block::Block::new( 4, vec![ transaction::dummy() ] ) ).unwrap();
+        assert!( segment_count( directory ) > count );
+        assert!( segment_len( directory, count ) > 0 );
+        drop( chain );
+        // A chain moved into the store replaces the one kept there, along with its segments
+        let chain = chain::Chain::new().into_storage( store::Store::with_segment_size( directory, 1 ).unwrap() ).unwrap();
+        assert_eq!( vec![ empty_hash() ], chain.storage().block_hashes().unwrap() );
+        drop( chain );
+        assert!( segment_count( directory ) < count );
+        let chain = reopen( directory, 1 );
+        assert_eq!( 1, chain.len() );
+        chain.validate().unwrap();
+        fs::remove_dir_all( directory ).unwrap();
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests that values written after the last flush, or torn by a crash, are dropped when the
+    // store is reopened
+    pub fn test_torn_write()
+    {
+
+        let directory = "testing-store-torn";
+        let chain = stored_chain( open_fresh( directory, store::SEGMENT_SIZE ), 3 );
+        let tail = *chain.tail_hash();
+        drop( chain );
+        let whole = segment_len( directory, 0 );
+        let mut store = store::Store::open( directory ).unwrap();
+        let entries = store.len();
+        // A block is stored but the store isn't flushed before the crash
+        let block = block::Block::new( 4, vec![ transaction::dummy() ] );
+        store.put_block( &block ).unwrap();
+        drop( store );
+        // Half of an index entry and a partial record are left at the ends of the files
+        let mut index = OpenOptions::new( ).append( true ).open( format!( "{}/index", directory ) ).unwrap();
+        index.write_all( &[ 1; 20 ] ).unwrap();
+        let mut segment = OpenOptions::new( ).append( true ).open( format!( "{}/segment-{:08}.log", directory, 0 ) ).unwrap();
+        segment.write_all( &[ 1; 50 ] ).unwrap();
+        // Only what was flushed is kept
+        let store = store::Store::open( directory ).unwrap();
+        assert_eq!( entries, store.len() );
+        assert_eq!( whole, segment_len( directory, 0 ) );
+        assert!( store.get_block( block.hash() ).unwrap().is_none() );
+        // The chain opens as it was and can be pushed onto again
+        let mut chain = chain::Chain::open( store ).unwrap();
+        assert_eq!( &tail, chain.tail_hash() );
+        chain.push( block::Block::new( 4, vec![ transaction::dummy() ] ) ).unwrap();
+        let tail = *chain.tail_hash();
+        drop( chain );
+        assert_eq!( &tail, reopen( directory, store::SEGMENT_SIZE ).tail_hash() );
+        fs::remove_dir_all( directory ).unwrap();
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests that a block the store fails to take leaves both the store and the chain as they were
+    pub fn test_failed_append()
+    {
+
+        let directory = "testing-store-failed";
+        // Every record fills a segment, so the next block goes in a new one
+        let mut chain = stored_chain( open_fresh( directory, 1 ), 2 );
+        let tail = *chain.tail_hash();
+        let entries = chain.storage().len();
+        // The next segment can't be created while a directory has its name
+        let blocked = format!( "{}/segment-{:08}.log", directory, segment_count( directory ) );
+        fs::create_dir( &blocked ).unwrap();
+        let block = block::Block::new( 3, vec![ transaction::dummy() ] );
+        assert!( matches!( chain.push( block.clone() ), Err( Error::Io( _ ) ) ) );
+        assert_eq!( tail, *chain.tail_hash() );
+        assert_eq!( 3, chain.len() );
+        assert_eq!( entries, chain.storage().len() );
+        // Once it can be the block is taken
+        fs::remove_dir( &blocked ).unwrap();
+        chain.push( block ).unwrap();
+        assert_eq!( 4, chain.len() );
+        // And the store reopens to the same chain
+        let tail = *chain.tail_hash();
+        drop( chain );
+        let chain = reopen( directory, 1 );
+        assert_eq!( &tail, chain.tail_hash() );
+        chain.validate().unwrap();
+        fs::remove_dir_all( directory ).unwrap();
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests that a block the store fails to take part way through is rolled back, so it isn't
+    // committed with the next block or found when the store is reopened
+    pub fn test_rollback()
+    {
+
+        let directory = "testing-store-rollback";
+        let chain = storage_tests::check_rollback( open_fresh( directory, 1 ) );
+        let best = hashes( &chain );
+        drop( chain );
+        let chain = reopen( directory, 1 );
+        assert_eq!( best, hashes( &chain ) );
+        assert_eq!( 3, chain.storage().block_hashes().unwrap().len() );
+        chain.validate().unwrap();
+        fs::remove_dir_all( directory ).unwrap();
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests that a record changed on disk fails its checksum
+    pub fn test_checksum()
+    {
+
+        let directory = "testing-store-checksum";
+        let chain = stored_chain( open_fresh( directory, store::SEGMENT_SIZE ), 3 );
+        let changed = *chain.get_by_height( 2 ).unwrap().unwrap().hash();
+        let kept = *chain.tail_hash();
+        drop( chain );
+        // Changes the hash in the json of the third of the four blocks, its own record is the
+        // first to hold it
+        let hash = changed.to_string();
+        let path = format!( "{}/segment-{:08}.log", directory, 0 );
+        let mut bytes = fs::read( &path ).unwrap();
+        let at = bytes.windows( hash.len() ).position( | window | window == hash.as_bytes() ).unwrap();
+        bytes[ at ] ^= 1;
+        fs::write( &path, &bytes ).unwrap();
+        // Only the changed record fails to be read
+        let store = store::Store::open( directory ).unwrap();
+        assert!( store.get_block( &empty_hash() ).is_ok() );
+        assert!( matches!( store.get_block( &changed ), Err( Error::Integrity( _ ) ) ) );
+        assert!( store.get_block( &kept ).is_ok() );
+        // Opening the chain only reads the index entries, checking it reads the changed block
+        let chain = chain::Chain::open( store ).unwrap();
+        assert!( matches!( chain.validate(), Err( Error::Integrity( _ ) ) ) );
+        fs::remove_dir_all( directory ).unwrap();
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests that a chain with several branches opens again from its store
+    pub fn test_branches()
+    {
+
+        let directory = "testing-store-branches";
+        let mut chain = stored_chain( open_fresh( directory, 512 ), 2 );
+        // A side branch from the origin overtakes the main branch
+        let mut parent = *chain.origin().unwrap().hash();
+        for index in 1 .. 4
+        {
+
+            let mut block = block::Block::new( index, vec![ transaction::dummy() ] );
+            chain.prepare_on( &parent, &mut block );
+            parent = *block.hash();
+            chain.accept( block ).unwrap();
+            
+        }
+        let best = hashes( &chain );
+        drop( chain );
+        let mut opened = reopen( directory, 512 );
+        assert_eq!( best, hashes( &opened ) );
+        assert_eq!( 6, opened.len() );
+        assert_eq!( 3, opened.height() );
+        opened.validate().unwrap();
+        // The opened chain carries on from its tail
+        opened.push( block::Block::new( 4, vec![ transaction::dummy() ] ) ).unwrap();
+        let tail = *opened.tail_hash();
+        drop( opened );
+        let opened = reopen( directory, 512 );
+        assert_eq!( &tail, opened.tail_hash() );
+        assert_eq!( 7, opened.len() );
+        fs::remove_dir_all( directory ).unwrap();
+        
+    }
+    
+}
+
+// Test flag indicating this module contains test methods
+#[cfg(test)]
+// Module for unit testing the storage backends, every check is run against each backend
+mod storage_tests
+{
+
+    // Includes super directory 
+    use super::*;
+    // Storage backends
+    use storage::Storage;
+
+    // Creates a transaction with the given content
+    fn post( uid: u64, content: &str ) -> transaction::Transaction
+    {
+
+        transaction::Transaction::new( uid, "zac".to_string(), content.to_string(), timestamp::from_millis( 0 ).unwrap(), "unverified".to_string() )
+        
+    }
+
+    // Creates a chain with three blocks on its best branch and a side branch of one block
+    fn branched_chain() -> chain::Chain
+    {
+
+        let mut chain = chain::Chain::with_difficulty( 1 );
+        for index in 1 .. 4
+        {
+
+            let mut block = block::Block::new( index, vec![ post( index, "main" ), post( index, "branch" ) ] );
+            chain.prepare( &mut block );
+            chain.push( block ).unwrap();
+            
+        }
+        let origin = *chain.origin().unwrap().hash();
+        let mut side = block::Block::new( 1, vec![ post( 9, "side" ) ] );
+        chain.prepare_on( &origin, &mut side );
+        chain.accept( side ).unwrap();
+        chain
+        
+    }
+
+    // Checks that blocks, transactions, Merkle Trees and records are read back as they were stored
+    fn check_values< S: Storage >( storage: &mut S )
+    {
+
+        let block = block::Block::new( 1, vec![ post( 1, "a" ), post( 2, "b" ) ] );
+        let missing = create_leaf_hash( &9 );
+        storage.put_block( &block ).unwrap();
+        assert_eq!( block.hash(), storage.get_block( block.hash() ).unwrap().unwrap().hash() );
+        assert!( storage.get_block( &missing ).unwrap().is_none() );
+        assert_eq!( vec![ *block.hash() ], storage.block_hashes().unwrap() );
+        // Transactions are found by id along with their block
+        let transaction = &block.transactions()[ 1 ];
+        storage.put_transaction( &transaction.id(), block.hash(), transaction ).unwrap();
+        let ( found, in_block ) = storage.get_transaction( &transaction.id() ).unwrap().unwrap();
+        assert_eq!( *transaction, found );
+        assert_eq!( block.hash(), &in_block );
+        assert!( storage.get_transaction( &missing ).unwrap().is_none() );
+        // Merkle Trees are found by root
+        let merkle = block.merkle();
+        storage.put_merkle( &merkle ).unwrap();
+        let found = storage.get_merkle( block.merkle_root() ).unwrap().unwrap();
+        assert_eq!( block.merkle_root(), found.root_hash() );
+        assert_eq!( merkle.leaf_count(), found.leaf_count() );
+        assert!( storage.get_merkle( &missing ).unwrap().is_none() );
+        // Records are replaced when they're stored again
+        storage.put_record( "name", "first" ).unwrap();
+        storage.put_record( "name", "second" ).unwrap();
+        assert_eq!( Some( "second".to_string() ), storage.get_record( "name" ).unwrap() );
+        assert!( storage.get_record( "missing" ).unwrap().is_none() );
+        storage.flush().unwrap();
+        
+    }
+
+    // Checks that a chain moved into storage keeps its transactions and Merkle Trees there
+    fn check_chain< S: Storage >( storage: S ) -> chain::Chain<S>
+    {
+
+        let chain = branched_chain().into_storage( storage ).unwrap();
+        check_stored( &chain );
+        chain
+        
+    }
+
+    // Checks that every transaction of the chain is found with the block it's in, on every
+    // branch, and that the blocks' Merkle Trees are stored
+    fn check_stored< S: Storage >( chain: &chain::Chain<S> )
+    {
+
+        let storage = chain.storage();
+        let block = chain.get_by_height( 2 ).unwrap().unwrap();
+        let ( transaction, in_block ) = storage.get_transaction( &block.transactions()[ 1 ].id() ).unwrap().unwrap();
+        assert_eq!( "branch", transaction.get_value() );
+        assert_eq!( block.hash(), &in_block );
+        let ( transaction, _ ) = storage.get_transaction( &post( 9, "side" ).id() ).unwrap().unwrap();
+        assert_eq!( "side", transaction.get_value() );
+        assert!( storage.get_merkle( block.merkle_root() ).unwrap().is_some() );
+        
+    }
+
+    // Checks that the chain opened from storage matches the given chain
+    fn check_opened< S: Storage, T: Storage >( chain: &chain::Chain<S>, opened: &chain::Chain<T> )
+    {
+
+        assert_eq!( chain.tail_hash(), opened.tail_hash() );
+        assert_eq!( chain.len(), opened.len() );
+        assert_eq!( chain.height(), opened.height() );
+        assert_eq!( chain.difficulty(), opened.difficulty() );
+        assert_eq!( chain.next_difficulty(), opened.next_difficulty() );
+        assert_eq!( chain.next_mmr_root(), opened.next_mmr_root() );
+        opened.validate().unwrap();
+        check_stored( opened );
+        
+    }
+
+    // Checks that moving a chain into storage holding another chain replaces it, none of the
+    // other chain's blocks or transactions are left behind
+    fn check_replaced< S: Storage >( storage: S )
+    {
+
+        let chain = chain::Chain::new().into_storage( storage ).unwrap();
+        assert_eq!( vec![ empty_hash() ], chain.storage().block_hashes().unwrap() );
+        assert!( chain.storage().get_transaction( &post( 9, "side" ).id() ).unwrap().is_none() );
+        chain.validate().unwrap();
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests values stored in memory
+    pub fn test_memory_values()
+    {
+
+        check_values( &mut storage::MemoryStorage::new() );
+        
+    }
+
+    // Storage that stores through to other storage, counting the blocks read from it and
+    // failing to store Merkle Trees while it's told to so a block is left part way stored
+    #[derive( Debug )]
+    pub struct Watched< S: Storage >
+    {
+
+        // The storage stored through to
+        pub inner: S,
+        // Whether storing a Merkle Tree fails
+        pub failing: rc::Rc< cell::Cell<bool> >,
+        // The number of blocks read
+        pub reads: rc::Rc< cell::Cell<usize> >
+
+    }
+
+    // Storage impl for watched storage
+    impl< S: Storage > Storage for Watched<S>
+    {
+
+        fn put_block( &mut self, block: &block::Block ) -> result::Result< (), Error >
+        {
+
+            self.inner.put_block( block )
+
+        }
+
+        fn get_block( &self, hash: &Digest ) -> result::Result< Option<block::Block>, Error >
+        {
+
+            self.reads.set( self.reads.get() + 1 );
+            self.inner.get_block( hash )
+
+        }
+
+        fn block_hashes( &self ) -> result::Result< Vec<Digest>, Error >
+        {
+
+            self.inner.block_hashes()
+
+        }
+
+        fn put_meta( &mut self, hash: &Digest, meta: &str ) -> result::Result< (), Error >
+        {
+
+            self.inner.put_meta( hash, meta )
+
+        }
+
+        fn metas( &self ) -> result::Result< Vec< ( Digest, String ) >, Error >
+        {
+
+            self.inner.metas()
+
+        }
+
+        fn put_transaction( &mut self, id: &Digest, block: &Digest, transaction: &transaction::Transaction ) -> result::Result< (), Error >
+        {
+
+            self.inner.put_transaction( id, block, transaction )
+
+        }
+
+        fn get_transaction( &self, id: &Digest ) -> result::Result< Option< ( transaction::Transaction, Digest ) >, Error >
+        {
+
+            self.inner.get_transaction( id )
+
+        }
+
+        fn put_merkle( &mut self, merkle: &merkle::Merkle ) -> result::Result< (), Error >
+        {
+
+            if self.failing.get()
+            {
+
+                return Err( Error::Io( io::Error::other( "storing Merkle Trees is failing" ) ) );
+
+            }
+            self.inner.put_merkle( merkle )
+
+        }
+
+        fn get_merkle( &self, root: &Digest ) -> result::Result< Option<merkle::Merkle>, Error >
+        {
+
+            self.inner.get_merkle( root )
+
+        }
+
+        fn put_record( &mut self, name: &str, value: &str ) -> result::Result< (), Error >
+        {
+
+            self.inner.put_record( name, value )
+
+        }
+
+        fn get_record( &self, name: &str ) -> result::Result< Option<String>, Error >
+        {
+
+            self.inner.get_record( name )
+
+        }
+
+        fn clear( &mut self ) -> result::Result< (), Error >
+        {
+
+            self.inner.clear()
+
+        }
+
+        fn flush( &mut self ) -> result::Result< (), Error >
+        {
+
+            self.inner.flush()
+
+        }
+
+        fn rollback( &mut self ) -> result::Result< (), Error >
+        {
+
+            self.inner.rollback()
+
+        }
+
+    }
+
+    // Checks that a block the storage fails to take part way through is rolled back, so none of
+    // it is flushed along with the next block. Returns the chain left in the storage
+    pub fn check_rollback< S: Storage >( storage: S ) -> chain::Chain< Watched<S> >
+    {
+
+        let watched = Watched{ inner: storage, failing: rc::Rc::default(), reads: rc::Rc::default() };
+        let failing = watched.failing.clone();
+        let mut chain = chain::Chain::new().into_storage( watched ).unwrap();
+        chain.push( block::Block::new( 1, vec![ post( 1, "kept" ) ] ) ).unwrap();
+        let tail = *chain.tail_hash();
+        // The block and its transaction are stored before its Merkle Tree fails to be
+        failing.set( true );
+        let mut block = block::Block::new( 2, vec![ post( 2, "dropped" ) ] );
+        chain.prepare( &mut block );
+        assert!( matches!( chain.accept( block.clone() ), Err( Error::Io( _ ) ) ) );
+        assert_eq!( &tail, chain.tail_hash() );
+        assert_eq!( 2, chain.len() );
+        assert!( chain.storage().get_block( block.hash() ).unwrap().is_none() );
+        assert!( chain.storage().get_transaction( &post( 2, "dropped" ).id() ).unwrap().is_none() );
+        // The next block is stored on its own
+        failing.set( false );
+        chain.push( block::Block::new( 3, vec![ post( 3, "next" ) ] ) ).unwrap();
+        assert_eq!( 3, chain.storage().block_hashes().unwrap().len() );
+        assert!( chain.storage().get_transaction( &post( 2, "dropped" ).id() ).unwrap().is_none() );
+        chain.validate().unwrap();
+        chain
+        
+    }
+
+    // Opens the chain kept in the storage, checking no block is read to open it
+    fn open_unread( storage: storage::MemoryStorage ) -> chain::Chain< Watched<storage::MemoryStorage> >
+    {
+
+        let watched = Watched{ inner: storage, failing: rc::Rc::default(), reads: rc::Rc::default() };
+        let reads = watched.reads.clone();
+        let opened = chain::Chain::open( watched ).unwrap();
+        assert_eq!( 0, reads.get() );
+        opened
+        
+    }
+
+    // Returns the index entries kept in the storage, sorted by block hash
+    fn sorted_metas< S: Storage >( storage: &S ) -> Vec< ( Digest, String ) >
+    {
+
+        let mut metas = storage.metas().unwrap();
+        metas.sort();
+        metas
+        
+    }
+
+    // Checks that a chain opens from its index entries without reading its blocks, and that
+    // storage kept before index entries were stored has its blocks indexed when it's opened
+    fn check_index( chain: &chain::Chain<storage::MemoryStorage> )
+    {
+
+        let opened = open_unread( chain.storage().clone() );
+        assert_eq!( chain.prove_block( chain.tail_hash() ), opened.prove_block( chain.tail_hash() ) );
+        check_opened( chain, &opened );
+        // Copies everything but the index entries into new storage
+        let mut legacy = storage::MemoryStorage::new();
+        for hash in chain.storage().block_hashes().unwrap()
+        {
+
+            let block = chain.storage().get_block( &hash ).unwrap().unwrap();
+            legacy.put_block( &block ).unwrap();
+            for transaction in block.transactions()
+            {
+
+                legacy.put_transaction( &transaction.id(), &hash, transaction ).unwrap();
+                
+            }
+            legacy.put_merkle( &block.merkle() ).unwrap();
+            
+        }
+        legacy.put_record( "chain", &chain.storage().get_record( "chain" ).unwrap().unwrap() ).unwrap();
+        legacy.flush().unwrap();
+        assert!( legacy.metas().unwrap().is_empty() );
+        // The blocks are indexed as the chain indexed them, then opened from the index entries
+        let opened = chain::Chain::open( legacy ).unwrap();
+        check_opened( chain, &opened );
+        assert_eq!( sorted_metas( chain.storage() ), sorted_metas( opened.storage() ) );
+        check_opened( chain, &open_unread( opened.storage().clone() ) );
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests a chain kept in memory
+    pub fn test_memory_chain()
+    {
+
+        // Nothing is opened from empty storage
+        assert!( matches!( chain::Chain::open( storage::MemoryStorage::new() ), Err( Error::Integrity( _ ) ) ) );
+        let chain = check_chain( storage::MemoryStorage::new() );
+        check_opened( &chain, &chain::Chain::open( chain.storage().clone() ).unwrap() );
+        check_replaced( chain.storage().clone() );
+        check_rollback( storage::MemoryStorage::new() );
+        check_index( &chain );
+        
+    }
+
+    // Opens a sled database in a new directory
+    #[cfg(feature = "sled")]
+    fn open_sled( directory: &str ) -> storage::SledStorage
+    {
+
+        if fs::metadata( directory ).is_ok()
+        {
+
+            fs::remove_dir_all( directory ).unwrap();
+            
+        }
+        storage::SledStorage::open( directory ).unwrap()
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    #[cfg(feature = "sled")]
+    // Tests values stored in sled
+    pub fn test_sled_values()
+    {
+
+        let directory = "testing-sled-values";
+        check_values( &mut open_sled( directory ) );
+        fs::remove_dir_all( directory ).unwrap();
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    #[cfg(feature = "sled")]
+    // Tests a chain kept in sled, before and after the database is reopened
+    pub fn test_sled_chain()
+    {
+
+        let directory = "testing-sled-chain";
+        assert!( matches!( chain::Chain::open( open_sled( directory ) ), Err( Error::Integrity( _ ) ) ) );
+        // The chain is moved back into memory to compare against, which closes the database
+        let chain = check_chain( open_sled( directory ) ).into_storage( storage::MemoryStorage::new() ).unwrap();
+        let opened = chain::Chain::open( storage::SledStorage::open( directory ).unwrap() ).unwrap();
+        check_opened( &chain, &opened );
+        drop( opened );
+        // The chain that replaces it is what's opened next time
+        check_replaced( storage::SledStorage::open( directory ).unwrap() );
+        let opened = chain::Chain::open( storage::SledStorage::open( directory ).unwrap() ).unwrap();
+        assert_eq!( 1, opened.len() );
+        assert_eq!( vec![ empty_hash() ], opened.storage().block_hashes().unwrap() );
+        drop( opened );
+        fs::remove_dir_all( directory ).unwrap();
+        check_rollback( storage::SledStorage::temporary().unwrap() );
+        
+    }
+    
+}
+
+// Test flag indicating this module contains test methods
+#[cfg(test)]
+// Module for unit testing atomic file writes
+mod file_tests
+{
+
+    // Includes super directory 
+    use super::*;
+
+    // Lists the files in the current directory whose names start with the given prefix
+    fn files_starting_with( prefix: &str ) -> Vec<String>
+    {
+
+        fs::read_dir( "." ).unwrap()
+            .map( | entry | entry.unwrap().file_name().to_string_lossy().into_owned() )
+            .filter( | name | name.starts_with( prefix ) )
+            .collect()
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests that writing a smaller object over a larger one leaves valid json behind
+    pub fn test_overwrite_with_smaller()
+    {
+
+        // Chains
+        let mut chain = chain::Chain::new();
+        for index in 1 .. 4
+        {
+
+            chain.push( block::Block::new( index, vec![ transaction::dummy(), transaction::dummy() ] ) ).unwrap();
+            
+        }
+        chain.write_to( "testing-overwrite-chain.json" ).unwrap();
+        chain::Chain::new().write_to( "testing-overwrite-chain.json" ).unwrap();
+        assert_eq!( 1, chain::Chain::read_and_construct( "testing-overwrite-chain.json" ).unwrap().len() );
+        // Blocks
+        block::Block::new( 1, vec![ transaction::dummy(), transaction::dummy() ] ).write_to( "testing-overwrite-block.json" ).unwrap();
+        block::Block::origin().write_to( "testing-overwrite-block.json" ).unwrap();
+        assert_eq!( empty_hash(), *block::Block::read_and_construct( "testing-overwrite-block.json" ).unwrap().hash() );
+        // Merkle Trees
+        merkle::Merkle::new( vec![ transaction::dummy(), transaction::dummy() ] ).write_to( "testing-overwrite-merkle.json" ).unwrap();
+        merkle::Merkle::new( vec![ transaction::dummy() ] ).write_to( "testing-overwrite-merkle.json" ).unwrap();
+        assert_eq!( 1, merkle::Merkle::read_and_construct( "testing-overwrite-merkle.json" ).unwrap().len() );
+        // Transactions are appended to the file unless they replace it
+        let long = transaction::Transaction::new( 0, "zac".to_string(), "a much longer post".to_string(), timestamp::from_millis( 0 ).unwrap(), "verified".to_string() );
+        long.write_to( "testing-overwrite-transaction.json" ).unwrap();
+        transaction::dummy().write_to( "testing-overwrite-transaction.json" ).unwrap();
+        let appended = [ serde_json::to_string( &long ).unwrap(), serde_json::to_string( &transaction::dummy() ).unwrap() ].concat();
+        assert_eq!( appended, fs::read_to_string( "testing-overwrite-transaction.json" ).unwrap() );
+        long.replace( "testing-overwrite-transaction.json" ).unwrap();
+        transaction::dummy().replace( "testing-overwrite-transaction.json" ).unwrap();
+        assert_eq!( transaction::dummy(), transaction::Transaction::read_and_construct( "testing-overwrite-transaction.json" ).unwrap() );
+        // No temporary files are left behind
+        assert_eq!( 4, files_starting_with( "testing-overwrite-" ).len() );
+        for name in files_starting_with( "testing-overwrite-" )
+        {
+
+            fs::remove_file( name ).unwrap();
+            
+        }
+        
+    }
+
+    // Test flag indicating the next function contains tests
+    #[test]
+    // Tests that a failed write returns an error, leaves the file as it was and cleans up
+    pub fn test_failed_write()
+    {
+
+        // Writing into a directory that doesn't exist fails without creating anything
+        let error = chain::Chain::new().write_to( "testing-atomic-missing/chain.json" ).unwrap_err();
+        assert!( matches!( error, Error::Io( ref error ) if error.kind() == io::ErrorKind::NotFound ) );
+        assert!( fs::metadata( "testing-atomic-missing" ).is_err() );
+        // Renaming over a directory fails, the directory is left as it was and the temporary
+        // file is removed
+        fs::create_dir_all( "testing-atomic-directory/inner" ).unwrap();
+        assert!( matches!( block::Block::origin().write_to( "testing-atomic-directory" ), Err( Error::Io( _ ) ) ) );
+        assert!( fs::metadata( "testing-atomic-directory/inner" ).unwrap().is_dir() );
+        assert_eq!( vec![ "testing-atomic-directory".to_string() ], files_starting_with( "testing-atomic-directory" ) );
+        fs::remove_dir_all( "testing-atomic-directory" ).unwrap();
+        
+    }
     
 }
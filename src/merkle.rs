@@ -12,21 +12,16 @@ use std::*;
 // Using tree for the skeleton of the Merkle Tree
 #[allow(unused_imports)]
 use tree::*;
-// Used for vector queue when loading nodes in to a temporary buffer for building the tree
-#[allow(unused_imports)]
-use std::collections::VecDeque;
-// Used for the hash map the represents the hashes on each level of the tree
-#[allow(unused_imports)]
-use std::collections::BTreeMap;
 // Used for getting the nodes needed for proof verification
 #[allow(unused_imports)]
 use proof::*;
 // Used for creating hashes
 #[allow(unused_imports)]
 use hash_util::*;
-// Used for serialization
-#[allow(unused_imports)]
-use self::serde_json::Error;
+// Reading and writing Merkle Trees fails with the crate's error
+use error::Error;
+// Files are written atomically
+use file;
 // Used for writing to output files
 #[allow(unused_imports)]
 use std::fs::{ OpenOptions, File };
@@ -41,15 +36,15 @@ use transaction::*;
  *     - The left or right traversal direction containing the hash value 
  *
  */
-#[derive(Debug)]
+#[derive( Debug, Clone, PartialEq, Serialize, Deserialize )]
 #[allow(dead_code)]
 pub enum Node
 {
 
     // Left traversal and hash
-    Left( String ),
+    Left( Digest ),
     // Right traversal and hash
-    Right( String )
+    Right( Digest )
     
 }
     
@@ -62,16 +57,15 @@ pub enum Node
  *
  */
 
-// Merkle Tree struct, defines the elements needed for each instance
+// Merkle Tree struct, defines the elements needed for each instance. Only the leaves, version
+// and algorithm are serialized, the tree is rebuilt from its record when it is read back in
 #[allow(dead_code)]
-#[derive(Clone)]
+#[derive( Clone, Debug )]
 #[derive( Serialize, Deserialize )]
+#[serde(from = "MerkleRecord")]
 pub struct Merkle
 {
     
-    // The binary tree representing the root node of the Merkle Tree
-     #[serde(skip)]
-    root: Tree,
     // The height of the Merkle Tree
      #[serde(skip)]
     height: usize,
@@ -80,15 +74,52 @@ pub struct Merkle
     leaf_count: usize,
     // The hash of the root node
      #[serde(skip)]
-    hash: String,
+    hash: Digest,
     // A vector of nodes representing the leaves of the tree
     nodes: Vec<Transaction>,
-    // A hash map of the hashes on each level of the tree
+    // The format the tree is hashed with, trees serialized before versions were recorded are
+    // read in with the legacy version
+    version: TreeVersion,
+    // The hash algorithm the tree is hashed with, trees serialized before algorithms were
+    // recorded are read in with the default algorithm
+    algorithm: HashAlgorithm,
+    // The hashes on each level of the tree from the leaves ( levels[ 0 ] ) up to the root, the
+    // levels are kept so a leaf can be appended or updated by rehashing only its path
     #[serde(skip)]
-    map: BTreeMap<usize, VecDeque<Tree>>
+    levels: Vec< Vec<Digest> >
     
 }
 
+// The serialized fields of a Merkle Tree
+#[derive( Deserialize )]
+struct MerkleRecord
+{
+
+    // The leaves
+    nodes: Vec<Transaction>,
+    // The tree version, legacy for trees serialized before versions were recorded
+    #[serde(default = "TreeVersion::legacy")]
+    version: TreeVersion,
+    // The hash algorithm, the default for trees serialized before algorithms were recorded
+    #[serde(default)]
+    algorithm: HashAlgorithm
+
+}
+
+// Rebuilds a Merkle Tree from its record, so the height, leaf count, root and levels of a
+// deserialized tree are those of its leaves
+impl From<MerkleRecord> for Merkle
+{
+
+    fn from( record: MerkleRecord ) -> Merkle
+    {
+
+        Merkle::with_algorithm( record.nodes, record.version, record.algorithm )
+
+    }
+
+}
+
 // Default impl for the Merkle Tree
 impl Default for Tree
 {
@@ -111,23 +142,43 @@ impl Merkle
     // New empty Merkle Tree constructor
     #[allow(dead_code)]
     pub fn empty() -> Self
+    {
+
+        Self::empty_with_version( TreeVersion::current() )
+        
+    }
+
+    // New empty Merkle Tree constructor for a given tree version
+    #[allow(dead_code)]
+    pub fn empty_with_version( version: TreeVersion ) -> Self
+    {
+
+        Self::empty_with_algorithm( version, HashAlgorithm::default() )
+        
+    }
+
+    // New empty Merkle Tree constructor for a given tree version and hash algorithm
+    #[allow(dead_code)]
+    pub fn empty_with_algorithm( version: TreeVersion, algorithm: HashAlgorithm ) -> Self
     {
 
         Merkle
         {
             
-            // The root of the tree, a new Merkle Tree will have an empty tree as its root
-            root: Tree::empty(),
             // The height of an empty tree
             height: 0,
             // The leaf count of an empty tree
             leaf_count: 0,
-            // The hash of an empty tree
-            hash: ::hash_util::empty_hash(),
+            // The root hash of an empty tree
+            hash: algorithm.empty_hash(),
             // The nodes of the empty tree
             nodes: Vec::new(),
-            // The hash map of hashes on each level of the tree
-            map: BTreeMap::new()
+            // The format the tree is hashed with
+            version,
+            // The hash algorithm the tree is hashed with
+            algorithm,
+            // The hashes on each level of the tree
+            levels: Vec::new()
 
         }
 
@@ -137,6 +188,25 @@ impl Merkle
     
     #[allow(dead_code)]
     pub fn new( nodes: Vec<Transaction> ) -> Self
+    {
+
+        Self::with_version( nodes, TreeVersion::current() )
+        
+    }
+
+    // Constructs a new Merkle tree with the given nodes hashed with the given tree version
+    #[allow(dead_code)]
+    pub fn with_version( nodes: Vec<Transaction>, version: TreeVersion ) -> Self
+    {
+
+        Self::with_algorithm( nodes, version, HashAlgorithm::default() )
+        
+    }
+
+    // Constructs a new Merkle tree with the given nodes hashed with the given tree version and
+    // hash algorithm
+    #[allow(dead_code)]
+    pub fn with_algorithm( nodes: Vec<Transaction>, version: TreeVersion, algorithm: HashAlgorithm ) -> Self
     {
 
         // If the input nodes are empty, the empty tree constructor is called 
@@ -144,7 +214,7 @@ impl Merkle
         {
 
             // Return self by calling the empty tree constructor 
-            Self::empty()
+            Self::empty_with_algorithm( version, algorithm )
 
         }
         // Otheriwse, a new Merkle Tree instance is constructed with the given nodes
@@ -156,12 +226,13 @@ impl Merkle
             let mut merkle = Merkle
             {
                 
-                root: Tree::empty(),
                 height: 0,
                 leaf_count: 0,
-                hash: ::hash_util::empty_hash(),
-                nodes: nodes,
-                map: BTreeMap::new(),
+                hash: algorithm.empty_hash(),
+                nodes,
+                version,
+                algorithm,
+                levels: Vec::new(),
                 
             };
             // Constructs and returns the tree
@@ -190,6 +261,9 @@ impl Merkle
      * a tree node be the parent of both nodes with the parent hash being the concatenation of *
      * the children's hashes.                                                                  *
      *                                                                                         *
+     * If the last node on a level has no partner, V2+ trees promote it to the level above     *
+     * unchanged while older versions fuse it with a copy of itself.                           *
+     *                                                                                         *
      * Repeat the process until the trivial case is hit.                                       *
      *                                                                                         *
      * First pass:                                                                             *
@@ -211,12 +285,11 @@ impl Merkle
      *  /\  /\   /\   /\                                                                       *
      * 1 2 3  4 5 6  7  8                                                                      *
      *                                                                                         *
-     * Note: The root node of each tree is the only part of the tree stored in the vector,     * 
-     *       the children of each node are shown above for explanatory purposes but are not    * 
-     *       explicitly stored in the vector. They are however stored as child fields of the   * 
-     *       nodes stored within the vector.                                                   *
+     * Note: Only the hashes of each pass are stored ( as a level of the tree ), the children  * 
+     *       of each node are shown above for explanatory purposes. Keeping every level lets   * 
+     *       appends and updates rehash a single path instead of rebuilding the tree.          * 
      *                                                                                         *
-     * The root node is now set to the only node in the buffer and the tree construction       *
+     * The root hash is now set to the only hash on the top level and the tree construction    *
      * concludes.                                                                              *
      *                                                                                         *
      * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
@@ -228,275 +301,530 @@ impl Merkle
         self.leaf_count = self.nodes.len();
         // Calculates the height based on the number of leaves
         self.height = self.calculate_height();
-        // Sets the root to be an empty tree, if there are nodes in the leaf vector this will
-        // be reset to the root node after the tree is built, if not the tree is empty and this
-        // root will stay the same. 
-        self.root = Tree::empty();
-        // The current level of the tree that's being constructed ( initially this is the leaf
-        // leve ).
-        let mut current_level = self.height;
-        // Clears the hash map
-        self.map.clear();
+        // Clears the levels, if there are nodes in the leaf vector they will be rebuilt
+        self.levels.clear();
         // If there are leaf nodes, execute the tree building algorithm 
         if !self.is_empty() 
         {
 
-            // Buffer is a temporary queue of nodes that represents the nodes on the current
-            // that are to be constructed 
-            let mut buffer = VecDeque::new();
-            // Iterates through the leaf nodes and adds them to buffer, push_back adds to the
-            // end of the queue
-            for node in &self.nodes
-            {
-
-                // Sets the current node to be a clone of the node in the leaf vector and
-                // places it in the queue
-                let current_node = Tree::leaf( node.clone() );
-                buffer.push_back( current_node );
-                
-            }
-            // 
-            self.map.insert( current_level, buffer );
+            // The leaf level holds the leaf hashes of the nodes
+            let ( version, algorithm ) = ( self.version, self.algorithm );
+            self.levels.push( self.nodes.iter().map( | node | version.leaf_hash_with( algorithm, node ) ).collect() );
             // Tree construction algorithm ( detailed above ), executes until the root level
             // is reached. 
-            while current_level > 0 
+            while self.levels[ self.levels.len() - 1 ].len() > 1
             {
 
-                // The level above the current one
-                let above_level = current_level - 1;
-                // The row of hashes above the current one 
-                let above_row =
+                // The level being fused and the row of hashes above it 
+                let current_level = self.levels.len() - 1;
+                let above_row = ( 0 .. self.levels[ current_level ].len().div_ceil( 2 ) ).map( | position |
                 {
-                    
-                    // The current row that the tree is going to be constructed out of  
-                    let mut row = VecDeque::new();
-                    // The current row of hashes
-                    let current_row = self.map.get( &current_level ).unwrap();
-                    // Iterative variable 
-                    let mut i = 0;
-                    // Iterates through the current level's nodes, the step is set to 2 because
-                    // two nodes are pulled from the queue at each iteration  
-                    while i < current_row.len()
-                    {
+
+                    self.parent_hash( current_level, position )
                         
-                        // Gets the left and right children by pulling from the queue at i
-                        // and i + 1
-                        //
-                        // dereferencing the .unwrap() of the buffer.get( ) is used because
-                        // buffer.get( ) is returning an Option and the .unwrap() returns a
-                        // reference to that option. 
-                        let left = current_row.get( i ).unwrap();
-                        let right = current_row.get( i + 1 ).unwrap_or( left );
-                        // Sets the combined node to be a node made out of the left and right
-                        // children accessed above
-                        let combined = Tree::node( left.clone(), right.clone() );
-                        // Pushes the new combined node to the row buffer
-                        row.push_back( combined );
-                        // Increases the iterative variable 
-                        i += 2;
-
-                    }
-                    row
-
-                };
-                // Inserts the above row of hashes at the correct level  
-                self.map.insert( above_level, above_row );
-                // Decreases the level value 
-                current_level -= 1;
+                } ).collect();
+                self.levels.push( above_row );
                 
             }
-            // Sets the root node equal to the node at the 0th level in the hash map
-            //
-            // .unwrap() is used to get rid of the Option that is wrapped around the node type
-            // and it is cloned to get rid of any borrowed value errors 
-            self.root = self.map.get( &0 ).unwrap()[ 0 ].clone();
             
         }
+        // Sets the root hash to the only hash on the top level 
+        self.hash = self.top_hash();
+        
+    }
+
+    // Computes the hash of the node at a position on the level above the given one from its
+    // children, an unpaired child is either promoted or fused with itself 
+    fn parent_hash( &self, level: usize, position: usize ) -> Digest
+    {
+
+        let row = &self.levels[ level ];
+        let left = &row[ 2 * position ];
+        match row.get( 2 * position + 1 )
+        {
+
+            Some( right ) => self.version.node_hash_with( self.algorithm, left, right ),
+            None if self.version.promotes_unpaired() => *left,
+            None => self.version.node_hash_with( self.algorithm, left, left )
+                
+        }
+        
+    }
+
+    // The hash on the top level, or the empty hash if there are no levels 
+    fn top_hash( &self ) -> Digest
+    {
+
+        match self.levels.last()
+        {
+
+            Some( row ) => row[ 0 ],
+            None => self.algorithm.empty_hash()
+                
+        }
+        
+    }
+
+    // Returns the hashes on a level of the tree, counting down from the root ( level 0 ) to the
+    // leaves ( level height )
+    fn level( &self, level: usize ) -> &Vec<Digest>
+    {
+
+        &self.levels[ self.height - level ]
         
     }
 
-    // Gets the hashes needed to execute a proof on a given leaf value 
     /* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *
      *                                                                                       *
-     * The algorithm for getting proof hashes is as follows:                                 *
+     * Appends a leaf to the tree without rebuilding it. Only the last node of each level    *
+     * has the new leaf below it, so starting from the new leaf hash, the parent of the last *
+     * node is recomputed ( or added ) on each level until a level with a single node is     *
+     * reached, which is the new root.                                                       *
      *                                                                                       *
-     * 1. Set the initial current level to be the height of the tree as we are               *
-     *    from the bottom of the tree up, set the next hash to be a leaf hash with the       *
-     *    input value and create the vector of Nodes to store the path in.                   *
+     * First pass:                 Append 5:                                                 *
+     *  [ 1234 ]                    [ 1234, 5 ]  ->  [ 12345 ]  ( V2+, 5 is promoted )       *
+     *  [ 12, 34 ]                  [ 12, 34, 5 ]                                            *
+     *  [ 1, 2, 3, 4 ]              [ 1, 2, 3, 4, 5 ]                                        *
      *                                                                                       *
-     * 2. Provided that the current level is > 0                                             *
+     * This takes O( log n ) hashes and gives the same tree as a full rebuild.               *
+     *                                                                                       *
+     * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+    #[allow(dead_code)]
+    pub fn append( &mut self, value: Transaction )
+    {
+
+        let leaf_hash = self.version.leaf_hash_with( self.algorithm, &value );
+        self.nodes.push( value );
+        self.leaf_count = self.nodes.len();
+        if self.levels.is_empty()
+        {
+
+            self.levels.push( Vec::new() );
+            
+        }
+        self.levels[ 0 ].push( leaf_hash );
+        // Recomputes the parent of the last node on each level 
+        let mut level = 0;
+        while self.levels[ level ].len() > 1
+        {
+
+            let position = ( self.levels[ level ].len() - 1 ) / 2;
+            let parent = self.parent_hash( level, position );
+            if self.levels.len() == level + 1
+            {
+
+                self.levels.push( Vec::new() );
+                
+            }
+            let above = &mut self.levels[ level + 1 ];
+            if position < above.len()
+            {
+
+                above[ position ] = parent;
+                
+            }
+            else
+            {
+
+                above.push( parent );
+                
+            }
+            level += 1;
+            
+        }
+        self.height = self.levels.len() - 1;
+        self.hash = self.top_hash();
+        
+    }
+
+    // Replaces the leaf at the given index, rehashing only the path from the leaf to the root,
+    // returns true if successful and false if there is no leaf at the index 
+    #[allow(dead_code)]
+    pub fn update( &mut self, index: usize, value: Transaction ) -> bool
+    {
+
+        // If the index isn't within the length of the vector there is nothing to update
+        if index >= self.leaf_count()
+        {
+
+            return false;
+            
+        }
+        self.levels[ 0 ][ index ] = self.version.leaf_hash_with( self.algorithm, &value );
+        self.nodes[ index ] = value;
+        // Recomputes the parent of the changed node on each level 
+        let mut position = index;
+        for level in 0 .. self.height
+        {
+
+            position /= 2;
+            self.levels[ level + 1 ][ position ] = self.parent_hash( level, position );
+            
+        }
+        self.hash = self.top_hash();
+        true
+        
+    }
+
+    // Gets the hashes needed to execute a proof on the leaf at a given index
+    /* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *
      *                                                                                       *
-     * 3. Call the hash index function on the next hash, if the index returned is >= 0       *
-     *    then this hash exists at the current level indexed at the return value,            *
-     *    otherwise the function returns -1 and this step in the process is skipped.         *
+     * The algorithm for getting proof hashes is as follows:                                 *
      *                                                                                       *
-     * 4. Provided the next hash is found at this level, store all the nodes on the          *
-     *    current level. If the Tree type of the node with this hash is a Leaf or a          *
-     *    Tree, add the appropriate hash to the vector and then create a node hash           *
-     *    with the current hash and the hash just added to the vector, set the next          *
-     *    hash equal to this result. If the Tree type was not a Leaf or a Tree               *
-     *    ( empty ) then do nothing.                                                         *
+     * 1. Set the initial current level to be the height of the tree as we are working       *
+     *    from the bottom of the tree up, set the position to be the index of the leaf and   *
+     *    create the vector of Nodes to store the path in.                                   *
      *                                                                                       *
-     * Note: For deciding which hash to add into the vector we assess where we are in        * 
-     *       the current level of the tree in the following way:                             * 
+     * 2. Provided that the current level is > 0                                             *
      *                                                                                       *
-     *                        Is the index of the current hash even?                         *
+     * 3. Find the sibling of the node at the current position, the sibling of an even       *
+     *    position is the node after it and the sibling of an odd position is the node       *
+     *    before it:                                                                         *
+     *                                                                                       *
+     *                           Is the current position even?                               *
      *                                 /                       \                             *
      *                              yes                         no                           *
      *                              /                             \                          *
-     *                    are there are more                 are there more hashes           *
-     *                    hashes on this                     on this level before this one?  *
-     *                    level after this one?                      /        \              *
-     *                     /               \                      yes          no            *
-     *                  yes                 no                    /              \           *
-     *                  /                     \            then push the       do nothing.   *
-     *          then push the next     then push this      previous hash to                  *
-     *          hash onto the          hash onto the       the vector and set                *
-     *          vector and sets the    vector and set the  the next hash to be               *
-     *          next hash to be a      next hash to be a   a node hash with the              *
-     *          node hash of the       node hash of the    previous hash and                 *
-     *          current and next       current hash with   the current hash.                 *
-     *          hashes.                itself.                                               *
-     *                                                                                       *
-     *     This process with checking indicies and boundaries is used so that the algorithm  *
-     *     chooses the right neighboring node with the current node that make up the         *
-     *     children of the parent node needed to get to the root with this hash.             *
+     *                    is there a node on                  push the previous              *
+     *                    this level after                    node's hash as a               *
+     *                    this one?                           left hash.                     *
+     *                     /               \                                                 *
+     *                  yes                 no                                               *
+     *                  /                     \                                              *
+     *          push the next node's     V2+ trees promote the node so nothing is pushed,    *
+     *          hash as a right hash.    older trees push the node's own hash as a right     *
+     *                                   hash as it was fused with itself.                   *
      *                                                                                       *
-     *     Consider any level in the tree, it will have nodes indexed from 0 to n where n    *
-     *     is the number of nodes on the level. Since the process of checking an even index  *
-     *     is checking the index % 2, 0 and all other even indicies will make up the left    *
-     *     child of their parent root, so to get its corresponding hash look at the index    *
-     *     that follows the current one. Odd indicies will be the right hand children of     *
-     *     the parent node so to get teh corresponding hash look at the index that precedes  *
-     *     the current one.                                                                  *
+     * 4. Halve the position ( the parent of the nodes at 2i and 2i + 1 is at i on the       *
+     *    level above ), decrease the current level and repeat steps 2 through 4 until the   *
+     *    root of the tree has been reached.                                                 *
      *                                                                                       *
-     * 5. Decrease the current level and repeat steps 2 through 5 until the root of the      *
-     *    tree has been reached.                                                             *
+     * 5. Return the vector of Nodes                                                         *
      *                                                                                       *
-     * 6. Return the vector of Nodes                                                         *
+     * Note: The path only depends on the position of the leaf, so leaves with the same      *
+     *       content each get the path to their own position.                                *
      *                                                                                       * 
      * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
     #[allow(dead_code)]
-    pub fn get_proof_hashes( &mut self, value: &Transaction ) -> Vec<Node> 
+    pub fn get_proof_hashes_at( &self, index: usize ) -> Result< Vec<Node>, ProofError >
     {
 
+        // There is no path to a leaf past the end of the tree
+        if index >= self.leaf_count()
+        {
+
+            return Err( ProofError::IndexOutOfRange{ index, leaf_count: self.leaf_count() } );
+            
+        }
         // The current level in the tree that the traversal is on 
         let mut current_level = self.height();
-        // The next hash to be examined, originally this is the leaf hash of the given value 
-        let mut next = ::hash_util::create_leaf_hash( &value.get_value() );
-        // The hashes needed for a proof on this value 
+        // The position of the node on the current level
+        let mut position = index;
+        // The hashes needed for a proof on this leaf 
         let mut hashes = Vec::new();
         // Traverses up the tree until the root has been reached 
         while current_level > 0
         {
 
-            // The index of the next hash 
-            //let mut hash_index = self.get_hash_index( current_level, &next );
-            // If this index exists ( is a non negative integer ) then assess the hashes 
-            //if hash_index >= 0
-            let next_copy = next.clone();
-            let hash_index = self.get_hash_index( current_level, next_copy );
-            if hash_index >= 0
+            // The nodes on the current level 
+            let current_nodes = self.level( current_level );
+            let hash = &current_nodes[ position ];
+            if position % 2 == 1
             {
 
-                // The nodes on the current level 
-                let current_nodes = self.map.get( &current_level ).unwrap();
-                // Match case on the node returned from the hash index 
-                match current_nodes.get( hash_index as usize )
-                {
+                // The left sibling
+                hashes.push( Node::Left( current_nodes[ position - 1 ] ) );
+                
+            }
+            else if let Some( next_node ) = current_nodes.get( position + 1 )
+            {
 
-                    // Checks to make sure the type is either a tree leaf or tree node, otherwise
-                    // these operations are ignored 
-                    Some( &Tree::Leaf{ ref hash, .. } ) |
-                    Some( &Tree::Node{ ref hash, .. } ) =>
-                    {
-
-                        // If the hash index is even
-                        if hash_index % 2 == 0
-                        {
-
-                            // If a node is returned from index + 1
-                            if let Some( next_node ) = current_nodes.get( ( hash_index + 1 ) as usize )
-                            {
-
-                                // Push the right hash onto the vector 
-                                hashes.push( Node::Right( next_node.hash().clone() ) );
-                                // Reset next to be the hash of the current and next hashes 
-                                next = ::hash_util::create_node_hash( hash, next_node.hash() );
-                                
-                            }
-                            // If a node isn't returned from index + 1
-                            else
-                            {
-
-                                // Push the right hash onto the vector 
-                                hashes.push( Node::Right( hash.clone() ) );
-                                // Reset next to be the hash of the current hash with itself 
-                                next = ::hash_util::create_node_hash( hash, hash );
-
-                            }
-                            
-                        }
-                        // Otherwise if the index is odd 
-                        else
-                        {
-
-                            // If a node is returned from index - 1
-                            if let Some( next_node ) = current_nodes.get( ( hash_index - 1 ) as usize )
-                            {
-
-                                // Push the left hash onto the vector 
-                                hashes.push( Node::Left( next_node.hash().clone() ) );
-                                // Reset the next to be the hash of the next hash and the current hash 
-                                next = ::hash_util::create_node_hash( next_node.hash(), hash );
-                                
-                            }
-                            
-                        }
-                        
-                    }
-                    // If the Some case returned none then none of the operations with hashing should
-                    // be executed and the while loop should just continue 
-                    _ => continue,
-                    
-                };
+                // The right sibling
+                hashes.push( Node::Right( *next_node ) );
+                
+            }
+            else if !self.version.promotes_unpaired()
+            {
+
+                // The node was fused with itself 
+                hashes.push( Node::Right( *hash ) );
                 
             }
-            // Decreases the current level 
+            // Moves to the parent on the level above 
+            position /= 2;
             current_level -= 1;
             
         }
         // Returns the hashes 
-        hashes
+        Ok( hashes )
+        
+    }
+
+    // Gets the hashes needed to execute a proof on a given leaf value, the first leaf holding
+    // the value is used 
+    #[allow(dead_code)]
+    pub fn get_proof_hashes( &self, value: &Transaction ) -> Result< Vec<Node>, ProofError >
+    {
+
+        let index = self.index_of( value ).ok_or( ProofError::LeafNotFound )?;
+        self.get_proof_hashes_at( index )
+        
+    }
+
+    // Returns the index of the first leaf holding the given value
+    #[allow(dead_code)]
+    pub fn index_of( &self, value: &Transaction ) -> Option<usize>
+    {
+
+        self.nodes.iter().position( | node | node == value )
+        
+    }
+
+    // Determines whether the tree is ambiguous, a tree that fuses unpaired nodes with
+    // themselves is ambiguous when two siblings anywhere in it have the same hash as the same
+    // root could then be built from a list without the duplicated nodes ( CVE-2012-2459 )
+    #[allow(dead_code)]
+    pub fn is_ambiguous( &self ) -> bool
+    {
+
+        // Trees that promote unpaired nodes never duplicate them
+        if self.version.promotes_unpaired()
+        {
+
+            return false;
+            
+        }
+        // Checks every pair of siblings on every level 
+        self.levels.iter().any( | level | level.chunks( 2 ).any( | pair | pair.len() == 2 && pair[ 0 ] == pair[ 1 ] ) )
+        
+    }
+
+    // Returns a Proof for the first leaf holding the given value, or an error if no leaf holds it
+    #[allow(dead_code)]
+    pub fn get_proof( &self, value: Transaction ) -> Result< Proof, ProofError >
+    {
+
+        // Finds the position of the value
+        let index = self.index_of( &value ).ok_or( ProofError::LeafNotFound )?;
+        self.get_proof_at( index )
         
     }
 
-    // Returns a Proof given the leaf value to verify
+    // Returns a Proof for the leaf at the given index, or an error if there is no such leaf
     #[allow(dead_code)]
-    pub fn get_proof( &mut self, value: Transaction ) -> Proof
+    pub fn get_proof_at( &self, index: usize ) -> Result< Proof, ProofError >
     {
 
         // Finds the path for the proof
-        let path = self.get_proof_hashes( &value );
+        let path = self.get_proof_hashes_at( index )?;
         // Returns a new proof with this path
-        Proof::new( value.clone(), self.root_hash().clone(), path )
+        Ok( Proof::new( self.version, self.algorithm, self.nodes[ index ].clone(), *self.root_hash(), index, self.leaf_count(), path ) )
         
     }
 
-    // Writes the serialization of a Merkle Tree to a specified output file
+    // Returns a MultiProof for the leaves at the given indices, the indices can be in any
+    // order and repeated indices are only proven once 
+    /* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *
+     *                                                                                       *
+     * Walks up the tree keeping the positions of the nodes the verifier will know on each   *
+     * level, starting with the proven leaves. For each known position the sibling's hash    *
+     * is stored unless the sibling is known as well, then the positions are halved to get   *
+     * the known nodes on the level above. The last node of an odd level has no sibling, so  *
+     * nothing is stored for it.                                                             *
+     *                                                                                       *
+     * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+    #[allow(dead_code)]
+    pub fn get_multi_proof( &self, indices: &[usize] ) -> Result< MultiProof, ProofError >
+    {
+
+        // The distinct positions in order
+        let mut known = indices.to_vec();
+        known.sort_unstable();
+        known.dedup();
+        match known.last()
+        {
+
+            None => return Err( ProofError::NoLeaves ),
+            Some( &index ) if index >= self.leaf_count() =>
+                return Err( ProofError::IndexOutOfRange{ index, leaf_count: self.leaf_count() } ),
+            _ => {}
+                
+        }
+        let values = known.iter().map( | &index | self.nodes[ index ].clone() ).collect();
+        let proven = known.clone();
+        // The sibling hashes the verifier needs 
+        let mut hashes = Vec::new();
+        let mut current_level = self.height();
+        while current_level > 0
+        {
+
+            let current_nodes = self.level( current_level );
+            for ( i, &position ) in known.iter().enumerate()
+            {
+
+                let sibling = position ^ 1;
+                // A known sibling is computed by the verifier
+                let sibling_known = ( i > 0 && known[ i - 1 ] == sibling ) || known.get( i + 1 ) == Some( &sibling );
+                if !sibling_known && sibling < current_nodes.len()
+                {
+
+                    hashes.push( current_nodes[ sibling ] );
+                    
+                }
+                
+            }
+            // The parents of the known nodes
+            known = known.iter().map( | position | position / 2 ).collect();
+            known.dedup();
+            current_level -= 1;
+            
+        }
+        Ok( MultiProof::new( self.version, self.algorithm, *self.root_hash(), self.leaf_count(), proven, values, hashes ) )
+        
+    }
+
+    // Returns a ConsistencyProof that the tree of the first first_size leaves is a prefix of
+    // this tree ( RFC 6962, section 2.1.2 )
+    /* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *
+     *                                                                                       *
+     * With k the largest power of 2 below the size n of the current range of leaves:        *
+     *                                                                                       *
+     *     - if m <= k the first tree is inside the left subtree, so the proof continues     *
+     *       into the left subtree and the hash of the right subtree [ k, n ) is added       *
+     *     - otherwise the left subtree [ 0, k ) is shared by both trees, so the proof       *
+     *       continues into the right subtree with m - k leaves and the left subtree's hash  *
+     *       is added                                                                        *
+     *                                                                                       *
+     * Once m reaches the size of the range the range is a subtree of both trees and its     *
+     * hash is added, unless it is the whole first tree ( the verifier has its root )        *
+     *                                                                                       *
+     * Every range is either a complete subtree or runs to the last leaf, so each of their   *
+     * hashes is already on a level of the tree.                                             *
+     *                                                                                       *
+     * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+    #[allow(dead_code)]
+    pub fn get_consistency_proof( &self, first_size: usize ) -> Result< ConsistencyProof, ProofError >
+    {
+
+        if !self.version.promotes_unpaired()
+        {
+
+            return Err( ProofError::UnsupportedVersion( self.version ) );
+            
+        }
+        if first_size == 0 || first_size > self.leaf_count()
+        {
+
+            return Err( ProofError::InvalidSizes{ first: first_size, second: self.leaf_count() } );
+            
+        }
+        // The range of leaves the proof is in and the size of the first tree within it 
+        let mut start = 0;
+        let mut end = self.leaf_count();
+        let mut size = first_size;
+        let mut whole_first_tree = true;
+        let mut hashes = Vec::new();
+        while size < end - start
+        {
+
+            let split = ( end - start ).next_power_of_two() / 2;
+            if size <= split
+            {
+
+                hashes.push( self.subtree_hash( start + split, end ) );
+                end = start + split;
+                
+            }
+            else
+            {
+
+                hashes.push( self.subtree_hash( start, start + split ) );
+                start += split;
+                size -= split;
+                whole_first_tree = false;
+                
+            }
+            
+        }
+        if !whole_first_tree
+        {
+
+            hashes.push( self.subtree_hash( start, end ) );
+            
+        }
+        // The hashes were found from the root down, the verifier uses them from the leaves up
+        hashes.reverse();
+        Ok( ConsistencyProof::new( self.version, self.algorithm, first_size, self.leaf_count(), hashes ) )
+        
+    }
+
+    // Returns the hash of the subtree over the leaves in [ start, end ), the range has to be a
+    // complete subtree or run from a subtree's first leaf to the last leaf of the tree
+    fn subtree_hash( &self, start: usize, end: usize ) -> Digest
+    {
+
+        let level = ( end - start ).next_power_of_two().trailing_zeros() as usize;
+        self.levels[ level ][ start >> level ]
+        
+    }
+
+    // Returns the root hash the tree had when it held only its first size leaves, or None if
+    // the tree has fewer leaves 
+    #[allow(dead_code)]
+    pub fn prefix_root_hash( &self, size: usize ) -> Option<Digest>
+    {
+
+        if size > self.leaf_count()
+        {
+
+            return None;
+            
+        }
+        // Trees that fuse unpaired nodes with themselves hash a prefix differently, so it
+        // is rebuilt
+        if size == 0 || !self.version.promotes_unpaired()
+        {
+
+            return Some( *Merkle::with_algorithm( self.nodes[ .. size ].to_vec(), self.version, self.algorithm ).root_hash() );
+            
+        }
+        // The complete subtrees from left to right, largest first
+        let mut subtrees = Vec::new();
+        let mut start = 0;
+        for level in ( 0 .. self.levels.len() ).rev()
+        {
+
+            if size & ( 1 << level ) != 0
+            {
+
+                subtrees.push( self.levels[ level ][ start >> level ] );
+                start += 1 << level;
+                
+            }
+            
+        }
+        // Fuses them from the right, the smaller subtrees were promoted up to the larger ones
+        let mut hash = subtrees.pop()?;
+        while let Some( left ) = subtrees.pop()
+        {
+
+            hash = self.version.node_hash_with( self.algorithm, &left, &hash );
+            
+        }
+        Some( hash )
+        
+    }
+
+    // Writes the serialization of a Merkle Tree to a specified output file, a file already
+    // there is replaced whole or left as it was
     #[allow(dead_code)]
     pub fn write_to( &self, file_name: &str ) -> Result< (), Error >
     {
 
         // Serializes the json
         let json_merkle = serde_json::to_string( &self )?;
-        // Creates the new file with the given name
-        let mut file = OpenOptions::new().write( true ).create( true ).open( file_name ).unwrap();
-        // Appends the json to the file
-        #[allow(unused_variables)]
-        let temp = file.write( json_merkle.as_ref() );
-        // Returns the result or Error 
-        Ok( () )
+        // Replaces the file with the json atomically
+        file::write_atomically( file_name, json_merkle.as_ref() )
         
     }
 
@@ -506,14 +834,13 @@ impl Merkle
     {
 
         // Opens the file with the specified name  
-        let mut file = OpenOptions::new().read( true ).open( file_name ).unwrap();
+        let mut file = OpenOptions::new().read( true ).open( file_name )?;
         // Creates an emtpy string
         let mut json = String::new();
         // Reads the file as a string
-        #[allow(unused_variables)]
-        let temp = file.read_to_string( &mut json ); 
+        file.read_to_string( &mut json )?;
         // Returns the String or Error 
-        Ok( ( json ) )
+        Ok( json )
         
     }
 
@@ -523,9 +850,9 @@ impl Merkle
     {
 
         // Constructs the JSON string
-        let string = Merkle::read_json( file_name ).expect( "Failed to read in the JSON" );
+        let string = Merkle::read_json( file_name )?;
         // Constructs the vector
-        let vec: Merkle = serde_json::from_str( string.as_ref() ).expect( "Failed to conver the JSON to a vector" );
+        let vec: Merkle = serde_json::from_str( string.as_ref() )?;
         // Returns the vector or Error
         Ok( vec.nodes() )
         
@@ -618,22 +945,18 @@ impl Merkle
 
     // Finds the index of a hash on a specific level of the tree
     #[allow(dead_code)]
-    pub fn get_hash_index( &mut self, level: usize, hash: String ) -> i32
+    pub fn get_hash_index( &mut self, level: usize, hash: Digest ) -> i32
     {
 
-        // The vector of tree nodes at the target level
-        //
-        // .unwrap() used to unwrap the Option returned by get
-        let target_level = self.map.get( &level ).unwrap();
-        // We then iterate over the length of the target level's vector
-        for i in 0 .. target_level.len()
+        // The vector of hashes at the target level
+        let target_level = self.level( level );
+        // We then iterate over the target level's vector
+        for ( i, current_node ) in target_level.iter().enumerate()
         {
 
-            // At each index in the target level, we pull the node from the vector
-            let current_node = target_level[ i ].clone();
-            // This node's hash is then compared with the target hash entered by the user,
-            // if they are the same, the index is returned. 
-            if *current_node.hash() == hash
+            // At each index in the target level, the node's hash is compared with the target
+            // hash entered by the user, if they are the same, the index is returned. 
+            if *current_node == hash
             {
 
                 // The index as a 32 sized integer 
@@ -644,28 +967,24 @@ impl Merkle
         }
         // If the hash wasn't found at this level, -1 ( representing a false value )
         // is returned
-        return -1;
+        -1
         
     }
 
     // Finds a hash at a specific level of the tree
     #[allow(dead_code)]
-    pub fn hash_found_at_level( &mut self, level: usize, hash: String ) -> bool
+    pub fn hash_found_at_level( &mut self, level: usize, hash: Digest ) -> bool
     {
 
-        // The vector of tree nodes at the target level
-        //
-        // .unwrap() used to unwrap the Option returned by get 
-        let target_level = self.map.get( &level ).unwrap();
-        // We then iterate over the length of the target level's vector 
-        for i in 0 .. target_level.len() 
+        // The vector of hashes at the target level
+        let target_level = self.level( level );
+        // We then iterate over the target level's vector 
+        for current_node in target_level.iter() 
         {
 
-            // At each index in the target level, we pull the node from the vector 
-            let current_node = target_level[ i ].clone();
-            // This node's hash is then compared with the target hash entered by the user,
-            // if they are the same, the boolean true is returned. 
-            if *current_node.hash() == hash
+            // At each node in the target level, the node's hash is compared with the target
+            // hash entered by the user, if they are the same, the boolean true is returned. 
+            if *current_node == hash
             {
 
                 return true;
@@ -684,13 +1003,8 @@ impl Merkle
     pub fn insert( &mut self, value: Transaction )
     {
 
-        // Inserts the new value into the nodes at the last index ( # of leaves currently
-        // in the vector )
-        self.nodes.push( value );
-        // Sets the leaf count to the new length of the nodes vector
-        self.leaf_count = self.nodes.len();
-        // Reconstructs the tree with the new node 
-        self.construct_tree();
+        // Appends the new value after the last leaf, only rehashing the path to the new leaf 
+        self.append( value );
         
     }
     
@@ -700,11 +1014,9 @@ impl Merkle
     {
 
         // The get function for vectors in rust returns the option ( optional value ) of a
-        // reference of whatever value type is returned from the get. Due to to this we use
-        // Some ( ... ) because if the value from get exists ( the index was valid ) then we
-        // can return it and otherwise if the index wasn't valid the return was None. Unwrap
-        // is then used to return the correct Option of this. 
-        Some( self.nodes.get( index ) ).unwrap() 
+        // reference of whatever value type is returned from the get, so if the index was valid
+        // the value is returned and otherwise None is returned.
+        self.nodes.get( index ) 
 
     }
 
@@ -758,12 +1070,30 @@ impl Merkle
         
     }
     
+    // Returns the format the tree is hashed with
+    #[allow(dead_code)]
+    pub fn version( &self ) -> TreeVersion
+    {
+
+        self.version
+        
+    }
+    
+    // Returns the hash algorithm the tree is hashed with
+    #[allow(dead_code)]
+    pub fn algorithm( &self ) -> HashAlgorithm
+    {
+
+        self.algorithm
+        
+    }
+
     // Returns the root hash of a given tree
     #[allow(dead_code)]
-    pub fn root_hash( &self ) -> &String
+    pub fn root_hash( &self ) -> &Digest
     {
 
-        self.root.hash()
+        &self.hash
         
     }
     
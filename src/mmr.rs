@@ -0,0 +1,464 @@
+// Use statements
+//
+// Standard library
+#[allow(unused_imports)]
+use std::*;
+// Used for creating hashes
+use hash_util::*;
+
+/*
+ *
+ * Merkle Mountain Range ( MMR ):
+ *     - This file contains an append only accumulator over block hashes. The leaves are
+ *       kept as a row of complete binary trees ( mountains ) of decreasing size, one for
+ *       each bit of the number of leaves, and the root bags the mountains' peaks together.
+ *
+ *     - Appending a leaf only merges the mountains at the right end, so a header can commit
+ *       to every block before it and a block can be proven against any later header without
+ *       the blocks in between.
+ *
+ *     - Leaves are the domain separated hashes of the block hashes and peaks are bagged from
+ *       the right ( the last two peaks are fused, then the one before with the result, .. ),
+ *       which gives the same root as an RFC 6962 tree over the same leaves.
+ *
+ */
+
+// Merkle Mountain Range struct
+#[derive( Debug, Clone, Default )]
+#[allow(dead_code)]
+pub struct Mmr
+{
+
+    // The hashes on each level of the mountains from the leaves ( levels[ 0 ] ) up, the node
+    // at position i on level h covers the leaves [ i * 2^h, ( i + 1 ) * 2^h )
+    levels: Vec< Vec<Digest> >,
+    // The hash algorithm the leaves and nodes are hashed with
+    algorithm: HashAlgorithm
+
+}
+
+// Merkle Mountain Range impl
+#[allow(dead_code)]
+impl Mmr
+{
+
+    // New empty MMR constructor
+    pub fn new() -> Self
+    {
+
+        Mmr::with_algorithm( HashAlgorithm::default() )
+
+    }
+
+    // New empty MMR constructor hashing with the given algorithm
+    pub fn with_algorithm( algorithm: HashAlgorithm ) -> Self
+    {
+
+        Mmr{ levels: Vec::new(), algorithm }
+
+    }
+
+    // Constructs an MMR with the given hashes appended in order
+    pub fn from_hashes< 'a, I: IntoIterator< Item = &'a Digest > >( hashes: I ) -> Self
+    {
+
+        Mmr::from_hashes_with_algorithm( HashAlgorithm::default(), hashes )
+
+    }
+
+    // Constructs an MMR hashing with the given algorithm with the given hashes appended in order
+    pub fn from_hashes_with_algorithm< 'a, I: IntoIterator< Item = &'a Digest > >( algorithm: HashAlgorithm, hashes: I ) -> Self
+    {
+
+        let mut mmr = Mmr::with_algorithm( algorithm );
+        for hash in hashes
+        {
+
+            mmr.append( hash );
+
+        }
+        mmr
+
+    }
+
+    /* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *
+     *                                                                                       *
+     * Appends a hash as the last leaf. While the level the new node was added to has an     *
+     * even number of nodes, the last two form a complete mountain and are fused into a      *
+     * node on the level above:                                                              *
+     *                                                                                       *
+     *  3 leaves:   12            4 leaves:      1234                                        *
+     *             /  \                         /    \                                       *
+     *            1    2   3                  12      34                                     *
+     *                                       /  \    /  \                                    *
+     *                                      1    2  3    4                                   *
+     *                                                                                       *
+     * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+    pub fn append( &mut self, hash: &Digest )
+    {
+
+        let mut node = self.algorithm.id_leaf_hash( hash );
+        let mut level = 0;
+        loop
+        {
+
+            if self.levels.len() == level
+            {
+
+                self.levels.push( Vec::new() );
+
+            }
+            self.levels[ level ].push( node );
+            let row = &self.levels[ level ];
+            if row.len() % 2 == 1
+            {
+
+                break;
+
+            }
+            node = self.algorithm.prefixed_node_hash( &row[ row.len() - 2 ], &row[ row.len() - 1 ] );
+            level += 1;
+
+        }
+
+    }
+
+    // Returns the nodes appending a hash after the first size leaves adds, from the leaf up to
+    // the highest node it completes, without appending it. The mountains the new leaf completes
+    // are the rightmost peaks of the prefix, one for each trailing one bit of its size
+    pub fn nodes_after( &self, size: usize, hash: &Digest ) -> Vec<Digest>
+    {
+
+        let mut peaks = self.peaks_at( cmp::min( size, self.len() ) );
+        let mut node = self.algorithm.id_leaf_hash( hash );
+        let mut nodes = vec![ node ];
+        let mut completed = size;
+        while completed % 2 == 1
+        {
+
+            let left = match peaks.pop()
+            {
+
+                Some( left ) => left,
+                None => break
+
+            };
+            node = self.algorithm.prefixed_node_hash( &left, &node );
+            nodes.push( node );
+            completed /= 2;
+
+        }
+        nodes
+
+    }
+
+    // Appends a leaf from the nodes appending its hash adds ( see nodes_after ), so an MMR can be
+    // rebuilt from stored nodes without hashing them again
+    pub fn append_nodes( &mut self, nodes: &[Digest] )
+    {
+
+        for ( level, node ) in nodes.iter().enumerate()
+        {
+
+            if self.levels.len() == level
+            {
+
+                self.levels.push( Vec::new() );
+
+            }
+            self.levels[ level ].push( *node );
+
+        }
+
+    }
+
+    // Removes the leaves after the first size leaves, the mountains of a prefix are kept on the
+    // levels so this only drops the nodes covering removed leaves
+    pub fn truncate( &mut self, size: usize )
+    {
+
+        for ( level, row ) in self.levels.iter_mut().enumerate()
+        {
+
+            row.truncate( size >> level );
+
+        }
+        while self.levels.last().is_some_and( | row | row.is_empty() )
+        {
+
+            self.levels.pop();
+
+        }
+
+    }
+
+    // Returns the number of leaves
+    pub fn len( &self ) -> usize
+    {
+
+        self.levels.first().map( | row | row.len() ).unwrap_or( 0 )
+
+    }
+
+    // Determines whether the MMR has no leaves
+    pub fn is_empty( &self ) -> bool
+    {
+
+        self.len() == 0
+
+    }
+
+    // Determines whether the leaf at an index is the given hash
+    pub fn leaf_is( &self, index: usize, hash: &Digest ) -> bool
+    {
+
+        self.levels.first().and_then( | row | row.get( index ) ) == Some( &self.algorithm.id_leaf_hash( hash ) )
+
+    }
+
+    // Returns the peaks of the MMR of the first size leaves from left ( largest ) to right
+    pub fn peaks_at( &self, size: usize ) -> Vec<Digest>
+    {
+
+        let mut peaks = Vec::new();
+        let mut start = 0;
+        for level in ( 0 .. self.levels.len() ).rev()
+        {
+
+            if size & ( 1 << level ) != 0
+            {
+
+                peaks.push( self.levels[ level ][ start >> level ] );
+                start += 1 << level;
+
+            }
+
+        }
+        peaks
+
+    }
+
+    // Returns the root of the MMR of the first size leaves, the empty hash if there are none
+    pub fn root_at( &self, size: usize ) -> Digest
+    {
+
+        bag( self.algorithm, self.peaks_at( cmp::min( size, self.len() ) ) )
+
+    }
+
+    // Returns the root of the MMR
+    pub fn root( &self ) -> Digest
+    {
+
+        self.root_at( self.len() )
+
+    }
+
+    // Returns a proof that the leaf at an index is in the MMR of the first size leaves, None if
+    // the index isn't below the size or the size is past the last leaf
+    pub fn prove( &self, index: usize, size: usize ) -> Option<MmrProof>
+    {
+
+        if index >= size || size > self.len()
+        {
+
+            return None;
+
+        }
+        let ( height, _, ordinal ) = mountain( index, size );
+        // The siblings from the leaf up to the peak of its mountain, the mountains start on
+        // multiples of their size so the sibling is found from the index alone
+        let path = ( 0 .. height ).map( | level | self.levels[ level ][ ( index >> level ) ^ 1 ] ).collect();
+        // The other peaks
+        let mut peaks = self.peaks_at( size );
+        peaks.remove( ordinal );
+        Some( MmrProof::new( self.algorithm, index, size, path, peaks ) )
+
+    }
+
+}
+
+/*
+ *
+ * MMR Proof:
+ *     - Proves the leaf at an index is in an MMR of a given size. The path climbs the leaf's
+ *       mountain ( the direction at each level is the matching bit of the leaf's position in
+ *       its mountain ) and the other peaks are bagged with the mountain's peak.
+ *
+ */
+#[derive( Debug, Clone, PartialEq, Serialize, Deserialize )]
+#[allow(dead_code)]
+pub struct MmrProof
+{
+
+    // The hash algorithm the MMR is hashed with
+    #[serde(default)]
+    algorithm: HashAlgorithm,
+    // The position of the leaf
+    index: usize,
+    // The number of leaves in the MMR
+    size: usize,
+    // The siblings from the leaf up to its mountain's peak
+    path: Vec<Digest>,
+    // The peaks of the other mountains, from left to right
+    peaks: Vec<Digest>
+
+}
+
+// MMR proof impl
+#[allow(dead_code)]
+impl MmrProof
+{
+
+    // New MMR proof constructor
+    pub fn new( algorithm: HashAlgorithm, index: usize, size: usize, path: Vec<Digest>, peaks: Vec<Digest> ) -> Self
+    {
+
+        MmrProof
+        {
+
+            algorithm,
+            index,
+            size,
+            path,
+            peaks
+
+        }
+
+    }
+
+    // Verifies that the given hash is the leaf at the proof's index of the MMR with the given root
+    pub fn verify( &self, hash: &Digest, root: &Digest ) -> bool
+    {
+
+        if self.index >= self.size
+        {
+
+            return false;
+
+        }
+        let ( height, start, ordinal ) = mountain( self.index, self.size );
+        if self.path.len() != height || self.peaks.len() + 1 != self.size.count_ones() as usize
+        {
+
+            return false;
+
+        }
+        // Climbs the mountain
+        let position = self.index - start;
+        let mut node = self.algorithm.id_leaf_hash( hash );
+        for ( level, sibling ) in self.path.iter().enumerate()
+        {
+
+            node = if ( position >> level ) & 1 == 0
+            {
+
+                self.algorithm.prefixed_node_hash( &node, sibling )
+
+            }
+            else
+            {
+
+                self.algorithm.prefixed_node_hash( sibling, &node )
+
+            };
+
+        }
+        // Bags the peaks with the mountain's peak in its place
+        let mut peaks = self.peaks.clone();
+        peaks.insert( ordinal, node );
+        bag( self.algorithm, peaks ) == *root
+
+    }
+
+    // Returns the hash algorithm the MMR is hashed with
+    pub fn algorithm( &self ) -> HashAlgorithm
+    {
+
+        self.algorithm
+
+    }
+
+    // Returns the position of the leaf
+    pub fn index( &self ) -> usize
+    {
+
+        self.index
+
+    }
+
+    // Returns the number of leaves in the MMR
+    pub fn size( &self ) -> usize
+    {
+
+        self.size
+
+    }
+
+    // Returns the siblings from the leaf up to its mountain's peak
+    pub fn path( &self ) -> &Vec<Digest>
+    {
+
+        &self.path
+
+    }
+
+    // Returns the peaks of the other mountains
+    pub fn peaks( &self ) -> &Vec<Digest>
+    {
+
+        &self.peaks
+
+    }
+
+}
+
+// Finds the mountain holding a leaf in an MMR of a given size as its height, its first leaf and
+// its place among the peaks
+fn mountain( index: usize, size: usize ) -> ( usize, usize, usize )
+{
+
+    let mut start = 0;
+    let mut ordinal = 0;
+    for height in ( 0 .. usize::BITS as usize ).rev()
+    {
+
+        if size & ( 1 << height ) != 0
+        {
+
+            if index < start + ( 1 << height )
+            {
+
+                return ( height, start, ordinal );
+
+            }
+            start += 1 << height;
+            ordinal += 1;
+
+        }
+
+    }
+    ( 0, start, ordinal )
+
+}
+
+// Bags peaks into a root from the right, no peaks bag to the empty hash
+fn bag( algorithm: HashAlgorithm, mut peaks: Vec<Digest> ) -> Digest
+{
+
+    let mut root = match peaks.pop()
+    {
+
+        Some( peak ) => peak,
+        None => return algorithm.empty_hash()
+
+    };
+    while let Some( peak ) = peaks.pop()
+    {
+
+        root = algorithm.prefixed_node_hash( &peak, &root );
+
+    }
+    root
+
+}
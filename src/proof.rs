@@ -1,3 +1,8 @@
+// Crate inclusion
+//
+// Used for the json encoding of proofs
+extern crate serde_json;
+
 // Use statements
 //
 // Standard library
@@ -15,6 +20,14 @@ use hash_util::*;
 // Transaction acess
 #[allow(unused_imports)]
 use transaction::*;
+// The format the proof's tree was hashed with
+use tree::TreeVersion;
+// The length of a digest in the binary encoding
+use digest::DIGEST_LENGTH;
+// Used for displaying proof errors
+use std::fmt;
+// Serializing proofs fails with the crate's error
+use error::Error;
 
 /*
  *
@@ -24,18 +37,81 @@ use transaction::*;
  *
  */
 
+// The reasons a proof can't be made
+#[derive( Debug, Clone, PartialEq )]
+pub enum ProofError
+{
+
+    // No leaf of the tree holds the value
+    LeafNotFound,
+    // The index is past the last leaf of the tree
+    IndexOutOfRange{ index: usize, leaf_count: usize },
+    // The bytes aren't a proof in the compact binary encoding
+    InvalidEncoding,
+    // A multi-proof was asked for without any leaves
+    NoLeaves,
+    // A consistency proof needs 0 < first size <= second size
+    InvalidSizes{ first: usize, second: usize },
+    // The tree's version doesn't build RFC 6962 trees, so it has no consistency proofs
+    UnsupportedVersion( TreeVersion )
+        
+}
+
+// Display impl for proof errors
+impl fmt::Display for ProofError
+{
+
+    fn fmt( &self, f: &mut fmt::Formatter ) -> fmt::Result
+    {
+
+        match *self
+        {
+
+            ProofError::LeafNotFound => write!( f, "the value is not a leaf of the tree" ),
+            ProofError::IndexOutOfRange{ index, leaf_count } =>
+                write!( f, "leaf {} is out of range for a tree with {} leaves", index, leaf_count ),
+            ProofError::InvalidEncoding => write!( f, "the bytes are not an encoded proof" ),
+            ProofError::NoLeaves => write!( f, "a multi-proof needs at least one leaf" ),
+            ProofError::InvalidSizes{ first, second } =>
+                write!( f, "no consistency proof from a tree of {} leaves to a tree of {} leaves", first, second ),
+            ProofError::UnsupportedVersion( version ) => write!( f, "{:?} trees have no consistency proofs", version )
+                
+        }
+        
+    }
+    
+}
+
+// Proof errors can be used as standard errors
+impl error::Error for ProofError {}
+
 // The struct for a proof, a proof contains the leaf value that is attempting verification,
 // the hash of the roof of the tree, and a potential path from the root hash to where the
 // value's leaf hash would be in the tree.
-#[derive(Debug)]
+//
+// Proofs serialize to json as
+//
+//     { "version": "V3", "algorithm": "Sha3_256", "value": { "uid": .., .. }, "hash": "<root>",
+//       "index": 2, "leaf_count": 5, "path": [ { "Left": "<hash>" }, { "Right": "<hash>" } ] }
+#[derive( Debug, Clone, PartialEq, Serialize, Deserialize )]
 #[allow(dead_code)]
 pub struct Proof
 {
 
+    // The format the tree was hashed with
+    version: TreeVersion,
+    // The hash algorithm the tree was hashed with, proofs serialized before algorithms were
+    // recorded are read in with the default algorithm
+    #[serde(default)]
+    algorithm: HashAlgorithm,
     // The leaf value attempting verification
     value: Transaction,
     // The hash of the Merkle Tree's root node
-    hash: String,
+    hash: Digest,
+    // The position of the value's leaf in the tree
+    index: usize,
+    // The number of leaves in the tree
+    leaf_count: usize,
     // The vector containing the path from the root hash to the value leaf
     path: Vec<Node>
     
@@ -47,51 +123,731 @@ impl Proof
 {
 
     // New proof constructor
-    pub fn new( value: Transaction, hash: String, path: Vec<Node> ) -> Self
+    pub fn new( version: TreeVersion, algorithm: HashAlgorithm, value: Transaction, hash: Digest, index: usize, leaf_count: usize, path: Vec<Node> ) -> Self
     {
 
-        // Creates a new proof with the given value hash and path
+        // Creates a new proof with the given version, algorithm, value, hash, position and path
         Proof
         {
 
             // Sets the vales to the input fields 
-            value: value,
-            hash: hash,
-            path: path
+            version,
+            algorithm,
+            value,
+            hash,
+            index,
+            leaf_count,
+            path
             
         }
         
     }
 
-    // Verifies whether a given value has been hashed into the tree and thereby into the
-    // composition of the root hash
-    pub fn verify( &self, root: &String ) -> bool
+    // Verifies whether the proof's value has been hashed into the tree at the proof's index
+    // and thereby into the composition of the given root hash
+    pub fn verify( &self, root: &Digest ) -> bool
     {
 
-        // The leaf hash value for the value attempting verification 
-        let mut hash = ::hash_util::create_leaf_hash( &self.value.get_value() );
-        // The verification system works by iterating through the path list which allows us to trace a
-        // hash up through the tree to the root. At each instance in the path a new node hash is
-        // constructed with the Node enum ( proof ) and the current hash marker ( hash ).
-        // After this process has reached the end of the path, the current hash marker will be the
-        // potential root hash if the leaf value was in fact in the tree. 
-        for instance in &self.path
+        verify_inclusion( self.version, self.algorithm, &self.value, self.index, self.leaf_count, &self.path, root )
+        
+    }
+
+    // Returns the position of the value's leaf in the tree
+    pub fn index( &self ) -> usize
+    {
+
+        self.index
+        
+    }
+
+    // Returns the number of leaves in the tree
+    pub fn leaf_count( &self ) -> usize
+    {
+
+        self.leaf_count
+        
+    }
+
+    // Returns the format the tree was hashed with
+    pub fn version( &self ) -> TreeVersion
+    {
+
+        self.version
+        
+    }
+
+    // Returns the hash algorithm the tree was hashed with
+    pub fn algorithm( &self ) -> HashAlgorithm
+    {
+
+        self.algorithm
+        
+    }
+
+    // Returns the leaf value the proof is for
+    pub fn value( &self ) -> &Transaction
+    {
+
+        &self.value
+        
+    }
+
+    // Returns the root hash of the tree the proof was made from
+    pub fn root( &self ) -> &Digest
+    {
+
+        &self.hash
+        
+    }
+
+    // Returns the path from the value's leaf to the root
+    pub fn path( &self ) -> &Vec<Node>
+    {
+
+        &self.path
+        
+    }
+
+    // Serializes the proof to json
+    pub fn to_json( &self ) -> Result< String, Error >
+    {
+
+        Ok( serde_json::to_string( self )? )
+        
+    }
+
+    // Constructs a proof from its json
+    pub fn from_json( json: &str ) -> Result< Proof, Error >
+    {
+
+        Ok( serde_json::from_str( json )? )
+        
+    }
+
+    /*
+     *
+     * Compact binary encoding:
+     *     - The proof with its hashes as raw 32 byte digests rather than hex strings, all
+     *       integers are big endian. 
+     *
+     *     - version: 1 byte, the tree version in the low 4 bits ( 0 for V0, 1 for V1, .. ) and
+     *       the hash algorithm in the high 4 bits ( 0 for SHA3-256, 1 for SHA-256, .. )
+     *     - index, leaf count: 8 bytes each
+     *     - root: 32 bytes
+     *     - path: 1 byte count, then for each node 1 byte direction ( 0 left, 1 right ) and
+     *       the 32 byte sibling digest
+     *     - value: the rest of the bytes, the canonical encoding of the transaction
+     *
+     */
+    pub fn to_bytes( &self ) -> Vec<u8>
+    {
+
+        let mut bytes = vec![ self.algorithm.to_byte() << 4 | self.version.to_byte() ];
+        bytes.extend_from_slice( &( self.index as u64 ).to_be_bytes() );
+        bytes.extend_from_slice( &( self.leaf_count as u64 ).to_be_bytes() );
+        bytes.extend_from_slice( self.hash.as_bytes() );
+        bytes.push( self.path.len() as u8 );
+        for node in &self.path
         {
 
-            hash = match instance
+            let ( direction, hash ) = match *node
             {
 
-                &Node::Left( ref proof ) => ::hash_util::create_node_hash( proof, &hash ),
-                &Node::Right( ref proof ) => ::hash_util::create_node_hash( &hash, proof ),
-                
+                Node::Left( ref hash ) => ( 0, hash ),
+                Node::Right( ref hash ) => ( 1, hash )
+                    
             };
+            bytes.push( direction );
+            bytes.extend_from_slice( hash.as_bytes() );
+            
+        }
+        bytes.extend_from_slice( &self.value.encode() );
+        bytes
+        
+    }
+
+    // Constructs a proof from its compact binary encoding
+    pub fn from_bytes( bytes: &[u8] ) -> Result< Proof, ProofError >
+    {
+
+        let mut reader = Reader{ bytes };
+        let byte = reader.take( 1 )?[ 0 ];
+        let version = TreeVersion::from_byte( byte & 0x0f ).ok_or( ProofError::InvalidEncoding )?;
+        let algorithm = HashAlgorithm::from_byte( byte >> 4 ).ok_or( ProofError::InvalidEncoding )?;
+        let index = reader.integer()?;
+        let leaf_count = reader.integer()?;
+        let hash = reader.digest()?;
+        let count = reader.take( 1 )?[ 0 ];
+        let mut path = Vec::new();
+        for _ in 0 .. count
+        {
+
+            let direction = reader.take( 1 )?[ 0 ];
+            let sibling = reader.digest()?;
+            path.push( match direction
+            {
+
+                0 => Node::Left( sibling ),
+                1 => Node::Right( sibling ),
+                _ => return Err( ProofError::InvalidEncoding )
+                    
+            } );
+            
+        }
+        let value = Transaction::decode( reader.bytes ).ok_or( ProofError::InvalidEncoding )?;
+        Ok( Proof::new( version, algorithm, value, hash, index, leaf_count, path ) )
+        
+    }
+    
+}
+
+// Reads the fields of an encoded proof from the front of its bytes 
+struct Reader<'a>
+{
+
+    // The bytes that haven't been read yet
+    bytes: &'a [u8]
+        
+}
+
+// Reader impl
+impl<'a> Reader<'a>
+{
+
+    // Takes the next count bytes 
+    fn take( &mut self, count: usize ) -> Result< &'a [u8], ProofError >
+    {
+
+        if self.bytes.len() < count
+        {
+
+            return Err( ProofError::InvalidEncoding );
+            
+        }
+        let ( taken, rest ) = self.bytes.split_at( count );
+        self.bytes = rest;
+        Ok( taken )
+        
+    }
+
+    // Takes the next 32 bytes as a digest
+    fn digest( &mut self ) -> Result< Digest, ProofError >
+    {
+
+        Digest::from_slice( self.take( DIGEST_LENGTH )? ).ok_or( ProofError::InvalidEncoding )
+        
+    }
+
+    // Takes the next 8 bytes as a big endian integer
+    fn integer( &mut self ) -> Result< usize, ProofError >
+    {
+
+        let mut value = [ 0; 8 ];
+        value.copy_from_slice( self.take( 8 )? );
+        Ok( u64::from_be_bytes( value ) as usize )
+        
+    }
+    
+}
+
+// Verifies that a value is the leaf at the given index of a tree with the given root, without
+// needing the tree ( so clients holding only a block's Merkle Root can check a proof )
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *
+ *                                                                                       *
+ * The index and leaf count decide the shape of the path: at each level an odd position  *
+ * needs a left hash, an even position with a node after it needs a right hash and the   *
+ * last node of a level with an odd number of nodes is promoted ( V2+ ) or paired with   *
+ * itself ( older versions ). A path with a hash in the wrong direction, a missing hash  *
+ * or a hash left over doesn't verify, so the proof shows where the value is as well as  *
+ * that it is in the tree.                                                               *
+ *                                                                                       *
+ * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+pub fn verify_inclusion( version: TreeVersion, algorithm: HashAlgorithm, value: &Transaction, index: usize, leaf_count: usize, path: &[Node], root: &Digest ) -> bool
+{
+
+    // A proof can't be for a leaf past the end of the tree
+    if index >= leaf_count
+    {
+
+        return false;
+        
+    }
+    // The leaf hash value for the value attempting verification, from V3 on this is the
+    // hash of the transaction's id so every field of the transaction has to match
+    let mut hash = version.leaf_hash_with( algorithm, value );
+    // The position of the current hash on its level and the number of nodes on the level
+    let mut position = index;
+    let mut width = leaf_count;
+    let mut path = path.iter();
+    // The verification system works by iterating through the path list which allows us to trace a
+    // hash up through the tree to the root. At each level a new node hash is constructed
+    // with the next Node in the path ( proof ) and the current hash marker ( hash ).
+    // After this process has reached the root, the current hash marker will be the
+    // potential root hash if the leaf value was in fact in the tree. 
+    while width > 1
+    {
+
+        hash = if position % 2 == 1
+        {
+
+            match path.next()
+            {
+
+                Some( Node::Left( proof ) ) => version.node_hash_with( algorithm, proof, &hash ),
+                _ => return false
+                    
+            }
+            
+        }
+        else if position + 1 < width
+        {
+
+            match path.next()
+            {
+
+                Some( Node::Right( proof ) ) => version.node_hash_with( algorithm, &hash, proof ),
+                _ => return false
+                    
+            }
+            
+        }
+        else if version.promotes_unpaired()
+        {
+
+            hash
+                
+        }
+        else
+        {
+
+            match path.next()
+            {
+
+                Some( Node::Right( proof ) ) if *proof == hash => version.node_hash_with( algorithm, &hash, &hash ),
+                _ => return false
+                    
+            }
+            
+        };
+        // Moves to the parent on the level above 
+        position /= 2;
+        width = width.div_ceil( 2 );
+        
+    }
+    // Boolean check evaluating whether the whole path was used and the two hashes are equal,
+    // if they are then the root hash was correctly built within the given value, otherwise the
+    // value wasn't used in building the root hash at this position
+    path.next().is_none() && hash == *root
+    
+}
+
+/*
+ *
+ * Multi-proof:
+ *     - Proves a set of leaves against one root. The sibling hashes the leaves' paths have in
+ *       common are only stored once, and a sibling that is itself one of the proven leaves
+ *       ( or is built from them ) isn't stored at all as the verifier computes it.
+ *
+ *     - The hashes are stored in the order the verifier uses them: level by level from the
+ *       leaves up, left to right within a level.
+ *
+ */
+#[derive( Debug, Clone, PartialEq, Serialize, Deserialize )]
+#[allow(dead_code)]
+pub struct MultiProof
+{
+
+    // The format the tree was hashed with
+    version: TreeVersion,
+    // The hash algorithm the tree was hashed with
+    #[serde(default)]
+    algorithm: HashAlgorithm,
+    // The hash of the Merkle Tree's root node
+    hash: Digest,
+    // The number of leaves in the tree
+    leaf_count: usize,
+    // The positions of the proven leaves in increasing order
+    indices: Vec<usize>,
+    // The proven leaf values, in the same order as their positions
+    values: Vec<Transaction>,
+    // The sibling hashes the verifier can't compute from the proven leaves
+    hashes: Vec<Digest>
+
+}
+
+// The impl for a multi-proof
+#[allow(dead_code)]
+impl MultiProof
+{
+
+    // New multi-proof constructor
+    pub fn new( version: TreeVersion, algorithm: HashAlgorithm, hash: Digest, leaf_count: usize, indices: Vec<usize>, values: Vec<Transaction>, hashes: Vec<Digest> ) -> Self
+    {
+
+        MultiProof
+        {
+
+            version,
+            algorithm,
+            hash,
+            leaf_count,
+            indices,
+            values,
+            hashes
+                
+        }
+        
+    }
+
+    /* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *
+     *                                                                                       *
+     * Verifies that every value is the leaf at its index in a tree with the given root.     *
+     *                                                                                       *
+     * Starting with the leaf hashes of the values at their positions, each level is         *
+     * combined into the one above: a known node whose sibling is also known is fused with   *
+     * it, a known node whose sibling isn't known is fused with the next stored hash ( on    *
+     * the side its position decides ) and the last node of an odd level is promoted ( V2+ ) *
+     * or fused with itself. Once the root level is reached, every stored hash has to have   *
+     * been used and the computed root has to match.                                         *
+     *                                                                                       *
+     * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+    pub fn verify( &self, root: &Digest ) -> bool
+    {
+
+        // The leaves have to be distinct, in order and inside the tree
+        if self.indices.is_empty() || self.indices.len() != self.values.len() ||
+            self.indices.windows( 2 ).any( | pair | pair[ 0 ] >= pair[ 1 ] ) ||
+            self.indices[ self.indices.len() - 1 ] >= self.leaf_count
+        {
+
+            return false;
+            
+        }
+        // The known nodes on the current level as ( position, hash ) in order of position
+        let mut known: Vec<( usize, Digest )> = self.indices.iter().cloned().zip( self.values.iter().map( | value | self.version.leaf_hash_with( self.algorithm, value ) ) ).collect();
+        let mut hashes = self.hashes.iter();
+        let mut width = self.leaf_count;
+        while width > 1
+        {
+
+            let mut above = Vec::new();
+            let mut i = 0;
+            while i < known.len()
+            {
+
+                let ( position, ref hash ) = known[ i ];
+                let parent = if position % 2 == 1
+                {
+
+                    // The left sibling wasn't known ( or it would have taken this node )
+                    match hashes.next()
+                    {
+
+                        Some( left ) => self.version.node_hash_with( self.algorithm, left, hash ),
+                        None => return false
+                            
+                    }
+                    
+                }
+                else if position + 1 < width
+                {
+
+                    // The right sibling is either the next known node or a stored hash 
+                    match known.get( i + 1 )
+                    {
+
+                        Some( &( next, ref right ) ) if next == position + 1 =>
+                        {
+
+                            i += 1;
+                            self.version.node_hash_with( self.algorithm, hash, right )
+                                
+                        },
+                        _ => match hashes.next()
+                        {
+
+                            Some( right ) => self.version.node_hash_with( self.algorithm, hash, right ),
+                            None => return false
+                                
+                        }
+                        
+                    }
+                    
+                }
+                else if self.version.promotes_unpaired()
+                {
+
+                    *hash
+                        
+                }
+                else
+                {
+
+                    self.version.node_hash_with( self.algorithm, hash, hash )
+                        
+                };
+                above.push( ( position / 2, parent ) );
+                i += 1;
+                
+            }
+            known = above;
+            width = width.div_ceil( 2 );
+            
+        }
+        hashes.next().is_none() && known.len() == 1 && known[ 0 ].1 == *root
+        
+    }
+
+    // Returns the format the tree was hashed with
+    pub fn version( &self ) -> TreeVersion
+    {
+
+        self.version
+        
+    }
+
+    // Returns the hash algorithm the tree was hashed with
+    pub fn algorithm( &self ) -> HashAlgorithm
+    {
+
+        self.algorithm
+        
+    }
+
+    // Returns the root hash of the tree the proof was made from
+    pub fn root( &self ) -> &Digest
+    {
+
+        &self.hash
+        
+    }
+
+    // Returns the number of leaves in the tree
+    pub fn leaf_count( &self ) -> usize
+    {
+
+        self.leaf_count
+        
+    }
+
+    // Returns the positions of the proven leaves 
+    pub fn indices( &self ) -> &Vec<usize>
+    {
+
+        &self.indices
+        
+    }
+
+    // Returns the proven leaf values
+    pub fn values( &self ) -> &Vec<Transaction>
+    {
+
+        &self.values
+        
+    }
+
+    // Returns the stored sibling hashes
+    pub fn hashes( &self ) -> &Vec<Digest>
+    {
+
+        &self.hashes
+        
+    }
+    
+}
+
+/*
+ *
+ * Consistency proof:
+ *     - Proves that the tree of the first size leaves is a prefix of the tree of the second
+ *       size leaves ( RFC 6962, section 2.1.2 ), so a log that was only appended to can be
+ *       checked without downloading it. Only trees that promote unpaired nodes ( V2+ ) are
+ *       built as RFC 6962 trees, so only they have consistency proofs.
+ *
+ */
+#[derive( Debug, Clone, PartialEq, Serialize, Deserialize )]
+#[allow(dead_code)]
+pub struct ConsistencyProof
+{
+
+    // The format the trees were hashed with
+    version: TreeVersion,
+    // The hash algorithm the trees were hashed with
+    #[serde(default)]
+    algorithm: HashAlgorithm,
+    // The number of leaves in the earlier tree
+    first_size: usize,
+    // The number of leaves in the later tree
+    second_size: usize,
+    // The hashes of the subtrees needed to rebuild both roots
+    hashes: Vec<Digest>
+
+}
+
+// The impl for a consistency proof
+#[allow(dead_code)]
+impl ConsistencyProof
+{
+
+    // New consistency proof constructor
+    pub fn new( version: TreeVersion, algorithm: HashAlgorithm, first_size: usize, second_size: usize, hashes: Vec<Digest> ) -> Self
+    {
+
+        ConsistencyProof
+        {
+
+            version,
+            algorithm,
+            first_size,
+            second_size,
+            hashes
+                
+        }
+        
+    }
+
+    // Verifies that the tree with the first root is a prefix of the tree with the second root
+    pub fn verify( &self, first_root: &Digest, second_root: &Digest ) -> bool
+    {
+
+        verify_consistency( self.version, self.algorithm, self.first_size, self.second_size, &self.hashes, first_root, second_root )
+        
+    }
+
+    // Returns the format the trees were hashed with
+    pub fn version( &self ) -> TreeVersion
+    {
+
+        self.version
+        
+    }
+
+    // Returns the hash algorithm the trees were hashed with
+    pub fn algorithm( &self ) -> HashAlgorithm
+    {
+
+        self.algorithm
+        
+    }
+
+    // Returns the number of leaves in the earlier tree
+    pub fn first_size( &self ) -> usize
+    {
+
+        self.first_size
+        
+    }
+
+    // Returns the number of leaves in the later tree
+    pub fn second_size( &self ) -> usize
+    {
+
+        self.second_size
+        
+    }
+
+    // Returns the hashes of the proof
+    pub fn hashes( &self ) -> &Vec<Digest>
+    {
+
+        &self.hashes
+        
+    }
+    
+}
+
+// Verifies a consistency proof between two tree sizes and roots without needing either tree
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *
+ *                                                                                       *
+ * Follows RFC 9162, section 2.1.4.2:                                                    *
+ *                                                                                       *
+ * 1. Equal sizes need an empty proof and equal roots.                                   *
+ *                                                                                       *
+ * 2. If the first size is a power of 2, the first tree is a subtree of the second and   *
+ *    its root is the first hash of the path.                                            *
+ *                                                                                       *
+ * 3. Walk the last leaf index of each tree ( fn, sn ) up from the leaves, skipping the  *
+ *    levels where fn is a right child. Starting both roots ( fr, sr ) from the first    *
+ *    hash, each following hash is either a left sibling of both paths ( fused on the    *
+ *    left of fr and sr ) or a right sibling of the second path only ( fused on the      *
+ *    right of sr ).                                                                     *
+ *                                                                                       *
+ * 4. The proof holds if fr and sr are the two roots and the whole second path was used. *
+ *                                                                                       *
+ * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+pub fn verify_consistency( version: TreeVersion, algorithm: HashAlgorithm, first_size: usize, second_size: usize, hashes: &[Digest], first_root: &Digest, second_root: &Digest ) -> bool
+{
+
+    if !version.promotes_unpaired() || first_size == 0 || first_size > second_size
+    {
+
+        return false;
+        
+    }
+    if first_size == second_size
+    {
+
+        return hashes.is_empty() && first_root == second_root;
+        
+    }
+    // A power of 2 sized first tree is a complete subtree whose root isn't sent 
+    let mut path: Vec<&Digest> = hashes.iter().collect();
+    if first_size.is_power_of_two()
+    {
+
+        path.insert( 0, first_root );
+        
+    }
+    let mut first_node = first_size - 1;
+    let mut second_node = second_size - 1;
+    while first_node % 2 == 1
+    {
+
+        first_node >>= 1;
+        second_node >>= 1;
+        
+    }
+    let ( mut first_hash, mut second_hash ) = match path.first()
+    {
+
+        Some( hash ) => ( **hash, **hash ),
+        None => return false
+            
+    };
+    for hash in &path[ 1 .. ]
+    {
+
+        let hash = **hash;
+        if second_node == 0
+        {
+
+            return false;
+            
+        }
+        if first_node % 2 == 1 || first_node == second_node
+        {
+
+            first_hash = version.node_hash_with( algorithm, &hash, &first_hash );
+            second_hash = version.node_hash_with( algorithm, &hash, &second_hash );
+            while first_node.is_multiple_of( 2 ) && first_node != 0
+            {
+
+                first_node >>= 1;
+                second_node >>= 1;
+                
+            }
+            
+        }
+        else
+        {
+
+            second_hash = version.node_hash_with( algorithm, &second_hash, &hash );
             
         }
-        // Boolean check evaluating whether or not the two hashes are equal, if they are then
-        // the root hash was correctly built within the given value, otherwise the value
-        // wasn't used in building the root hash and therefore isn't in the tree. 
-        hash == *root
+        first_node >>= 1;
+        second_node >>= 1;
         
     }
+    first_hash == *first_root && second_hash == *second_root && second_node == 0
     
 }
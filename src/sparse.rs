@@ -0,0 +1,431 @@
+// Use statements
+//
+// Standard library
+#[allow(unused_imports)]
+use std::*;
+// Leaves ordered by key
+use std::collections::BTreeMap;
+// Non-empty nodes by position
+use std::collections::HashMap;
+// Used for creating hashes
+use hash_util::*;
+
+/*
+ *
+ * Sparse Merkle:
+ *     - This file contains a sparse Merkle Tree, a tree with a leaf for every possible 256
+ *       bit key. Almost every leaf is empty, so only the non-empty nodes are stored and the
+ *       hash of an empty subtree is taken from a table with one hash per level. Since every
+ *       key has a fixed place in the tree, a proof can show a key is absent ( its leaf is
+ *       empty ) as well as present.
+ *
+ *     - Keys are digests, key_of hashes a value ( a username, a post id ) into a key. The
+ *       bits of the key pick the path from the root, the first bit choosing the root's left
+ *       ( 0 ) or right ( 1 ) child.
+ *
+ */
+
+// The number of levels below the root, one per bit of a key
+pub const DEPTH: usize = 256;
+
+// Sparse Merkle Tree struct
+#[derive( Debug, Clone )]
+#[allow(dead_code)]
+pub struct SparseMerkle
+{
+
+    // The values of the non-empty leaves by key
+    leaves: BTreeMap<Digest, String>,
+    // The hashes of the non-empty nodes by level ( 0 for leaves ) and the key bits above them
+    nodes: HashMap<( usize, [ u8; 32 ] ), Digest>,
+    // The hash of an empty subtree on each level
+    empty: Vec<Digest>
+
+}
+
+// Default impl for the sparse Merkle Tree
+impl Default for SparseMerkle
+{
+
+    // An empty tree
+    fn default() -> Self
+    {
+
+        Self::new()
+
+    }
+
+}
+
+// Sparse Merkle Tree impl
+#[allow(dead_code)]
+impl SparseMerkle
+{
+
+    // New empty sparse Merkle Tree constructor
+    pub fn new() -> Self
+    {
+
+        SparseMerkle
+        {
+
+            leaves: BTreeMap::new(),
+            nodes: HashMap::new(),
+            empty: empty_hashes()
+
+        }
+
+    }
+
+    // Hashes a value into a key
+    pub fn key_of( value: &str ) -> Digest
+    {
+
+        create_leaf_hash( &value )
+
+    }
+
+    // Sets the value of a key
+    pub fn insert( &mut self, key: &Digest, value: String )
+    {
+
+        self.update( *key.as_bytes(), Some( leaf_hash( key.as_bytes(), &value ) ) );
+        self.leaves.insert( *key, value );
+
+    }
+
+    // Empties the leaf of a key, returns the value it had
+    pub fn remove( &mut self, key: &Digest ) -> Option<String>
+    {
+
+        self.update( *key.as_bytes(), None );
+        self.leaves.remove( key )
+
+    }
+
+    /* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *
+     *                                                                                       *
+     * Sets the leaf of a key to the given hash ( None for empty ) and rehashes its path up  *
+     * to the root, taking DEPTH hashes whatever the number of keys.                         *
+     *                                                                                       *
+     * A node is found by its level and the key with the bits below the node cleared. At    *
+     * each level the node's sibling is the node with the level's bit of the key flipped,   *
+     * which is the empty hash for the level if it isn't stored. Nodes hashing to the empty  *
+     * hash of their level are removed so only the non-empty nodes are stored.              *
+     *                                                                                       *
+     * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+    fn update( &mut self, key: [ u8; 32 ], leaf: Option<Digest> )
+    {
+
+        let mut hash = leaf.unwrap_or( self.empty[ 0 ] );
+        let mut position = key;
+        for level in 0 .. DEPTH + 1
+        {
+
+            // Stores the node, or removes it if it is empty
+            if hash == self.empty[ level ]
+            {
+
+                self.nodes.remove( &( level, position ) );
+
+            }
+            else
+            {
+
+                self.nodes.insert( ( level, position ), hash );
+
+            }
+            if level == DEPTH
+            {
+
+                break;
+
+            }
+            // Fuses the node with its sibling into its parent
+            let bit = DEPTH - 1 - level;
+            let sibling = self.node( level, flip_bit( &position, bit ) );
+            hash = if get_bit( &position, bit )
+            {
+
+                node_hash( &sibling, &hash )
+
+            }
+            else
+            {
+
+                node_hash( &hash, &sibling )
+
+            };
+            position = clear_bit( &position, bit );
+
+        }
+
+    }
+
+    // Returns the hash of the node at a level and position, the empty hash if it isn't stored
+    fn node( &self, level: usize, position: [ u8; 32 ] ) -> Digest
+    {
+
+        match self.nodes.get( &( level, position ) )
+        {
+
+            Some( hash ) => *hash,
+            None => self.empty[ level ]
+
+        }
+
+    }
+
+    // Returns the value of a key, None if its leaf is empty
+    pub fn get( &self, key: &Digest ) -> Option<&String>
+    {
+
+        self.leaves.get( key )
+
+    }
+
+    // Returns a proof that the key has its value, or that its leaf is empty if it has none
+    pub fn prove( &self, key: &Digest ) -> SparseProof
+    {
+
+        // The non-empty siblings from the leaf up
+        let mut siblings = Vec::new();
+        let mut position = *key.as_bytes();
+        for level in 0 .. DEPTH
+        {
+
+            let bit = DEPTH - 1 - level;
+            if let Some( sibling ) = self.nodes.get( &( level, flip_bit( &position, bit ) ) )
+            {
+
+                siblings.push( ( level, *sibling ) );
+
+            }
+            position = clear_bit( &position, bit );
+
+        }
+        SparseProof::new( *key, self.get( key ).cloned(), siblings )
+
+    }
+
+    // Returns the root hash of the tree
+    pub fn root_hash( &self ) -> &Digest
+    {
+
+        match self.nodes.get( &( DEPTH, [ 0; 32 ] ) )
+        {
+
+            Some( hash ) => hash,
+            None => &self.empty[ DEPTH ]
+
+        }
+
+    }
+
+    // Returns the number of non-empty leaves
+    pub fn len( &self ) -> usize
+    {
+
+        self.leaves.len()
+
+    }
+
+    // Determines whether every leaf is empty
+    pub fn is_empty( &self ) -> bool
+    {
+
+        self.leaves.is_empty()
+
+    }
+
+}
+
+/*
+ *
+ * Sparse Proof:
+ *     - The path from a key's leaf to the root. Most siblings on the path are empty
+ *       subtrees, so only the non-empty siblings are kept with their level and the verifier
+ *       fills the rest in from the empty hashes. A proof with a value shows the key has that
+ *       value ( inclusion ) and a proof without one shows the key's leaf is empty
+ *       ( non-inclusion ).
+ *
+ */
+#[derive( Debug, Clone, PartialEq, Serialize, Deserialize )]
+#[allow(dead_code)]
+pub struct SparseProof
+{
+
+    // The key the proof is for
+    key: Digest,
+    // The value of the key, None if its leaf is empty
+    value: Option<String>,
+    // The non-empty siblings on the path as ( level, hash ), from the leaf up
+    siblings: Vec<( usize, Digest )>
+
+}
+
+// Sparse proof impl
+#[allow(dead_code)]
+impl SparseProof
+{
+
+    // New sparse proof constructor
+    pub fn new( key: Digest, value: Option<String>, siblings: Vec<( usize, Digest )> ) -> Self
+    {
+
+        SparseProof
+        {
+
+            key,
+            value,
+            siblings
+
+        }
+
+    }
+
+    // Verifies that the key has the proof's value ( or an empty leaf ) in the tree with the
+    // given root
+    pub fn verify( &self, root: &Digest ) -> bool
+    {
+
+        let key = self.key.as_bytes();
+        let empty = empty_hashes();
+        let mut hash = match self.value
+        {
+
+            Some( ref value ) => leaf_hash( key, value ),
+            None => empty[ 0 ]
+
+        };
+        let mut siblings = self.siblings.iter().peekable();
+        for ( level, empty_sibling ) in empty.iter().enumerate().take( DEPTH )
+        {
+
+            // The stored sibling for this level, or the empty subtree
+            let sibling = match siblings.peek()
+            {
+
+                Some( &&( sibling_level, ref sibling ) ) if sibling_level == level =>
+                {
+
+                    siblings.next();
+                    sibling
+
+                },
+                _ => empty_sibling
+
+            };
+            hash = if get_bit( key, DEPTH - 1 - level )
+            {
+
+                node_hash( sibling, &hash )
+
+            }
+            else
+            {
+
+                node_hash( &hash, sibling )
+
+            };
+
+        }
+        // Every sibling has to have been used, in order
+        siblings.next().is_none() && hash == *root
+
+    }
+
+    // Whether the proof shows the key is present
+    pub fn is_inclusion( &self ) -> bool
+    {
+
+        self.value.is_some()
+
+    }
+
+    // Returns the key the proof is for
+    pub fn key( &self ) -> &Digest
+    {
+
+        &self.key
+
+    }
+
+    // Returns the value of the key, None if its leaf is empty
+    pub fn value( &self ) -> Option<&String>
+    {
+
+        self.value.as_ref()
+
+    }
+
+    // Returns the non-empty siblings of the proof
+    pub fn siblings( &self ) -> &Vec<( usize, Digest )>
+    {
+
+        &self.siblings
+
+    }
+
+}
+
+// The hash of an empty subtree on each level, an empty leaf hashes to the empty hash and an
+// empty node fuses two empty subtrees from the level below
+pub fn empty_hashes() -> Vec<Digest>
+{
+
+    let mut hashes = vec![ empty_hash() ];
+    for level in 0 .. DEPTH
+    {
+
+        let hash = node_hash( &hashes[ level ], &hashes[ level ] );
+        hashes.push( hash );
+
+    }
+    hashes
+
+}
+
+// Hashes a leaf, the leaf prefix followed by the key and the value
+fn leaf_hash( key: &[ u8; 32 ], value: &str ) -> Digest
+{
+
+    HashAlgorithm::default().digest( &[ &[ LEAF_PREFIX ], key, value.as_bytes() ] )
+
+}
+
+// Hashes a node from its children's hashes
+fn node_hash( left: &Digest, right: &Digest ) -> Digest
+{
+
+    create_prefixed_node_hash( left, right )
+
+}
+
+// Returns a bit of the key, bit 0 is the most significant bit of the first byte
+fn get_bit( key: &[ u8; 32 ], bit: usize ) -> bool
+{
+
+    key[ bit / 8 ] & ( 0x80 >> ( bit % 8 ) ) != 0
+
+}
+
+// Returns the key with a bit flipped
+fn flip_bit( key: &[ u8; 32 ], bit: usize ) -> [ u8; 32 ]
+{
+
+    let mut flipped = *key;
+    flipped[ bit / 8 ] ^= 0x80 >> ( bit % 8 );
+    flipped
+
+}
+
+// Returns the key with a bit cleared
+fn clear_bit( key: &[ u8; 32 ], bit: usize ) -> [ u8; 32 ]
+{
+
+    let mut cleared = *key;
+    cleared[ bit / 8 ] &= !( 0x80 >> ( bit % 8 ) );
+    cleared
+
+}
@@ -0,0 +1,548 @@
+// Crate inclusion
+//
+// Records are stored as json
+extern crate serde_json;
+// Embedded on disk database
+#[cfg(feature = "sled")]
+extern crate sled;
+
+// Use statements
+//
+// Standard library
+#[allow(unused_imports)]
+use std::*;
+// The in memory storage is kept in hash maps
+use std::collections::HashMap;
+// Used for putting back values replaced since the last flush
+use std::hash::Hash;
+// Blocks, transactions and Merkle Trees are what is stored
+use block::Block;
+use transaction::Transaction;
+use merkle::Merkle;
+// Hash utilities
+use hash_util::*;
+// Storage fails with the crate's error
+use error::Error;
+
+/*
+ *
+ * Storage:
+ *     - This file contains the trait chains are saved to and loaded from, along with its
+ *       backends. Blocks are stored by their hashes, transactions by their ids along with the
+ *       hash of the block they're in and Merkle Trees by their roots. The index entry of each
+ *       block ( what a chain keeps in memory about it ) is stored by the block's hash, so a
+ *       chain is opened without reading its blocks. Short named records hold everything else
+ *       ( the chain's settings and tail ).
+ *
+ *     - MemoryStorage keeps everything in hash maps, it is where chains are kept unless they're
+ *       moved into other storage. SledStorage keeps everything in an embedded sled database on disk and is built with
+ *       the sled feature ( cargo build --features sled ).
+ *
+ */
+pub trait Storage
+{
+
+    // Stores a block under its hash, replacing a block stored under the same hash
+    fn put_block( &mut self, block: &Block ) -> Result< (), Error >;
+
+    // Get the block stored under the given hash
+    fn get_block( &self, hash: &Digest ) -> Result< Option<Block>, Error >;
+
+    // Get the hashes of every stored block
+    fn block_hashes( &self ) -> Result< Vec<Digest>, Error >;
+
+    // Stores the index entry of a block under the block's hash
+    fn put_meta( &mut self, hash: &Digest, meta: &str ) -> Result< (), Error >;
+
+    // Get the index entry of every block along with the block's hash
+    fn metas( &self ) -> Result< Vec< ( Digest, String ) >, Error >;
+
+    // Stores a transaction under its id along with the hash of the block it is in
+    fn put_transaction( &mut self, id: &Digest, block: &Digest, transaction: &Transaction ) -> Result< (), Error >;
+
+    // Get the transaction stored under the given id and the hash of the block it is in
+    fn get_transaction( &self, id: &Digest ) -> Result< Option< ( Transaction, Digest ) >, Error >;
+
+    // Stores a Merkle Tree under its root
+    fn put_merkle( &mut self, merkle: &Merkle ) -> Result< (), Error >;
+
+    // Get the Merkle Tree stored under the given root
+    fn get_merkle( &self, root: &Digest ) -> Result< Option<Merkle>, Error >;
+
+    // Stores a named record
+    fn put_record( &mut self, name: &str, value: &str ) -> Result< (), Error >;
+
+    // Get the named record
+    fn get_record( &self, name: &str ) -> Result< Option<String>, Error >;
+
+    // Removes everything stored
+    fn clear( &mut self ) -> Result< (), Error >;
+
+    // Writes everything stored so far to the backend's medium
+    fn flush( &mut self ) -> Result< (), Error >;
+
+    // Drops everything stored since the last flush, putting back the values it replaced
+    fn rollback( &mut self ) -> Result< (), Error >;
+
+}
+
+// In memory storage struct
+#[derive( Debug, Clone, Default )]
+pub struct MemoryStorage
+{
+
+    // Blocks by hash
+    blocks: HashMap< Digest, Block >,
+    // Block index entries by the block's hash
+    metas: HashMap< Digest, String >,
+    // Transactions by id, with the hash of the block they're in
+    transactions: HashMap< Digest, ( Transaction, Digest ) >,
+    // Merkle Trees by root
+    merkles: HashMap< Digest, Merkle >,
+    // Named records
+    records: HashMap< String, String >,
+    // The values replaced since the last flush, in the order they were replaced
+    replaced: Vec<Replaced>
+
+}
+
+// A value replaced in memory storage, along with the value it replaced ( None if there was none )
+#[derive( Debug, Clone )]
+enum Replaced
+{
+
+    Block( Digest, Option<Block> ),
+    Meta( Digest, Option<String> ),
+    Transaction( Digest, Option< ( Transaction, Digest ) > ),
+    Merkle( Digest, Option<Merkle> ),
+    Record( String, Option<String> )
+
+}
+
+// Puts back the value a key held before it was replaced
+fn restore< K: Hash + Eq, V >( map: &mut HashMap< K, V >, key: K, previous: Option<V> )
+{
+
+    match previous
+    {
+
+        Some( value ) => map.insert( key, value ),
+        None => map.remove( &key )
+
+    };
+
+}
+
+// In memory storage impl
+#[allow(dead_code)]
+impl MemoryStorage
+{
+
+    // New empty storage constructor
+    pub fn new() -> MemoryStorage
+    {
+
+        MemoryStorage::default()
+
+    }
+
+}
+
+// Storage impl for in memory storage
+impl Storage for MemoryStorage
+{
+
+    fn put_block( &mut self, block: &Block ) -> Result< (), Error >
+    {
+
+        let previous = self.blocks.insert( *block.hash(), block.clone() );
+        self.replaced.push( Replaced::Block( *block.hash(), previous ) );
+        Ok( () )
+
+    }
+
+    fn get_block( &self, hash: &Digest ) -> Result< Option<Block>, Error >
+    {
+
+        Ok( self.blocks.get( hash ).cloned() )
+
+    }
+
+    fn block_hashes( &self ) -> Result< Vec<Digest>, Error >
+    {
+
+        Ok( self.blocks.keys().cloned().collect() )
+
+    }
+
+    fn put_meta( &mut self, hash: &Digest, meta: &str ) -> Result< (), Error >
+    {
+
+        let previous = self.metas.insert( *hash, meta.to_string() );
+        self.replaced.push( Replaced::Meta( *hash, previous ) );
+        Ok( () )
+
+    }
+
+    fn metas( &self ) -> Result< Vec< ( Digest, String ) >, Error >
+    {
+
+        Ok( self.metas.iter().map( | ( hash, meta ) | ( *hash, meta.clone() ) ).collect() )
+
+    }
+
+    fn put_transaction( &mut self, id: &Digest, block: &Digest, transaction: &Transaction ) -> Result< (), Error >
+    {
+
+        let previous = self.transactions.insert( *id, ( transaction.clone(), *block ) );
+        self.replaced.push( Replaced::Transaction( *id, previous ) );
+        Ok( () )
+
+    }
+
+    fn get_transaction( &self, id: &Digest ) -> Result< Option< ( Transaction, Digest ) >, Error >
+    {
+
+        Ok( self.transactions.get( id ).cloned() )
+
+    }
+
+    fn put_merkle( &mut self, merkle: &Merkle ) -> Result< (), Error >
+    {
+
+        let previous = self.merkles.insert( *merkle.root_hash(), merkle.clone() );
+        self.replaced.push( Replaced::Merkle( *merkle.root_hash(), previous ) );
+        Ok( () )
+
+    }
+
+    fn get_merkle( &self, root: &Digest ) -> Result< Option<Merkle>, Error >
+    {
+
+        Ok( self.merkles.get( root ).cloned() )
+
+    }
+
+    fn put_record( &mut self, name: &str, value: &str ) -> Result< (), Error >
+    {
+
+        let previous = self.records.insert( name.to_string(), value.to_string() );
+        self.replaced.push( Replaced::Record( name.to_string(), previous ) );
+        Ok( () )
+
+    }
+
+    fn get_record( &self, name: &str ) -> Result< Option<String>, Error >
+    {
+
+        Ok( self.records.get( name ).cloned() )
+
+    }
+
+    fn clear( &mut self ) -> Result< (), Error >
+    {
+
+        *self = MemoryStorage::new();
+        Ok( () )
+
+    }
+
+    fn flush( &mut self ) -> Result< (), Error >
+    {
+
+        self.replaced.clear();
+        Ok( () )
+
+    }
+
+    fn rollback( &mut self ) -> Result< (), Error >
+    {
+
+        while let Some( replaced ) = self.replaced.pop()
+        {
+
+            match replaced
+            {
+
+                Replaced::Block( hash, previous ) => restore( &mut self.blocks, hash, previous ),
+                Replaced::Meta( hash, previous ) => restore( &mut self.metas, hash, previous ),
+                Replaced::Transaction( id, previous ) => restore( &mut self.transactions, id, previous ),
+                Replaced::Merkle( root, previous ) => restore( &mut self.merkles, root, previous ),
+                Replaced::Record( name, previous ) => restore( &mut self.records, name, previous )
+
+            }
+
+        }
+        Ok( () )
+
+    }
+
+}
+
+// Sled errors are IO errors
+#[cfg(feature = "sled")]
+impl From<sled::Error> for Error
+{
+
+    fn from( error: sled::Error ) -> Error
+    {
+
+        Error::Io( io::Error::from( error ) )
+
+    }
+
+}
+
+// Sled storage struct, each kind of value is kept in its own tree of the database
+#[cfg(feature = "sled")]
+#[derive( Debug )]
+pub struct SledStorage
+{
+
+    // The database
+    db: sled::Db,
+    // Block json by hash
+    blocks: sled::Tree,
+    // Block index entries by the block's hash
+    metas: sled::Tree,
+    // Transaction and block hash json by transaction id
+    transactions: sled::Tree,
+    // Merkle Tree json by root
+    merkles: sled::Tree,
+    // Named records
+    records: sled::Tree,
+    // The values replaced since the last flush with the trees and keys they were replaced in, in
+    // the order they were replaced ( None if the key held nothing )
+    replaced: Vec< ( sled::Tree, sled::IVec, Option<sled::IVec> ) >
+
+}
+
+// Sled storage impl
+#[cfg(feature = "sled")]
+#[allow(dead_code)]
+impl SledStorage
+{
+
+    // Opens the database in the given directory, creating it if it doesn't exist
+    pub fn open( directory: &str ) -> Result< SledStorage, Error >
+    {
+
+        SledStorage::with_db( sled::open( directory )? )
+
+    }
+
+    // Opens a database in a temporary directory that is removed once the storage is dropped
+    pub fn temporary() -> Result< SledStorage, Error >
+    {
+
+        SledStorage::with_db( sled::Config::new().temporary( true ).open()? )
+
+    }
+
+    // Inserts a value into one of the trees, keeping the value it replaced so it can be put back
+    fn insert( &mut self, tree: sled::Tree, key: &[u8], value: &[u8] ) -> Result< (), Error >
+    {
+
+        let previous = tree.insert( key, value )?;
+        self.replaced.push( ( tree, sled::IVec::from( key ), previous ) );
+        Ok( () )
+
+    }
+
+    // Opens the trees of the database
+    fn with_db( db: sled::Db ) -> Result< SledStorage, Error >
+    {
+
+        Ok( SledStorage
+        {
+
+            blocks: db.open_tree( "blocks" )?,
+            metas: db.open_tree( "metas" )?,
+            transactions: db.open_tree( "transactions" )?,
+            merkles: db.open_tree( "merkles" )?,
+            records: db.open_tree( "records" )?,
+            replaced: Vec::new(),
+            db
+
+        } )
+
+    }
+
+}
+
+// Storage impl for sled storage
+#[cfg(feature = "sled")]
+impl Storage for SledStorage
+{
+
+    fn put_block( &mut self, block: &Block ) -> Result< (), Error >
+    {
+
+        self.insert( self.blocks.clone(), block.hash().as_bytes(), &serde_json::to_vec( block )? )
+
+    }
+
+    fn get_block( &self, hash: &Digest ) -> Result< Option<Block>, Error >
+    {
+
+        match self.blocks.get( hash.as_bytes() )?
+        {
+
+            Some( json ) => Ok( Some( serde_json::from_slice( &json )? ) ),
+            None => Ok( None )
+
+        }
+
+    }
+
+    fn block_hashes( &self ) -> Result< Vec<Digest>, Error >
+    {
+
+        let mut hashes = Vec::new();
+        for key in self.blocks.iter().keys()
+        {
+
+            let key = key?;
+            let hash = Digest::from_slice( &key ).ok_or_else( || Error::Integrity( format!( "a block is stored under a {} byte key", key.len() ) ) )?;
+            hashes.push( hash );
+
+        }
+        Ok( hashes )
+
+    }
+
+    fn put_meta( &mut self, hash: &Digest, meta: &str ) -> Result< (), Error >
+    {
+
+        self.insert( self.metas.clone(), hash.as_bytes(), meta.as_bytes() )
+
+    }
+
+    fn metas( &self ) -> Result< Vec< ( Digest, String ) >, Error >
+    {
+
+        let mut metas = Vec::new();
+        for entry in self.metas.iter()
+        {
+
+            let ( key, value ) = entry?;
+            let hash = Digest::from_slice( &key ).ok_or_else( || Error::Integrity( format!( "an index entry is stored under a {} byte key", key.len() ) ) )?;
+            let meta = String::from_utf8( value.to_vec() ).map_err( | _ | Error::Integrity( format!( "the index entry of block {} isn't utf-8", hash ) ) )?;
+            metas.push( ( hash, meta ) );
+
+        }
+        Ok( metas )
+
+    }
+
+    fn put_transaction( &mut self, id: &Digest, block: &Digest, transaction: &Transaction ) -> Result< (), Error >
+    {
+
+        self.insert( self.transactions.clone(), id.as_bytes(), &serde_json::to_vec( &( transaction, block ) )? )
+
+    }
+
+    fn get_transaction( &self, id: &Digest ) -> Result< Option< ( Transaction, Digest ) >, Error >
+    {
+
+        match self.transactions.get( id.as_bytes() )?
+        {
+
+            Some( json ) => Ok( Some( serde_json::from_slice( &json )? ) ),
+            None => Ok( None )
+
+        }
+
+    }
+
+    fn put_merkle( &mut self, merkle: &Merkle ) -> Result< (), Error >
+    {
+
+        self.insert( self.merkles.clone(), merkle.root_hash().as_bytes(), &serde_json::to_vec( merkle )? )
+
+    }
+
+    fn get_merkle( &self, root: &Digest ) -> Result< Option<Merkle>, Error >
+    {
+
+        let json = match self.merkles.get( root.as_bytes() )?
+        {
+
+            Some( json ) => json,
+            None => return Ok( None )
+
+        };
+        let merkle: Merkle = serde_json::from_slice( &json )?;
+        if merkle.root_hash() != root
+        {
+
+            return Err( Error::Integrity( format!( "the Merkle Tree stored under {} has the root {}", root, merkle.root_hash() ) ) );
+
+        }
+        Ok( Some( merkle ) )
+
+    }
+
+    fn put_record( &mut self, name: &str, value: &str ) -> Result< (), Error >
+    {
+
+        self.insert( self.records.clone(), name.as_bytes(), value.as_bytes() )
+
+    }
+
+    fn get_record( &self, name: &str ) -> Result< Option<String>, Error >
+    {
+
+        match self.records.get( name.as_bytes() )?
+        {
+
+            Some( value ) => String::from_utf8( value.to_vec() ).map( Some ).map_err( | _ | Error::Integrity( format!( "record {} isn't utf-8", name ) ) ),
+            None => Ok( None )
+
+        }
+
+    }
+
+    fn clear( &mut self ) -> Result< (), Error >
+    {
+
+        for tree in &[ &self.blocks, &self.metas, &self.transactions, &self.merkles, &self.records ]
+        {
+
+            tree.clear()?;
+
+        }
+        self.replaced.clear();
+        Ok( () )
+
+    }
+
+    fn flush( &mut self ) -> Result< (), Error >
+    {
+
+        self.db.flush()?;
+        self.replaced.clear();
+        Ok( () )
+
+    }
+
+    fn rollback( &mut self ) -> Result< (), Error >
+    {
+
+        while let Some( ( tree, key, previous ) ) = self.replaced.pop()
+        {
+
+            match previous
+            {
+
+                Some( value ) => tree.insert( key, value )?,
+                None => tree.remove( key )?
+
+            };
+
+        }
+        Ok( () )
+
+    }
+
+}
@@ -0,0 +1,748 @@
+// Crate inclusion
+//
+// Blocks are stored as json
+extern crate serde_json;
+
+// Use statements
+//
+// Standard library
+#[allow(unused_imports)]
+use std::*;
+// The locations of the stored values are kept in a hash map
+use std::collections::HashMap;
+// Segments and the index are plain files in the store's directory
+use std::fs::{ File, OpenOptions };
+// Prelude
+use std::io::prelude::*;
+// Used for reading the index in and reading entries and records at their offsets
+use std::io::{ BufReader, SeekFrom };
+// Used for finding the files of the store
+use std::path::{ Path, PathBuf };
+// Record lengths are checked to fit their 4 byte field
+use std::convert::TryFrom;
+// Blocks, transactions and Merkle Trees are what is stored
+use block::Block;
+use transaction::Transaction;
+use merkle::Merkle;
+// Hash utilities
+use hash_util::*;
+// The number of bytes in a hash
+use digest::DIGEST_LENGTH;
+// Chains are kept in the store through the storage trait
+use storage::Storage;
+// Storing values fails with the crate's error
+use error::Error;
+// Used for syncing the store's directory
+use file::sync_directory;
+
+/*
+ *
+ * Store:
+ *     - This file contains an append only, on disk storage backend. Chains are kept in it like
+ *       any other storage ( Chain::open and Chain::into_storage ). Values are appended to a log
+ *       split into segment files ( a new segment is started once the current one would grow
+ *       past the segment size ) and an index file records where each value was put, a value
+ *       put again under the same key replaces the one put before it.
+ *
+ *     - Segment records are laid out as:
+ *
+ *           length ( 4 bytes, big endian ) | checksum ( 32 bytes ) | json ( length bytes )
+ *
+ *       where the checksum is the SHA3-256 hash of the json. Index entries are 49 bytes:
+ *
+ *           kind ( 1 byte ) | key ( 32 bytes ) | segment ( 4 bytes ) | offset ( 8 bytes ) | length ( 4 bytes )
+ *
+ *       where the kind says whether the record is a block ( keyed by hash ), a block's index
+ *       entry ( keyed by the block's hash ), a transaction ( keyed by id ), a Merkle Tree
+ *       ( keyed by root ) or a named record ( keyed by the hash of its name ).
+ *
+ *     - Flushing the store syncs the segments, then writes a commit entry marking the end of
+ *       the log and syncs the index. Opening a store drops everything written after the last
+ *       commit entry, so the values put between two flushes ( a block and everything stored
+ *       with it ) are kept whole or not at all, and rolling the store back cuts the index and
+ *       segments back to the last commit. Only the index is read in when a store is opened,
+ *       values are read from disk as they're asked for.
+ *
+ */
+
+// The size a segment may grow to before the next one is started, in bytes
+pub const SEGMENT_SIZE: u64 = 64 * 1024 * 1024;
+// The number of bytes in an index entry
+const ENTRY_LENGTH: u64 = 1 + DIGEST_LENGTH as u64 + 4 + 8 + 4;
+// The number of bytes before a record's json
+const RECORD_HEADER_LENGTH: u64 = 4 + DIGEST_LENGTH as u64;
+// The name of the index file
+const INDEX_FILE: &str = "index";
+
+// The name of the segment file with the given number
+fn segment_name( segment: u32 ) -> String
+{
+
+    format!( "segment-{:08}.log", segment )
+
+}
+
+// The checksum of a record's json
+fn checksum( json: &[u8] ) -> Digest
+{
+
+    HashAlgorithm::Sha3_256.digest( &[ json ] )
+
+}
+
+// The key a named record is stored under
+fn record_key( name: &str ) -> Digest
+{
+
+    HashAlgorithm::Sha3_256.digest( &[ name.as_bytes() ] )
+
+}
+
+// The kinds of index entry
+#[derive( Debug, Clone, Copy, PartialEq, Eq, Hash )]
+enum Kind
+{
+
+    // Marks the end of the log at a flush, it has no record
+    Commit,
+    // A block keyed by its hash
+    Block,
+    // A transaction and the hash of the block it's in, keyed by the transaction's id
+    Transaction,
+    // A Merkle Tree keyed by its root
+    Merkle,
+    // A named record keyed by the hash of its name
+    Record,
+    // A block's index entry keyed by the block's hash
+    Meta
+
+}
+
+// Kind impl
+impl Kind
+{
+
+    // The byte the kind is written to the index as
+    fn byte( self ) -> u8
+    {
+
+        match self
+        {
+
+            Kind::Commit => 0,
+            Kind::Block => 1,
+            Kind::Transaction => 2,
+            Kind::Merkle => 3,
+            Kind::Record => 4,
+            Kind::Meta => 5
+
+        }
+
+    }
+
+    // The kind written to the index as the given byte
+    fn from_byte( byte: u8 ) -> Option<Kind>
+    {
+
+        match byte
+        {
+
+            0 => Some( Kind::Commit ),
+            1 => Some( Kind::Block ),
+            2 => Some( Kind::Transaction ),
+            3 => Some( Kind::Merkle ),
+            4 => Some( Kind::Record ),
+            5 => Some( Kind::Meta ),
+            _ => None
+
+        }
+
+    }
+
+}
+
+// Where a value was stored
+#[derive( Debug, Clone, Copy, PartialEq )]
+struct Entry
+{
+
+    // The kind of value
+    kind: Kind,
+    // The key the value was stored under
+    key: Digest,
+    // The segment the value's record is in
+    segment: u32,
+    // The offset of the record in its segment, for a commit the length of its segment
+    offset: u64,
+    // The length of the value's json
+    length: u32
+
+}
+
+// Entry impl
+impl Entry
+{
+
+    // Encodes the entry as it is written to the index
+    fn encode( &self ) -> Vec<u8>
+    {
+
+        let mut bytes = Vec::with_capacity( ENTRY_LENGTH as usize );
+        bytes.push( self.kind.byte() );
+        bytes.extend_from_slice( self.key.as_bytes() );
+        bytes.extend_from_slice( &self.segment.to_be_bytes() );
+        bytes.extend_from_slice( &self.offset.to_be_bytes() );
+        bytes.extend_from_slice( &self.length.to_be_bytes() );
+        bytes
+
+    }
+
+    // Decodes an entry read from the index, fails with an integrity error if its kind isn't
+    // known
+    fn decode( bytes: &[ u8; ENTRY_LENGTH as usize ] ) -> Result< Entry, Error >
+    {
+
+        let kind = Kind::from_byte( bytes[ 0 ] ).ok_or_else( || Error::Integrity( format!( "an index entry has the unknown kind {}", bytes[ 0 ] ) ) )?;
+        let bytes = &bytes[ 1 .. ];
+        let mut segment = [ 0; 4 ];
+        let mut offset = [ 0; 8 ];
+        let mut length = [ 0; 4 ];
+        segment.copy_from_slice( &bytes[ DIGEST_LENGTH .. DIGEST_LENGTH + 4 ] );
+        offset.copy_from_slice( &bytes[ DIGEST_LENGTH + 4 .. DIGEST_LENGTH + 12 ] );
+        length.copy_from_slice( &bytes[ DIGEST_LENGTH + 12 .. ] );
+        Ok( Entry
+        {
+
+            kind,
+            key: Digest::from_slice( &bytes[ .. DIGEST_LENGTH ] ).unwrap_or_default(),
+            segment: u32::from_be_bytes( segment ),
+            offset: u64::from_be_bytes( offset ),
+            length: u32::from_be_bytes( length )
+
+        } )
+
+    }
+
+}
+
+// The store struct
+#[derive( Debug )]
+pub struct Store
+{
+
+    // The directory the index and segments are kept in
+    directory: PathBuf,
+    // The size a segment may grow to before the next one is started
+    segment_size: u64,
+    // The index file
+    index: File,
+    // The number of the segment being appended to
+    segment: u32,
+    // The segment being appended to
+    writer: File,
+    // The length of the segment being appended to
+    end: u64,
+    // The number of entries in the index
+    len: u64,
+    // The latest entry of every stored value by kind and key
+    entries: HashMap< ( Kind, Digest ), Entry >,
+    // The segment, its length and the number of entries in the index at the last commit
+    committed: ( u32, u64, u64 ),
+    // The entries replaced since the last commit along with the entries they replaced ( None if
+    // there were none ), in the order they were replaced
+    replaced: Vec< ( ( Kind, Digest ), Option<Entry> ) >,
+    // Whether entries have been appended since the last commit
+    uncommitted: bool,
+    // Whether files have been created or removed in the directory since the last commit
+    created: bool
+
+}
+
+// Store impl
+#[allow(dead_code)]
+impl Store
+{
+
+    // Opens the store in the given directory with the default segment size, the directory is
+    // created if it doesn't exist
+    pub fn open( directory: &str ) -> Result< Store, Error >
+    {
+
+        Store::with_segment_size( directory, SEGMENT_SIZE )
+
+    }
+
+    // Opens the store in the given directory, starting a new segment once the current one
+    // would grow past the given size. Anything written after the last commit is dropped
+    pub fn with_segment_size( directory: &str, segment_size: u64 ) -> Result< Store, Error >
+    {
+
+        fs::create_dir_all( directory )?;
+        let directory = PathBuf::from( directory );
+        let index = OpenOptions::new( ).read( true ).append( true ).create( true ).open( directory.join( INDEX_FILE ) )?;
+        // Drops a partly written entry, then the entries written after the last commit
+        let mut len = index.metadata()?.len() / ENTRY_LENGTH;
+        let mut last = None;
+        while len > 0
+        {
+
+            match read_entry( &index, len - 1 )
+            {
+
+                Ok( entry ) if entry.kind == Kind::Commit =>
+                {
+
+                    last = Some( entry );
+                    break;
+
+                },
+                Ok( _ ) | Err( Error::Integrity( _ ) ) => len -= 1,
+                Err( error ) => return Err( error )
+
+            }
+
+        }
+        index.set_len( len * ENTRY_LENGTH )?;
+        // Drops anything written to the segments after the last commit, including segments
+        // started after it
+        let ( segment, end ) = last.map( | entry | ( entry.segment, entry.offset ) ).unwrap_or( ( 0, 0 ) );
+        let writer = OpenOptions::new( ).append( true ).create( true ).open( directory.join( segment_name( segment ) ) )?;
+        if writer.metadata()?.len() < end
+        {
+
+            return Err( Error::Integrity( format!( "segment {} is shorter than it was when last committed", segment ) ) );
+
+        }
+        writer.set_len( end )?;
+        let mut next = segment + 1;
+        while directory.join( segment_name( next ) ).exists()
+        {
+
+            fs::remove_file( directory.join( segment_name( next ) ) )?;
+            next += 1;
+
+        }
+        index.sync_all()?;
+        writer.sync_all()?;
+        sync_directory( &directory )?;
+        // Reads the index in, later entries replace earlier ones under the same key
+        let mut entries = HashMap::new();
+        let mut reader = BufReader::new( &index );
+        reader.seek( SeekFrom::Start( 0 ) )?;
+        for _ in 0 .. len
+        {
+
+            let mut bytes = [ 0; ENTRY_LENGTH as usize ];
+            reader.read_exact( &mut bytes )?;
+            let entry = Entry::decode( &bytes )?;
+            if entry.kind != Kind::Commit
+            {
+
+                entries.insert( ( entry.kind, entry.key ), entry );
+
+            }
+
+        }
+        Ok( Store
+        {
+
+            directory,
+            segment_size,
+            index,
+            segment,
+            writer,
+            end,
+            len,
+            entries,
+            committed: ( segment, end, len ),
+            replaced: Vec::new(),
+            uncommitted: false,
+            created: false
+
+        } )
+
+    }
+
+    // Get the number of entries in the index, including commits and values that have been
+    // replaced
+    pub fn len( &self ) -> u64
+    {
+
+        self.len
+
+    }
+
+    // Get whether or not anything is stored
+    pub fn is_empty( &self ) -> bool
+    {
+
+        self.len == 0
+
+    }
+
+    // Appends a value's json to the store under its kind and key, the value isn't durable
+    // until the store is flushed. A failed append leaves the store as it was
+    fn append( &mut self, kind: Kind, key: Digest, json: &[u8] ) -> Result< (), Error >
+    {
+
+        let length = u32::try_from( json.len() ).map_err( | _ | Error::Integrity( format!( "a value of {} bytes is too large to store", json.len() ) ) )?;
+        let record_length = RECORD_HEADER_LENGTH + json.len() as u64;
+        // Starts a new segment once the current one would grow past the segment size, the
+        // current segment is synced first as a commit only syncs the newest one
+        if self.end > 0 && self.end + record_length > self.segment_size
+        {
+
+            self.writer.sync_data()?;
+            let writer = OpenOptions::new( ).append( true ).create( true ).open( self.directory.join( segment_name( self.segment + 1 ) ) )?;
+            self.segment += 1;
+            self.writer = writer;
+            self.end = 0;
+            self.created = true;
+
+        }
+        // Writes the record, then the entry pointing at it
+        let mut record = Vec::with_capacity( record_length as usize );
+        record.extend_from_slice( &length.to_be_bytes() );
+        record.extend_from_slice( checksum( json ).as_bytes() );
+        record.extend_from_slice( json );
+        let entry = Entry{ kind, key, segment: self.segment, offset: self.end, length };
+        if let Err( error ) = self.writer.write_all( &record )
+        {
+
+            // Cuts off whatever part of the record was written, so the next append starts
+            // where this one did
+            self.writer.set_len( self.end )?;
+            return Err( Error::Io( error ) );
+
+        }
+        self.end += record_length;
+        self.write_entry( &entry )?;
+        let previous = self.entries.insert( ( kind, key ), entry );
+        self.replaced.push( ( ( kind, key ), previous ) );
+        Ok( () )
+
+    }
+
+    // Writes an entry to the index, whatever part of it was written is cut off if the write
+    // fails
+    fn write_entry( &mut self, entry: &Entry ) -> Result< (), Error >
+    {
+
+        if let Err( error ) = self.index.write_all( &entry.encode() )
+        {
+
+            self.index.set_len( self.len * ENTRY_LENGTH )?;
+            return Err( Error::Io( error ) );
+
+        }
+        self.len += 1;
+        self.uncommitted = true;
+        Ok( () )
+
+    }
+
+    // Reads the json of the latest value stored under the kind and key
+    fn read( &self, kind: Kind, key: &Digest ) -> Result< Option< Vec<u8> >, Error >
+    {
+
+        match self.entries.get( &( kind, *key ) )
+        {
+
+            Some( entry ) => Ok( Some( read_record( &self.directory, entry )? ) ),
+            None => Ok( None )
+
+        }
+
+    }
+
+}
+
+// Reads the entry at the given position from the index
+fn read_entry( index: &File, position: u64 ) -> Result< Entry, Error >
+{
+
+    let mut file = index;
+    let mut bytes = [ 0; ENTRY_LENGTH as usize ];
+    file.seek( SeekFrom::Start( position * ENTRY_LENGTH ) )?;
+    file.read_exact( &mut bytes )?;
+    Entry::decode( &bytes )
+
+}
+
+// Reads the json of an entry's record from its segment, checking the record's length and
+// checksum
+fn read_record( directory: &Path, entry: &Entry ) -> Result< Vec<u8>, Error >
+{
+
+    read_record_from( &mut File::open( directory.join( segment_name( entry.segment ) ) )?, entry )
+
+}
+
+// Reads the json of an entry's record from its open segment like read_record
+fn read_record_from( file: &mut File, entry: &Entry ) -> Result< Vec<u8>, Error >
+{
+
+    file.seek( SeekFrom::Start( entry.offset ) )?;
+    let mut header = [ 0; RECORD_HEADER_LENGTH as usize ];
+    file.read_exact( &mut header )?;
+    let mut length = [ 0; 4 ];
+    length.copy_from_slice( &header[ .. 4 ] );
+    if u32::from_be_bytes( length ) != entry.length
+    {
+
+        return Err( Error::Integrity( format!( "the record stored under {} doesn't have the length it was stored with", entry.key ) ) );
+
+    }
+    let mut json = vec![ 0; entry.length as usize ];
+    file.read_exact( &mut json )?;
+    if checksum( &json ).as_bytes()[ .. ] != header[ 4 .. ]
+    {
+
+        return Err( Error::Integrity( format!( "the record stored under {} doesn't match its checksum", entry.key ) ) );
+
+    }
+    Ok( json )
+
+}
+
+// Storage impl for the store
+impl Storage for Store
+{
+
+    fn put_block( &mut self, block: &Block ) -> Result< (), Error >
+    {
+
+        self.append( Kind::Block, *block.hash(), &serde_json::to_vec( block )? )
+
+    }
+
+    fn get_block( &self, hash: &Digest ) -> Result< Option<Block>, Error >
+    {
+
+        let json = match self.read( Kind::Block, hash )?
+        {
+
+            Some( json ) => json,
+            None => return Ok( None )
+
+        };
+        let block: Block = serde_json::from_slice( &json )?;
+        if block.hash() != hash
+        {
+
+            return Err( Error::Integrity( format!( "block {} was stored as {}", block.hash(), hash ) ) );
+
+        }
+        Ok( Some( block ) )
+
+    }
+
+    fn block_hashes( &self ) -> Result< Vec<Digest>, Error >
+    {
+
+        Ok( self.entries.keys().filter( | &&( kind, _ ) | kind == Kind::Block ).map( | &( _, hash ) | hash ).collect() )
+
+    }
+
+    fn put_meta( &mut self, hash: &Digest, meta: &str ) -> Result< (), Error >
+    {
+
+        self.append( Kind::Meta, *hash, meta.as_bytes() )
+
+    }
+
+    // The entries are read in the order they were appended, through one open file per segment
+    fn metas( &self ) -> Result< Vec< ( Digest, String ) >, Error >
+    {
+
+        let mut entries: Vec<&Entry> = self.entries.values().filter( | entry | entry.kind == Kind::Meta ).collect();
+        entries.sort_by_key( | entry | ( entry.segment, entry.offset ) );
+        let mut metas = Vec::with_capacity( entries.len() );
+        let mut open: Option< ( u32, File ) > = None;
+        for entry in entries
+        {
+
+            let file = match open
+            {
+
+                Some( ( segment, ref mut file ) ) if segment == entry.segment => file,
+                _ => &mut open.insert( ( entry.segment, File::open( self.directory.join( segment_name( entry.segment ) ) )? ) ).1
+
+            };
+            let meta = String::from_utf8( read_record_from( file, entry )? ).map_err( | _ | Error::Integrity( format!( "the index entry of block {} isn't utf-8", entry.key ) ) )?;
+            metas.push( ( entry.key, meta ) );
+
+        }
+        Ok( metas )
+
+    }
+
+    fn put_transaction( &mut self, id: &Digest, block: &Digest, transaction: &Transaction ) -> Result< (), Error >
+    {
+
+        self.append( Kind::Transaction, *id, &serde_json::to_vec( &( transaction, block ) )? )
+
+    }
+
+    fn get_transaction( &self, id: &Digest ) -> Result< Option< ( Transaction, Digest ) >, Error >
+    {
+
+        match self.read( Kind::Transaction, id )?
+        {
+
+            Some( json ) => Ok( Some( serde_json::from_slice( &json )? ) ),
+            None => Ok( None )
+
+        }
+
+    }
+
+    fn put_merkle( &mut self, merkle: &Merkle ) -> Result< (), Error >
+    {
+
+        self.append( Kind::Merkle, *merkle.root_hash(), &serde_json::to_vec( merkle )? )
+
+    }
+
+    fn get_merkle( &self, root: &Digest ) -> Result< Option<Merkle>, Error >
+    {
+
+        let json = match self.read( Kind::Merkle, root )?
+        {
+
+            Some( json ) => json,
+            None => return Ok( None )
+
+        };
+        let merkle: Merkle = serde_json::from_slice( &json )?;
+        if merkle.root_hash() != root
+        {
+
+            return Err( Error::Integrity( format!( "the Merkle Tree stored under {} has the root {}", root, merkle.root_hash() ) ) );
+
+        }
+        Ok( Some( merkle ) )
+
+    }
+
+    fn put_record( &mut self, name: &str, value: &str ) -> Result< (), Error >
+    {
+
+        self.append( Kind::Record, record_key( name ), value.as_bytes() )
+
+    }
+
+    fn get_record( &self, name: &str ) -> Result< Option<String>, Error >
+    {
+
+        match self.read( Kind::Record, &record_key( name ) )?
+        {
+
+            Some( value ) => String::from_utf8( value ).map( Some ).map_err( | _ | Error::Integrity( format!( "record {} isn't utf-8", name ) ) ),
+            None => Ok( None )
+
+        }
+
+    }
+
+    fn clear( &mut self ) -> Result< (), Error >
+    {
+
+        // Empties the index and syncs it before the segments are touched, so a store cut off
+        // part way through opens empty rather than with entries pointing past its segments
+        self.index.set_len( 0 )?;
+        self.index.sync_all()?;
+        sync_directory( &self.directory )?;
+        self.len = 0;
+        self.entries.clear();
+        self.committed = ( 0, 0, 0 );
+        self.replaced.clear();
+        self.uncommitted = true;
+        // Then empties the first segment and removes the others
+        self.writer = OpenOptions::new( ).append( true ).create( true ).open( self.directory.join( segment_name( 0 ) ) )?;
+        self.writer.set_len( 0 )?;
+        for segment in 1 .. self.segment + 1
+        {
+
+            fs::remove_file( self.directory.join( segment_name( segment ) ) )?;
+
+        }
+        self.segment = 0;
+        self.end = 0;
+        self.writer.sync_all()?;
+        sync_directory( &self.directory )?;
+        self.created = false;
+        Ok( () )
+
+    }
+
+    fn flush( &mut self ) -> Result< (), Error >
+    {
+
+        // The records are synced before the commit entry is written, so a commit entry on
+        // disk means every record before it is whole
+        self.writer.sync_data()?;
+        if self.uncommitted
+        {
+
+            let commit = Entry{ kind: Kind::Commit, key: Digest::default(), segment: self.segment, offset: self.end, length: 0 };
+            self.write_entry( &commit )?;
+            self.uncommitted = false;
+
+        }
+        self.index.sync_data()?;
+        if self.created
+        {
+
+            sync_directory( &self.directory )?;
+            self.created = false;
+
+        }
+        self.committed = ( self.segment, self.end, self.len );
+        self.replaced.clear();
+        Ok( () )
+
+    }
+
+    fn rollback( &mut self ) -> Result< (), Error >
+    {
+
+        // Cuts the index back first, so the records after the commit are never pointed at
+        let ( segment, end, len ) = self.committed;
+        self.index.set_len( len * ENTRY_LENGTH )?;
+        self.len = len;
+        self.uncommitted = false;
+        while let Some( ( key, previous ) ) = self.replaced.pop()
+        {
+
+            match previous
+            {
+
+                Some( entry ) => self.entries.insert( key, entry ),
+                None => self.entries.remove( &key )
+
+            };
+
+        }
+        // Then cuts the segments back, removing those started since the commit
+        if segment != self.segment
+        {
+
+            self.writer = OpenOptions::new( ).append( true ).create( true ).open( self.directory.join( segment_name( segment ) ) )?;
+            for started in segment + 1 .. self.segment + 1
+            {
+
+                fs::remove_file( self.directory.join( segment_name( started ) ) )?;
+
+            }
+            self.segment = segment;
+            self.created = true;
+
+        }
+        self.writer.set_len( end )?;
+        self.end = end;
+        Ok( () )
+
+    }
+
+}
@@ -0,0 +1,84 @@
+// Crate inclusion
+//
+// Chrono used for timestamping
+extern crate chrono;
+// Serde used for serialization
+extern crate serde;
+
+// Use statements
+//
+// Standard library
+#[allow(unused_imports)]
+use std::*;
+// Timestamps are UTC date times
+use self::chrono::{ DateTime, NaiveDateTime, TimeZone, Utc };
+// Timestamps serialize as strings
+use self::serde::{ Serializer, Deserialize, Deserializer };
+use self::serde::de;
+
+/*
+ *
+ * Timestamp:
+ *     - This file contains the helpers for the times blocks and transactions were created at.
+ *       Times are held as UTC date times so they can be sorted, compared and validated.
+ *
+ *     - Times are written as the display format of a UTC date time
+ *       ( "2017-12-16 01:55:49.707511 UTC" ), the form they were kept in when they were
+ *       strings, so existing json files read in unchanged. RFC 3339 times are read in as well.
+ *       Used with #[serde(with = "::timestamp")].
+ *
+ */
+
+// The format timestamps are displayed and written in
+pub const FORMAT: &str = "%Y-%m-%d %H:%M:%S%.f UTC";
+
+// Returns the current time
+#[allow(dead_code)]
+pub fn now() -> DateTime<Utc>
+{
+
+    Utc::now()
+
+}
+
+// Returns the time the given number of milliseconds after the Unix epoch, None if it is out of
+// range
+#[allow(dead_code)]
+pub fn from_millis( millis: i64 ) -> Option< DateTime<Utc> >
+{
+
+    DateTime::from_timestamp_millis( millis )
+
+}
+
+// Parses a time in the display format or RFC 3339, None if it is in neither
+#[allow(dead_code)]
+pub fn parse( value: &str ) -> Option< DateTime<Utc> >
+{
+
+    match NaiveDateTime::parse_from_str( value, FORMAT )
+    {
+
+        Ok( naive ) => Some( Utc.from_utc_datetime( &naive ) ),
+        Err( _ ) => DateTime::parse_from_rfc3339( value ).ok().map( | time | time.with_timezone( &Utc ) )
+
+    }
+
+}
+
+// Serializes a time in the display format
+pub fn serialize< S: Serializer >( time: &DateTime<Utc>, serializer: S ) -> Result< S::Ok, S::Error >
+{
+
+    serializer.collect_str( time )
+
+}
+
+// Deserializes a time from the display format or RFC 3339
+pub fn deserialize< 'de, D: Deserializer<'de> >( deserializer: D ) -> Result< DateTime<Utc>, D::Error >
+{
+
+    let value = String::deserialize( deserializer )?;
+    parse( &value ).ok_or_else( || de::Error::custom( format!( "{:?} is not a UTC timestamp", value ) ) )
+
+}
@@ -6,8 +6,10 @@ extern crate serde_json;
 
 // Use statements
 //
-// Standard error 
-use std::io::Error;
+// Reading and writing transactions fails with the crate's error
+use error::Error;
+// Files are written atomically
+use file;
 // String functionality
 #[allow(unused_imports)]
 use std::string::{ String, ToString };
@@ -15,6 +17,12 @@ use std::string::{ String, ToString };
 #[allow(unused_imports)]
 use std::fs::{ File, OpenOptions };
 use std::io::prelude::*;
+// Hashing the canonical encoding
+use hash_util::{ create_transaction_id, Digest, HashAlgorithm };
+// Transactions are timestamped with UTC date times
+use chrono::{ DateTime, Utc };
+// Used for parsing timestamps
+use timestamp;
 
 /*
  *
@@ -23,8 +31,9 @@ use std::io::prelude::*;
  *
  */
 
-// Transaction struct 
+// Transaction struct, read and written through its record so legacy timestamps survive
 #[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "TransactionRecord", into = "TransactionRecord")]
 pub struct Transaction
 {
 
@@ -34,35 +43,141 @@ pub struct Transaction
     username: String,
     // content
     content: String,
-    // timestamp
-    timestamp: String,
+    // The time the transaction was created, written in the display format of a UTC date time
+    timestamp: DateTime<Utc>,
+    // The original timestamp of a transaction written before timestamps were times
+    // ( "now", "all day every day", or a time in another format such as RFC 3339 ), None for
+    // every other transaction
+    legacy_timestamp: Option<String>,
     // verification status
     status: String
         
 }
 
+/*
+ *
+ * Transaction record:
+ *     - A transaction as it is written to json, with its timestamp as a string.
+ *
+ *     - Transactions written before timestamps were times hold free form strings that don't
+ *       parse as a time. Those are read in with the Unix epoch as their timestamp and the
+ *       original string kept as the legacy timestamp, which is what is written back out and
+ *       encoded, so reading and writing a legacy transaction leaves it ( and its id ) unchanged.
+ *       A time written in another format than the display format ( RFC 3339 ) is read in as
+ *       that time, with its original string kept the same way.
+ *
+ */
+#[derive(Serialize, Deserialize)]
+struct TransactionRecord
+{
+
+    uid: u64,
+    username: String,
+    content: String,
+    timestamp: String,
+    status: String
+
+}
+
+// Reads a transaction from its record, keeping a timestamp that doesn't parse as a legacy one
+impl From<TransactionRecord> for Transaction
+{
+
+    fn from( record: TransactionRecord ) -> Transaction
+    {
+
+        let ( timestamp, legacy_timestamp ) = read_timestamp( record.timestamp );
+        Transaction
+        {
+
+            uid: record.uid,
+            username: record.username,
+            content: record.content,
+            timestamp,
+            legacy_timestamp,
+            status: record.status
+
+        }
+
+    }
+
+}
+
+// Writes a transaction as its record
+impl From<Transaction> for TransactionRecord
+{
+
+    fn from( transaction: Transaction ) -> TransactionRecord
+    {
+
+        TransactionRecord
+        {
+
+            timestamp: transaction.written_timestamp(),
+            uid: transaction.uid,
+            username: transaction.username,
+            content: transaction.content,
+            status: transaction.status
+
+        }
+
+    }
+
+}
+
+// Parses a written timestamp, a string that isn't a time is a legacy timestamp read in as the
+// Unix epoch. A time that wouldn't be written back the same is kept as a legacy timestamp too
+fn read_timestamp( value: String ) -> ( DateTime<Utc>, Option<String> )
+{
+
+    match timestamp::parse( &value )
+    {
+
+        Some( time ) if time.to_string() == value => ( time, None ),
+        Some( time ) => ( time, Some( value ) ),
+        None => ( DateTime::UNIX_EPOCH, Some( value ) )
+
+    }
+
+}
+
 // Functions for transaction
 impl Transaction
 {
 
     // Constructor for a new transaction 
-    pub fn new( uid: u64, username: String, content: String, timestamp: String, status: String ) -> Self
+    pub fn new( uid: u64, username: String, content: String, timestamp: DateTime<Utc>, status: String ) -> Self
     {
 
         Transaction
         {
             
-            uid: uid,
-            username: username,
-            content: content,
-            timestamp: timestamp,
-            status: status
+            uid,
+            username,
+            content,
+            timestamp,
+            legacy_timestamp: None,
+            status
                 
         }
         
     }
+
+    // Returns the timestamp as it is written, the legacy timestamp if the transaction has one
+    fn written_timestamp( &self ) -> String
+    {
+
+        match self.legacy_timestamp
+        {
+
+            Some( ref legacy ) => legacy.clone(),
+            None => self.timestamp.to_string()
+
+        }
+
+    }
     
-    // Write the transaction to a file
+    // Write the transaction to a file, appending it to a file already there
     #[allow(dead_code)]
     pub fn write_to( &self, filename: &str ) -> Result< (), Error >
     {
@@ -70,13 +185,22 @@ impl Transaction
         // Open the filepath with append specification
         let mut file = OpenOptions::new(  ).append( true ).create( true ).open( filename )?;
         // Write the json to the filepath
-        #[allow(unused_variables)]
-        let temp = file.write_all( serde_json::to_string( &self )?.as_ref() );
+        file.write_all( serde_json::to_string( &self )?.as_ref() )?;
         // Return the result 
         Ok( () )
         
     }
 
+    // Write the transaction to a file, a file already there is replaced whole or left as it was
+    #[allow(dead_code)]
+    pub fn replace( &self, filename: &str ) -> Result< (), Error >
+    {
+
+        // Replace the file with the json atomically
+        file::write_atomically( filename, serde_json::to_string( &self )?.as_ref() )
+        
+    }
+
     // Read in from json 
     #[allow(dead_code)]
     pub fn read_json( filename: &str ) -> Result< String, Error >
@@ -86,8 +210,7 @@ impl Transaction
         let mut file = OpenOptions::new( ).read( true ).open( filename )?;
         // Read in json
         let mut json = String::new();
-        #[allow(unused_variables)]
-        let temp = file.read_to_string( &mut json );
+        file.read_to_string( &mut json )?;
         // Return the string
         Ok( json )
         
@@ -99,13 +222,161 @@ impl Transaction
     {
 
         // Construct the transaction
-        let string = Transaction::read_json( filename ).expect( "Could not read json" );
-        let transaction : Transaction = serde_json::from_str( string.as_ref() ).expect( "Could not convert to transaction" );
+        let string = Transaction::read_json( filename )?;
+        let transaction : Transaction = serde_json::from_str( string.as_ref() )?;
         // Return the transaction
         Ok( transaction )
         
     }
 
+    /*
+     *
+     * Canonical encoding:
+     *     - Every field of the transaction in a fixed order, so the same transaction always
+     *       encodes to the same bytes ( unlike its json, which a serializer is free to format ).
+     *
+     *     - uid: 8 bytes, big endian
+     *     - username, content, timestamp, status: each as a 4 byte big endian length
+     *       followed by its UTF-8 bytes, the lengths keep one field from running into the next
+     *       ( the timestamp is written in its display format, or as its legacy string )
+     *
+     */
+    #[allow(dead_code)]
+    pub fn encode( &self ) -> Vec<u8>
+    {
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice( &self.uid.to_be_bytes() );
+        for field in &[ &self.username, &self.content, &self.written_timestamp(), &self.status ]
+        {
+
+            bytes.extend_from_slice( &( field.len() as u32 ).to_be_bytes() );
+            bytes.extend_from_slice( field.as_bytes() );
+            
+        }
+        bytes
+        
+    }
+
+    // Constructs a transaction from its canonical encoding, None if the bytes aren't exactly
+    // one encoded transaction
+    #[allow(dead_code)]
+    pub fn decode( bytes: &[u8] ) -> Option<Transaction>
+    {
+
+        if bytes.len() < 8
+        {
+
+            return None;
+            
+        }
+        let ( uid, mut rest ) = bytes.split_at( 8 );
+        let mut uid_bytes = [ 0; 8 ];
+        uid_bytes.copy_from_slice( uid );
+        // Reads the four length prefixed fields in order
+        let mut fields = Vec::new();
+        for _ in 0 .. 4
+        {
+
+            if rest.len() < 4
+            {
+
+                return None;
+                
+            }
+            let mut length = [ 0; 4 ];
+            length.copy_from_slice( &rest[ .. 4 ] );
+            let length = u32::from_be_bytes( length ) as usize;
+            if rest.len() - 4 < length
+            {
+
+                return None;
+                
+            }
+            fields.push( String::from_utf8( rest[ 4 .. 4 + length ].to_vec() ).ok()? );
+            rest = &rest[ 4 + length .. ];
+            
+        }
+        if !rest.is_empty()
+        {
+
+            return None;
+            
+        }
+        let status = fields.pop()?;
+        let ( timestamp, legacy_timestamp ) = read_timestamp( fields.pop()? );
+        let content = fields.pop()?;
+        let username = fields.pop()?;
+        let mut transaction = Transaction::new( u64::from_be_bytes( uid_bytes ), username, content, timestamp, status );
+        transaction.legacy_timestamp = legacy_timestamp;
+        Some( transaction )
+        
+    }
+
+    // Returns the transaction's id, the hash of its canonical encoding 
+    #[allow(dead_code)]
+    pub fn id( &self ) -> Digest
+    {
+
+        create_transaction_id( &self.encode() )
+        
+    }
+
+    // Returns the transaction's id hashed with the given algorithm
+    #[allow(dead_code)]
+    pub fn id_with( &self, algorithm: HashAlgorithm ) -> Digest
+    {
+
+        algorithm.transaction_id( &self.encode() )
+        
+    }
+
+    // Returns the transaction's uid
+    #[allow(dead_code)]
+    pub fn uid( &self ) -> u64
+    {
+
+        self.uid
+        
+    }
+
+    // Returns the transaction's username
+    #[allow(dead_code)]
+    pub fn username( &self ) -> &String
+    {
+
+        &self.username
+        
+    }
+
+    // Returns the transaction's timestamp
+    #[allow(dead_code)]
+    pub fn timestamp( &self ) -> &DateTime<Utc>
+    {
+
+        &self.timestamp
+        
+    }
+
+    // Returns the original timestamp of a transaction written before timestamps were times,
+    // None if its timestamp is a time written in the display format
+    #[allow(dead_code)]
+    pub fn legacy_timestamp( &self ) -> Option<&String>
+    {
+
+        self.legacy_timestamp.as_ref()
+        
+    }
+
+    // Returns the transaction's verification status
+    #[allow(dead_code)]
+    pub fn status( &self ) -> &String
+    {
+
+        &self.status
+        
+    }
+
     // Returns the transactions value
     #[allow(dead_code)]
     pub fn get_value( &self ) -> &String
@@ -136,7 +407,8 @@ pub fn dummy() -> Transaction
         uid: 5,
         username: "name".to_string(),
         content: "hello".to_string(),
-        timestamp: "now".to_string(),
+        timestamp: DateTime::UNIX_EPOCH,
+        legacy_timestamp: None,
         status: "unverified".to_string()
             
     }
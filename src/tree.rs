@@ -15,6 +15,147 @@ use transaction::*;
  *
  */
 
+/*
+ *
+ * Tree Version:
+ *     - The format used for hashing the leaves and nodes of a tree. Versions are kept so
+ *       roots built with an older format can still be rebuilt and verified.
+ *
+ *     - V0: leaves hash the value and nodes hash the concatenation of their children's hex
+ *           hashes with nothing separating the two, so the two child hashes of a node can
+ *           be presented as the value of a leaf ( second preimage attack ).
+ *     - V1: leaves are prefixed with 0x00 and nodes with 0x01 ( RFC 6962 ) and nodes hash
+ *           the bytes of their children's digests.
+ *     - V2: hashed like V1, but a node left without a partner at the end of a level is
+ *           promoted to the level above unchanged. V0 and V1 pair it with itself, which
+ *           gives [ a, b, c ] and [ a, b, c, c ] the same root ( CVE-2012-2459 ).
+ *     - V3: built like V2, but leaves hash the transaction's id rather than its content, so
+ *           the uid, username, timestamp and status of a transaction are committed to as well.
+ *
+ */
+#[derive( Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize )]
+pub enum TreeVersion
+{
+
+    // Unprefixed hashing 
+    V0,
+    // Domain separated hashing
+    V1,
+    // Domain separated hashing with unpaired nodes promoted
+    V2,
+    // Leaves committing to the transaction id
+    V3
+
+}
+
+// Tree Version impl 
+impl TreeVersion
+{
+
+    // The version new trees are built with
+    pub fn current() -> TreeVersion
+    {
+
+        TreeVersion::V3
+        
+    }
+
+    // The version of trees serialized before versions were recorded
+    pub fn legacy() -> TreeVersion
+    {
+
+        TreeVersion::V0
+        
+    }
+
+    // Hashes a leaf with this version and the default hash algorithm
+    pub fn leaf_hash( &self, value: &Transaction ) -> Digest
+    {
+
+        self.leaf_hash_with( HashAlgorithm::default(), value )
+        
+    }
+
+    // Hashes a leaf with this version and the given hash algorithm
+    pub fn leaf_hash_with( &self, algorithm: HashAlgorithm, value: &Transaction ) -> Digest
+    {
+
+        match *self
+        {
+
+            TreeVersion::V0 => algorithm.leaf_hash( value.get_value() ),
+            TreeVersion::V1 | TreeVersion::V2 => algorithm.prefixed_leaf_hash( value.get_value() ),
+            TreeVersion::V3 => algorithm.id_leaf_hash( &value.id_with( algorithm ) )
+                
+        }
+        
+    }
+
+    // Hashes a node from its children's hashes with this version and the default hash algorithm
+    pub fn node_hash( &self, left: &Digest, right: &Digest ) -> Digest
+    {
+
+        self.node_hash_with( HashAlgorithm::default(), left, right )
+        
+    }
+
+    // Hashes a node from its children's hashes with this version and the given hash algorithm
+    pub fn node_hash_with( &self, algorithm: HashAlgorithm, left: &Digest, right: &Digest ) -> Digest
+    {
+
+        match *self
+        {
+
+            TreeVersion::V0 => algorithm.node_hash( left, right ),
+            TreeVersion::V1 | TreeVersion::V2 | TreeVersion::V3 => algorithm.prefixed_node_hash( left, right )
+                
+        }
+        
+    }
+
+    // The byte the version is encoded as
+    pub fn to_byte( &self ) -> u8
+    {
+
+        match *self
+        {
+
+            TreeVersion::V0 => 0,
+            TreeVersion::V1 => 1,
+            TreeVersion::V2 => 2,
+            TreeVersion::V3 => 3
+                
+        }
+        
+    }
+
+    // The version encoded as the given byte, if there is one
+    pub fn from_byte( byte: u8 ) -> Option<TreeVersion>
+    {
+
+        match byte
+        {
+
+            0 => Some( TreeVersion::V0 ),
+            1 => Some( TreeVersion::V1 ),
+            2 => Some( TreeVersion::V2 ),
+            3 => Some( TreeVersion::V3 ),
+            _ => None
+                
+        }
+        
+    }
+
+    // Whether a node without a partner is promoted unchanged ( rather than paired with itself )
+    pub fn promotes_unpaired( &self ) -> bool
+    {
+
+        *self == TreeVersion::V2 || *self == TreeVersion::V3
+        
+    }
+    
+}
+
 /* Enum for the tree used for allowing multiple classifications of trees:
  *
  *     - An empty tree that contains only a hash
@@ -32,7 +173,7 @@ pub enum Tree
     {
         
         // Empty trees only contain a hash
-        hash: String
+        hash: Digest
 
     },
     // Leaf definition
@@ -40,7 +181,7 @@ pub enum Tree
     {
 
         // Leaves act as a node with a hash and value but no children
-        hash: String,
+        hash: Digest,
         value: Transaction 
 
     },
@@ -54,7 +195,7 @@ pub enum Tree
     {
 
         // Nodes have a hash and left and right children 
-        hash: String,
+        hash: Digest,
         left: Box<Tree>,
         right: Box<Tree>
 
@@ -76,34 +217,67 @@ impl Tree
 
     }
     
-    // Leaf node constructor
+    // Leaf node constructor, hashes with the current tree version
     #[allow(dead_code)]
     pub fn leaf( value: Transaction ) -> Tree
     {
 
-        // Creates the hash given the leaf's value, create_leaf_hash() takes in
-        // a reference to the value so we pass in value.as_ref()
-        let leaf_hash = create_leaf_hash( &value.get_value() );
+        Tree::versioned_leaf( TreeVersion::current(), value )
+        
+    }
+
+    // Leaf node constructor for a given tree version
+    #[allow(dead_code)]
+    pub fn versioned_leaf( version: TreeVersion, value: Transaction ) -> Tree
+    {
+
+        Tree::hashed_leaf( version, HashAlgorithm::default(), value )
+        
+    }
+
+    // Leaf node constructor for a given tree version and hash algorithm
+    #[allow(dead_code)]
+    pub fn hashed_leaf( version: TreeVersion, algorithm: HashAlgorithm, value: Transaction ) -> Tree
+    {
+
+        // Creates the hash given the leaf's value with the version's leaf hashing
+        let leaf_hash = version.leaf_hash_with( algorithm, &value );
         // Returns a tree leaf with the given hash and value
         Tree::Leaf
         {
 
             hash: leaf_hash,
-            value: value
+            value
 
         }
         
     }
     
-    // Tree node constructor
+    // Tree node constructor, hashes with the current tree version
     #[allow(dead_code)]
     pub fn node( left: Tree, right: Tree ) -> Tree
     {
 
-        // Creates the node hash using the children's hashes.
-        // Passes in the reference to the left and write hashes by
-        // using unwrap()
-        let node_hash = create_node_hash( left.hash(), right.hash() );
+        Tree::versioned_node( TreeVersion::current(), left, right )
+        
+    }
+    
+    // Tree node constructor for a given tree version
+    #[allow(dead_code)]
+    pub fn versioned_node( version: TreeVersion, left: Tree, right: Tree ) -> Tree
+    {
+
+        Tree::hashed_node( version, HashAlgorithm::default(), left, right )
+        
+    }
+    
+    // Tree node constructor for a given tree version and hash algorithm
+    #[allow(dead_code)]
+    pub fn hashed_node( version: TreeVersion, algorithm: HashAlgorithm, left: Tree, right: Tree ) -> Tree
+    {
+
+        // Creates the node hash using the children's hashes with the version's node hashing 
+        let node_hash = version.node_hash_with( algorithm, left.hash(), right.hash() );
         // Returns a tree node with the given hash and
         // allocates memory for the left and right children 
         Tree::Node
@@ -119,7 +293,7 @@ impl Tree
     
     // Retrieve the hash of a given tree
     #[allow(dead_code)]
-    pub fn hash( &self ) -> &String
+    pub fn hash( &self ) -> &Digest
     {
 
         // Includes tree
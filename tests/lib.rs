@@ -2,8 +2,8 @@ extern crate off_blockway;
 
 
 use off_blockway::block::*;
-use off_blockway::hash_util::*;
 use off_blockway::chain::*;
+use off_blockway::transaction::*;
 
 // Integration tests for the blockchain
 #[test]
@@ -12,12 +12,12 @@ fn it_works()
 
     let mut chain = Chain::new();
     println!("{:?}", chain.len() );
-    chain.push( Block::new( 0, empty_hash() ) );
-    chain.push( Block::new( 0, empty_hash() ) );
-    chain.push( Block::new( 0, empty_hash() ) );
-    chain.push( Block::new( 0, empty_hash() ) );
+    chain.push( Block::new( 0, vec![ dummy() ] ) ).unwrap();
+    chain.push( Block::new( 0, vec![ dummy() ] ) ).unwrap();
+    chain.push( Block::new( 0, vec![ dummy() ] ) ).unwrap();
+    chain.push( Block::new( 0, vec![ dummy() ] ) ).unwrap();
     println!("{:?}", chain.len() );
 
     
-    assert!( true );
+    assert_eq!( 5, chain.len() );
 }